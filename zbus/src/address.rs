@@ -9,6 +9,8 @@
 //!
 //! [Server addresses]: https://dbus.freedesktop.org/doc/dbus-specification.html#addresses
 
+#[cfg(unix)]
+use crate::connection::socket::UnixExecStream;
 #[cfg(target_os = "macos")]
 use crate::process::run;
 #[cfg(windows)]
@@ -39,6 +41,7 @@ use std::{
     fmt::{Display, Formatter},
     str::from_utf8_unchecked,
 };
+use tracing::debug;
 
 /// A `tcp:` address family.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -149,6 +152,111 @@ impl VsockAddress {
     }
 }
 
+/// A `unixexec:` D-Bus address.
+///
+/// The process at [`UnixExecAddress::path`] is spawned and D-Bus messages are exchanged over its
+/// standard input and output.
+#[cfg(unix)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnixExecAddress {
+    pub(crate) path: OsString,
+    pub(crate) argv0: Option<OsString>,
+    pub(crate) args: Vec<OsString>,
+}
+
+#[cfg(unix)]
+impl UnixExecAddress {
+    /// Create a new `unixexec:` address, running `path` with `args` as its arguments.
+    ///
+    /// The `argv[0]` of the process is `path`, unless set through
+    /// [`UnixExecAddress::set_argv0`].
+    pub fn new<P, A>(path: P, args: A) -> Self
+    where
+        P: Into<OsString>,
+        A: IntoIterator,
+        A::Item: Into<OsString>,
+    {
+        Self {
+            path: path.into(),
+            argv0: None,
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Set the `argv[0]` of the process.
+    pub fn set_argv0<A: Into<OsString>>(mut self, argv0: A) -> Self {
+        self.argv0 = Some(argv0.into());
+
+        self
+    }
+
+    /// Returns the `unixexec:` address `path` value, i.e the program to run.
+    pub fn path(&self) -> &std::ffi::OsStr {
+        &self.path
+    }
+
+    /// Returns the `unixexec:` address `argv0` value, if set.
+    pub fn argv0(&self) -> Option<&std::ffi::OsStr> {
+        self.argv0.as_deref()
+    }
+
+    /// Returns the arguments of the process, i.e the `argv1`, `argv2`.. values of the address.
+    pub fn args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    // Helper for FromStr
+    fn from_unixexec(opts: HashMap<&str, &str>) -> Result<Self> {
+        use std::os::unix::ffi::OsStringExt;
+
+        let decode = |value: &str| decode_percents(value).map(OsString::from_vec);
+        let path = opts
+            .get("path")
+            .ok_or_else(|| Error::Address("unixexec address is missing `path`".into()))?;
+        let argv0 = opts.get("argv0").map(|argv0| decode(argv0)).transpose()?;
+
+        let mut args = Vec::new();
+        let mut argc = 0;
+        for key in opts.keys() {
+            if let Some(n) = key.strip_prefix("argv") {
+                let n = n
+                    .parse::<usize>()
+                    .map_err(|_| Error::Address(format!("invalid unixexec key `{key}`")))?;
+                argc = argc.max(n);
+            }
+        }
+        for n in 1..=argc {
+            let arg = opts
+                .get(format!("argv{n}").as_str())
+                .ok_or_else(|| Error::Address(format!("unixexec address is missing `argv{n}`")))?;
+            args.push(decode(arg)?);
+        }
+
+        Ok(Self {
+            path: decode(path)?,
+            argv0,
+            args,
+        })
+    }
+
+    fn write_options(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use std::os::unix::ffi::OsStrExt;
+
+        f.write_str("path=")?;
+        encode_percents(f, self.path.as_bytes())?;
+        if let Some(argv0) = &self.argv0 {
+            f.write_str(",argv0=")?;
+            encode_percents(f, argv0.as_bytes())?;
+        }
+        for (i, arg) in self.args.iter().enumerate() {
+            write!(f, ",argv{}=", i + 1)?;
+            encode_percents(f, arg.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
 /// A bus address
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
@@ -190,12 +298,19 @@ pub enum Address {
     ///
     /// This address is mostly relevant to server (typically bus broker) implementations.
     UnixTmpDir(OsString),
+    /// A program to spawn, communicating over its standard input and output.
+    ///
+    /// This variant is only available on unix.
+    #[cfg(unix)]
+    UnixExec(UnixExecAddress),
 }
 
 #[cfg(not(feature = "tokio"))]
 #[derive(Debug)]
 pub(crate) enum Stream {
     Unix(Async<UnixStream>),
+    #[cfg(unix)]
+    UnixExec(UnixExecStream),
     Tcp(Async<TcpStream>),
     #[cfg(feature = "vsock")]
     Vsock(Async<VsockStream>),
@@ -206,6 +321,8 @@ pub(crate) enum Stream {
 pub(crate) enum Stream {
     #[cfg(unix)]
     Unix(UnixStream),
+    #[cfg(unix)]
+    UnixExec(UnixExecStream),
     Tcp(TcpStream),
     #[cfg(feature = "tokio-vsock")]
     Vsock(VsockStream),
@@ -397,57 +514,40 @@ impl Address {
                 // you can't connect to a unix:dir
                 Err(Error::Unsupported)
             }
+
+            #[cfg(unix)]
+            Address::UnixExec(addr) => UnixExecStream::spawn(&addr)
+                .map(Stream::UnixExec)
+                .map_err(|e| Error::InputOutput(e.into())),
         }
     }
 
     /// Get the address for session socket respecting the DBUS_SESSION_BUS_ADDRESS environment
     /// variable. If we don't recognize the value (or it's not set) we fall back to
     /// $XDG_RUNTIME_DIR/bus
+    ///
+    /// If the variable contains a list of addresses, this is the first one. Use
+    /// [`AddressList::session`] to get all of them.
     pub fn session() -> Result<Self> {
-        match env::var("DBUS_SESSION_BUS_ADDRESS") {
-            Ok(val) => Self::from_str(&val),
-            _ => {
-                #[cfg(windows)]
-                {
-                    #[cfg(feature = "windows-gdbus")]
-                    return Self::from_str("autolaunch:");
-
-                    #[cfg(not(feature = "windows-gdbus"))]
-                    return Self::from_str("autolaunch:scope=*user");
-                }
-
-                #[cfg(all(unix, not(target_os = "macos")))]
-                {
-                    let runtime_dir = env::var("XDG_RUNTIME_DIR")
-                        .unwrap_or_else(|_| format!("/run/user/{}", Uid::effective()));
-                    let path = format!("unix:path={runtime_dir}/bus");
-
-                    Self::from_str(&path)
-                }
-
-                #[cfg(target_os = "macos")]
-                return Self::from_str("launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET");
-            }
-        }
+        AddressList::session().map(AddressList::into_first)
     }
 
     /// Get the address for system bus respecting the DBUS_SYSTEM_BUS_ADDRESS environment
     /// variable. If we don't recognize the value (or it's not set) we fall back to
     /// /var/run/dbus/system_bus_socket
+    ///
+    /// If the variable contains a list of addresses, this is the first one. Use
+    /// [`AddressList::system`] to get all of them.
     pub fn system() -> Result<Self> {
-        match env::var("DBUS_SYSTEM_BUS_ADDRESS") {
-            Ok(val) => Self::from_str(&val),
-            _ => {
-                #[cfg(all(unix, not(target_os = "macos")))]
-                return Self::from_str("unix:path=/var/run/dbus/system_bus_socket");
-
-                #[cfg(windows)]
-                return Self::from_str("autolaunch:");
+        AddressList::system().map(AddressList::into_first)
+    }
 
-                #[cfg(target_os = "macos")]
-                return Self::from_str("launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET");
-            }
-        }
+    /// Get the address of the bus that started this process.
+    ///
+    /// If the bus address contains a list of addresses, this is the first one. Use
+    /// [`AddressList::starter`] for details and to get all of them.
+    pub fn starter() -> Result<Self> {
+        AddressList::starter().map(AddressList::into_first)
     }
 
     // Helper for FromStr
@@ -683,6 +783,12 @@ impl Display for Address {
             Self::Launchd(env) => {
                 write!(f, "launchd:env={}", env)?;
             }
+
+            #[cfg(unix)]
+            Self::UnixExec(addr) => {
+                f.write_str("unixexec:")?;
+                addr.write_options(f)?;
+            }
         }
 
         Ok(())
@@ -743,6 +849,8 @@ impl FromStr for Address {
                     })
                     .transpose()?,
             )),
+            #[cfg(unix)]
+            "unixexec" => UnixExecAddress::from_unixexec(options).map(Self::UnixExec),
             "launchd" => Ok(Self::Launchd(
                 options
                     .get("env")
@@ -765,9 +873,218 @@ impl TryFrom<&str> for Address {
     }
 }
 
+/// A list of D-Bus addresses, to be tried in order.
+///
+/// In its string form, the addresses are separated by `;`. This is the form of the
+/// `DBUS_SESSION_BUS_ADDRESS` and `DBUS_SYSTEM_BUS_ADDRESS` environment variables.
+///
+/// A list always contains at least one address.
+///
+/// # Example
+///
+/// ```
+/// use zbus::{address::AddressList, Address};
+///
+/// let list: AddressList = "unix:path=/run/bus;tcp:host=localhost,port=4142".parse().unwrap();
+/// let addresses: Vec<_> = list.iter().map(Address::to_string).collect();
+/// assert_eq!(addresses, ["unix:path=/run/bus", "tcp:host=localhost,port=4142"]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddressList(Vec<Address>);
+
+impl AddressList {
+    /// Get the addresses of the session bus, respecting the DBUS_SESSION_BUS_ADDRESS environment
+    /// variable. If we don't recognize the value (or it's not set) we fall back to
+    /// $XDG_RUNTIME_DIR/bus
+    pub fn session() -> Result<Self> {
+        match env::var("DBUS_SESSION_BUS_ADDRESS") {
+            Ok(val) => Self::from_str(&val),
+            _ => {
+                #[cfg(windows)]
+                {
+                    #[cfg(feature = "windows-gdbus")]
+                    return Self::from_str("autolaunch:");
+
+                    #[cfg(not(feature = "windows-gdbus"))]
+                    return Self::from_str("autolaunch:scope=*user");
+                }
+
+                #[cfg(all(unix, not(target_os = "macos")))]
+                {
+                    let runtime_dir = env::var("XDG_RUNTIME_DIR")
+                        .unwrap_or_else(|_| format!("/run/user/{}", Uid::effective()));
+                    let path = format!("unix:path={runtime_dir}/bus");
+
+                    Self::from_str(&path)
+                }
+
+                #[cfg(target_os = "macos")]
+                return Self::from_str("launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET");
+            }
+        }
+    }
+
+    /// Get the addresses of the system bus, respecting the DBUS_SYSTEM_BUS_ADDRESS environment
+    /// variable. If we don't recognize the value (or it's not set) we fall back to
+    /// /var/run/dbus/system_bus_socket
+    pub fn system() -> Result<Self> {
+        match env::var("DBUS_SYSTEM_BUS_ADDRESS") {
+            Ok(val) => Self::from_str(&val),
+            _ => {
+                #[cfg(all(unix, not(target_os = "macos")))]
+                return Self::from_str("unix:path=/var/run/dbus/system_bus_socket");
+
+                #[cfg(windows)]
+                return Self::from_str("autolaunch:");
+
+                #[cfg(target_os = "macos")]
+                return Self::from_str("launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET");
+            }
+        }
+    }
+
+    /// Get the addresses of the bus that started this process.
+    ///
+    /// When a service is activated by a message bus, the bus tells it its address through the
+    /// `DBUS_STARTER_ADDRESS` environment variable and its kind through `DBUS_STARTER_BUS_TYPE`.
+    /// The former is used if set. Otherwise, the addresses of the session or system bus are
+    /// returned, depending on the latter.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Address`] if neither variable is set (i-e the process wasn't started by a bus) or
+    /// if `DBUS_STARTER_BUS_TYPE` is neither `session` nor `system`.
+    pub fn starter() -> Result<Self> {
+        Self::starter_from(|var| env::var(var).ok())
+    }
+
+    fn starter_from<V>(var: V) -> Result<Self>
+    where
+        V: Fn(&str) -> Option<String>,
+    {
+        if let Some(address) = var("DBUS_STARTER_ADDRESS") {
+            return Self::from_str(&address);
+        }
+
+        match var("DBUS_STARTER_BUS_TYPE").as_deref() {
+            Some("session") => Self::session(),
+            Some("system") => Self::system(),
+            Some(bus_type) => Err(Error::Address(format!(
+                "unknown bus type `{bus_type}` in DBUS_STARTER_BUS_TYPE"
+            ))),
+            None => Err(Error::Address(
+                "neither DBUS_STARTER_ADDRESS nor DBUS_STARTER_BUS_TYPE is set".into(),
+            )),
+        }
+    }
+
+    /// The addresses, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &Address> {
+        self.0.iter()
+    }
+
+    /// The first address of the list.
+    pub fn first(&self) -> &Address {
+        // SAFETY: A list is never empty.
+        &self.0[0]
+    }
+
+    fn into_first(self) -> Address {
+        // SAFETY: A list is never empty.
+        self.0.into_iter().next().unwrap()
+    }
+
+    /// Connect to the first address that accepts the connection.
+    ///
+    /// If none does, the error of the only address or, for longer lists, an
+    /// [`Error::Address`] listing the errors of each address is returned.
+    pub(crate) async fn connect(self) -> Result<(Address, Stream)> {
+        if self.0.len() == 1 {
+            let address = self.into_first();
+            let stream = address.clone().connect().await?;
+
+            return Ok((address, stream));
+        }
+
+        let mut errors = Vec::with_capacity(self.0.len());
+        for address in self.0 {
+            match address.clone().connect().await {
+                Ok(stream) => return Ok((address, stream)),
+                Err(e) => {
+                    debug!("Failed to connect to `{address}`: {e}");
+                    errors.push(format!("`{address}`: {e}"));
+                }
+            }
+        }
+
+        Err(Error::Address(format!(
+            "Failed to connect to any address: {}",
+            errors.join("; ")
+        )))
+    }
+}
+
+impl Display for AddressList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, address) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(";")?;
+            }
+            write!(f, "{address}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for AddressList {
+    type Err = Error;
+
+    /// Parse a `;`-separated list of D-Bus addresses.
+    ///
+    /// Empty entries (e.g due to a trailing `;`) are ignored.
+    fn from_str(addresses: &str) -> Result<Self> {
+        let addresses = addresses
+            .split(';')
+            .filter(|address| !address.is_empty())
+            .map(Address::from_str)
+            .collect::<Result<Vec<_>>>()?;
+        if addresses.is_empty() {
+            return Err(Error::Address("empty address list".to_owned()));
+        }
+
+        Ok(Self(addresses))
+    }
+}
+
+impl TryFrom<&str> for AddressList {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        Self::from_str(value)
+    }
+}
+
+impl From<Address> for AddressList {
+    fn from(address: Address) -> Self {
+        Self(vec![address])
+    }
+}
+
+impl<'a> IntoIterator for &'a AddressList {
+    type Item = &'a Address;
+    type IntoIter = std::slice::Iter<'a, Address>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Address, TcpAddress, TcpAddressFamily};
+    #[cfg(unix)]
+    use super::UnixExecAddress;
+    use super::{Address, AddressList, TcpAddress, TcpAddressFamily};
     use crate::Error;
     use std::str::FromStr;
     use test_log::test;
@@ -905,6 +1222,36 @@ mod tests {
             Address::UnixTmpDir("/some/dir".into()),
             Address::from_str("unix:tmpdir=/some/dir").unwrap()
         );
+
+        #[cfg(unix)]
+        {
+            assert_eq!(
+                Address::UnixExec(
+                    UnixExecAddress::new("/usr/bin/ssh", ["host", "dbus stdio bridge"])
+                        .set_argv0("ssh")
+                ),
+                Address::from_str(
+                    "unixexec:path=/usr/bin/ssh,argv0=ssh,argv2=dbus%20stdio%20bridge,argv1=host"
+                )
+                .unwrap()
+            );
+            assert_eq!(
+                Address::UnixExec(UnixExecAddress::new("/bin/bridge", Vec::<String>::new())),
+                Address::from_str("unixexec:path=/bin/bridge").unwrap()
+            );
+            match Address::from_str("unixexec:argv0=bridge").unwrap_err() {
+                Error::Address(e) => assert_eq!(e, "unixexec address is missing `path`"),
+                _ => panic!(),
+            }
+            match Address::from_str("unixexec:path=/bin/bridge,argv2=foo").unwrap_err() {
+                Error::Address(e) => assert_eq!(e, "unixexec address is missing `argv1`"),
+                _ => panic!(),
+            }
+            match Address::from_str("unixexec:path=/bin/bridge,argvfoo=foo").unwrap_err() {
+                Error::Address(e) => assert_eq!(e, "invalid unixexec key `argvfoo`"),
+                _ => panic!(),
+            }
+        }
     }
 
     #[test]
@@ -989,6 +1336,19 @@ mod tests {
             .to_string(),
             "vsock:cid=98,port=2934", // no support for guid= yet..
         );
+
+        #[cfg(unix)]
+        {
+            let addr = Address::UnixExec(
+                UnixExecAddress::new("/usr/bin/ssh", ["host", "dbus,stdio;bridge"])
+                    .set_argv0("ssh"),
+            );
+            assert_eq!(
+                addr.to_string(),
+                "unixexec:path=/usr/bin/ssh,argv0=ssh,argv1=host,argv2=dbus%2cstdio%3bbridge"
+            );
+            assert_eq!(Address::from_str(&addr.to_string()).unwrap(), addr);
+        }
     }
 
     #[test]
@@ -999,6 +1359,118 @@ mod tests {
         crate::utils::block_on(async { addr.connect().await }).unwrap();
     }
 
+    #[test]
+    fn parse_address_lists() {
+        let list =
+            AddressList::from_str("unix:path=/tmp/dbus-foo;tcp:host=localhost,port=4142;").unwrap();
+        assert_eq!(
+            list.iter().cloned().collect::<Vec<_>>(),
+            [
+                Address::Unix("/tmp/dbus-foo".into()),
+                Address::Tcp(TcpAddress {
+                    host: "localhost".into(),
+                    port: 4142,
+                    bind: None,
+                    family: None
+                }),
+            ]
+        );
+        assert_eq!(list.first(), &Address::Unix("/tmp/dbus-foo".into()));
+        assert_eq!(
+            list.to_string(),
+            "unix:path=/tmp/dbus-foo;tcp:host=localhost,port=4142"
+        );
+        assert_eq!(
+            AddressList::from(Address::Unix("/tmp/dbus-foo".into())),
+            AddressList::from_str("unix:path=/tmp/dbus-foo").unwrap()
+        );
+
+        match AddressList::from_str(";").unwrap_err() {
+            Error::Address(e) => assert_eq!(e, "empty address list"),
+            _ => panic!(),
+        }
+        match AddressList::from_str("unix:path=/tmp/dbus-foo;tcp:host=localhost").unwrap_err() {
+            Error::Address(e) => assert_eq!(e, "tcp address is missing `port`"),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn starter_address() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        let list = AddressList::starter_from(env(&[
+            (
+                "DBUS_STARTER_ADDRESS",
+                "unix:path=/tmp/dbus-foo;tcp:host=localhost,port=4142",
+            ),
+            ("DBUS_STARTER_BUS_TYPE", "system"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            list.to_string(),
+            "unix:path=/tmp/dbus-foo;tcp:host=localhost,port=4142"
+        );
+        assert_eq!(
+            AddressList::starter_from(env(&[("DBUS_STARTER_BUS_TYPE", "session")])).unwrap(),
+            AddressList::session().unwrap()
+        );
+        assert_eq!(
+            AddressList::starter_from(env(&[("DBUS_STARTER_BUS_TYPE", "system")])).unwrap(),
+            AddressList::system().unwrap()
+        );
+        match AddressList::starter_from(env(&[("DBUS_STARTER_BUS_TYPE", "foo")])).unwrap_err() {
+            Error::Address(e) => assert_eq!(e, "unknown bus type `foo` in DBUS_STARTER_BUS_TYPE"),
+            _ => panic!(),
+        }
+        match AddressList::starter_from(env(&[])).unwrap_err() {
+            Error::Address(e) => assert_eq!(
+                e,
+                "neither DBUS_STARTER_ADDRESS nor DBUS_STARTER_BUS_TYPE is set"
+            ),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn connect_address_list() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+
+        // The first address fails so the second one is used.
+        let list = AddressList::from_str(&format!(
+            "unix:path={};tcp:host=localhost,port={port}",
+            missing.display()
+        ))
+        .unwrap();
+        let (addr, _) = crate::utils::block_on(list.connect()).unwrap();
+        assert_eq!(addr.to_string(), format!("tcp:host=localhost,port={port}"));
+
+        // All the failures are reported.
+        drop(listener);
+        let list = AddressList::from_str(&format!(
+            "unix:path={0}-1;unix:path={0}-2",
+            missing.display()
+        ))
+        .unwrap();
+        match crate::utils::block_on(list.connect()).unwrap_err() {
+            Error::Address(e) => {
+                assert!(e.starts_with("Failed to connect to any address: "));
+                assert!(e.contains("missing-1`: "));
+                assert!(e.contains("missing-2`: "));
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
     #[test]
     fn connect_nonce_tcp() {
         struct PercentEncoded<'a>(&'a [u8]);
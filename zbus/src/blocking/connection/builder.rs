@@ -10,11 +10,13 @@ use tokio::net::UnixStream;
 #[cfg(windows)]
 use uds_windows::UnixStream;
 
+use std::{future::Future, time::Duration};
 use zvariant::{ObjectPath, Str};
 
 use crate::{
-    address::Address,
+    address::AddressList,
     blocking::Connection,
+    connection::{AuthenticatedPeer, Interceptor, ReconnectPolicy, SaslMechanism},
     names::{UniqueName, WellKnownName},
     object_server::Interface,
     utils::block_on,
@@ -24,7 +26,7 @@ use crate::{
 /// A builder for [`zbus::blocking::Connection`].
 #[derive(Debug)]
 #[must_use]
-pub struct Builder<'a>(crate::connection::Builder<'a>);
+pub struct Builder<'a>(pub(super) crate::connection::Builder<'a>);
 
 assert_impl_all!(Builder<'_>: Send, Sync, Unpin);
 
@@ -39,12 +41,19 @@ impl<'a> Builder<'a> {
         crate::connection::Builder::system().map(Self)
     }
 
+    /// Create a builder for the connection to the message bus that started this process.
+    ///
+    /// See [`zbus::connection::Builder::starter`] for details.
+    pub fn starter() -> Result<Self> {
+        crate::connection::Builder::starter().map(Self)
+    }
+
     /// Create a builder for connection that will use the given [D-Bus bus address].
     ///
     /// [D-Bus bus address]: https://dbus.freedesktop.org/doc/dbus-specification.html#addresses
     pub fn address<A>(address: A) -> Result<Self>
     where
-        A: TryInto<Address>,
+        A: TryInto<AddressList>,
         A::Error: Into<Error>,
     {
         crate::connection::Builder::address(address).map(Self)
@@ -73,6 +82,16 @@ impl<'a> Builder<'a> {
         Self(self.0.auth_mechanisms(auth_mechanisms))
     }
 
+    /// Register a custom [`SaslMechanism`] to use during authentication.
+    ///
+    /// See [`zbus::connection::Builder::sasl_mechanism`] for details.
+    pub fn sasl_mechanism<M>(self, mechanism: M) -> Self
+    where
+        M: SaslMechanism,
+    {
+        Self(self.0.sasl_mechanism(mechanism))
+    }
+
     /// The cookie context to use during authentication.
     ///
     /// This is only used when the `cookie` authentication mechanism is enabled and only valid for
@@ -113,6 +132,27 @@ impl<'a> Builder<'a> {
         Self(self.0.server(guid))
     }
 
+    /// Set the policy deciding which clients the to-be-created server connection accepts.
+    ///
+    /// See [`zbus::connection::Builder::peer_policy`] for details.
+    pub fn peer_policy<F, Fut>(self, policy: F) -> Self
+    where
+        F: Fn(AuthenticatedPeer) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        Self(self.0.peer_policy(policy))
+    }
+
+    /// Register an [`Interceptor`] on the to-be-created connection.
+    ///
+    /// See [`zbus::connection::Builder::interceptor`] for details.
+    pub fn interceptor<I>(self, interceptor: I) -> Self
+    where
+        I: Interceptor,
+    {
+        Self(self.0.interceptor(interceptor))
+    }
+
     /// Set the capacity of the main (unfiltered) queue.
     ///
     /// Since typically you'd want to set this at instantiation time, you can set it through the
@@ -136,6 +176,20 @@ impl<'a> Builder<'a> {
         Self(self.0.max_queued(max))
     }
 
+    /// Set the default timeout for method replies.
+    ///
+    /// See [`zbus::connection::Builder::method_timeout`] for details.
+    pub fn method_timeout(self, timeout: Duration) -> Self {
+        Self(self.0.method_timeout(timeout))
+    }
+
+    /// Re-establish the connection, following `policy`, whenever the socket is lost.
+    ///
+    /// See [`zbus::connection::Builder::reconnect`] for details.
+    pub fn reconnect(self, policy: ReconnectPolicy) -> Self {
+        Self(self.0.reconnect(policy))
+    }
+
     /// Register a D-Bus [`Interface`] to be served at a given path.
     ///
     /// This is similar to [`zbus::blocking::ObjectServer::at`], except that it allows you to have
@@ -3,16 +3,17 @@
 use enumflags2::BitFlags;
 use event_listener::EventListener;
 use static_assertions::assert_impl_all;
-use std::{io, ops::Deref};
+use std::{io, ops::Deref, time::Duration};
 use zbus_names::{BusName, ErrorName, InterfaceName, MemberName, OwnedUniqueName, WellKnownName};
 use zvariant::ObjectPath;
 
 use crate::{
     blocking::ObjectServer,
+    connection::{ConnectionState, ConnectionStats},
     fdo::{ConnectionCredentials, RequestNameFlags, RequestNameReply},
     message::Message,
     utils::block_on,
-    DBusError, Error, Result,
+    DBusError, Error, Guid, Result,
 };
 
 mod builder;
@@ -41,6 +42,35 @@ impl Connection {
         block_on(crate::Connection::system()).map(Self::from)
     }
 
+    /// Create a `Connection` to the message bus that started this process.
+    ///
+    /// See [`zbus::connection::Builder::starter`] for details.
+    pub fn starter() -> Result<Self> {
+        block_on(crate::Connection::starter()).map(Self::from)
+    }
+
+    /// Create a pair of peer-to-peer connections, connected to each other.
+    ///
+    /// See [`zbus::Connection::pair`] for details.
+    #[cfg(unix)]
+    pub fn pair() -> Result<(Self, Self)> {
+        Self::pair_with(Ok)
+    }
+
+    /// Create a pair of peer-to-peer connections, connected to each other.
+    ///
+    /// See [`zbus::Connection::pair_with`] for details.
+    #[cfg(unix)]
+    pub fn pair_with<F>(server: F) -> Result<(Self, Self)>
+    where
+        F: FnOnce(Builder<'static>) -> Result<Builder<'static>>,
+    {
+        let server = |builder| server(Builder(builder)).map(|builder| builder.0);
+
+        block_on(crate::Connection::pair_with(server))
+            .map(|(client, server)| (Self::from(client), Self::from(server)))
+    }
+
     /// The capacity of the main (unfiltered) queue.
     pub fn max_queued(&self) -> usize {
         self.inner.max_queued()
@@ -51,13 +81,23 @@ impl Connection {
         self.inner.set_max_queued(max)
     }
 
+    /// The default timeout for method replies.
+    pub fn method_timeout(&self) -> Option<Duration> {
+        self.inner.method_timeout()
+    }
+
     /// The server's GUID.
-    pub fn server_guid(&self) -> &str {
+    pub fn server_guid(&self) -> Guid {
         self.inner.server_guid()
     }
 
+    /// The current state of the connection.
+    pub fn state(&self) -> ConnectionState {
+        self.inner.state()
+    }
+
     /// The unique name as assigned by the message bus or `None` if not a message bus connection.
-    pub fn unique_name(&self) -> Option<&OwnedUniqueName> {
+    pub fn unique_name(&self) -> Option<OwnedUniqueName> {
         self.inner.unique_name()
     }
 
@@ -102,6 +142,40 @@ impl Connection {
         )
     }
 
+    /// Send a method call, failing with [`Error::Timeout`] if no reply is received within
+    /// `timeout`.
+    ///
+    /// See [`zbus::Connection::call_method_with_timeout`] for details.
+    pub fn call_method_with_timeout<'d, 'p, 'i, 'm, D, P, I, M, B>(
+        &self,
+        destination: Option<D>,
+        path: P,
+        iface: Option<I>,
+        method_name: M,
+        timeout: Duration,
+        body: &B,
+    ) -> Result<Message>
+    where
+        D: TryInto<BusName<'d>>,
+        P: TryInto<ObjectPath<'p>>,
+        I: TryInto<InterfaceName<'i>>,
+        M: TryInto<MemberName<'m>>,
+        D::Error: Into<Error>,
+        P::Error: Into<Error>,
+        I::Error: Into<Error>,
+        M::Error: Into<Error>,
+        B: serde::ser::Serialize + zvariant::DynamicType,
+    {
+        block_on(self.inner.call_method_with_timeout(
+            destination,
+            path,
+            iface,
+            method_name,
+            timeout,
+            body,
+        ))
+    }
+
     /// Emit a signal.
     ///
     /// Create a signal message, and send it over the connection.
@@ -252,11 +326,19 @@ impl Connection {
     ///
     /// # Caveats
     ///
-    /// Currently `unix_group_ids` and `linux_security_label` fields are not populated.
+    /// Currently `unix_group_ids` and `linux_security_label` fields are only populated on Linux
+    /// and Android.
     pub fn peer_credentials(&self) -> io::Result<ConnectionCredentials> {
         block_on(self.inner.peer_credentials())
     }
 
+    /// A snapshot of the statistics of the connection.
+    ///
+    /// See [`crate::Connection::stats`] for details.
+    pub fn stats(&self) -> ConnectionStats {
+        block_on(self.inner.stats())
+    }
+
     /// Close the connection.
     ///
     /// After this call, all reading and writing operations will fail.
@@ -276,7 +358,7 @@ mod tests {
     use ntest::timeout;
     #[cfg(all(unix, not(feature = "tokio")))]
     use std::os::unix::net::UnixStream;
-    use std::thread;
+    use std::{collections::HashMap, thread};
     use test_log::test;
     #[cfg(all(unix, feature = "tokio"))]
     use tokio::net::UnixStream;
@@ -284,9 +366,10 @@ mod tests {
     use uds_windows::UnixStream;
 
     use crate::{
-        blocking::{connection::Builder, MessageIterator},
-        Guid,
+        blocking::{connection::Builder, Connection, MessageIterator},
+        fdo, Guid,
     };
+    use zvariant::OwnedValue;
 
     #[test]
     #[timeout(15000)]
@@ -335,4 +418,25 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    #[timeout(15000)]
+    fn pair() {
+        let (client, server) =
+            Connection::pair_with(|server| server.serve_at("/org/zbus/Stats", fdo::Stats)).unwrap();
+        let reply = client
+            .call_method(
+                None::<()>,
+                "/org/zbus/Stats",
+                Some("org.freedesktop.DBus.Debug.Stats"),
+                "GetStats",
+                &(),
+            )
+            .unwrap();
+        let stats: HashMap<String, OwnedValue> = reply.body().deserialize().unwrap();
+        // The reply reports the call it answers.
+        assert_eq!(stats["IncomingMethodCalls"], OwnedValue::from(1u64));
+        assert_eq!(stats["OutgoingMessages"], OwnedValue::from(0u64));
+        assert_eq!(server.stats().received().messages(), 1);
+    }
 }
@@ -2,7 +2,8 @@ use futures_util::StreamExt;
 use static_assertions::assert_impl_all;
 
 use crate::{
-    blocking::Connection, message::Message, utils::block_on, MatchRule, OwnedMatchRule, Result,
+    blocking::Connection, message::Message, utils::block_on, MatchRule, OverflowPolicy,
+    OwnedMatchRule, Result,
 };
 
 /// A blocking wrapper of [`crate::MessageStream`].
@@ -101,6 +102,29 @@ impl MessageIterator {
         .map(|s| Self { azync: s })
     }
 
+    /// Create a message iterator for the given match rule, with the given [`OverflowPolicy`].
+    ///
+    /// This is a wrapper around [`crate::MessageStream::for_match_rule_with_overflow_policy`].
+    pub fn for_match_rule_with_overflow_policy<R>(
+        rule: R,
+        conn: &Connection,
+        max_queued: Option<usize>,
+        policy: OverflowPolicy,
+    ) -> Result<Self>
+    where
+        R: TryInto<OwnedMatchRule>,
+        R::Error: Into<crate::Error>,
+    {
+        block_on(crate::MessageStream::for_match_rule_with_overflow_policy(
+            rule,
+            conn.inner(),
+            max_queued,
+            policy,
+        ))
+        .map(Some)
+        .map(|s| Self { azync: s })
+    }
+
     /// The associated match rule, if any.
     pub fn match_rule(&self) -> Option<MatchRule<'_>> {
         self.azync
@@ -6,7 +6,9 @@ use static_assertions::assert_impl_all;
 use zvariant::ObjectPath;
 
 use crate::{
-    object_server::{Interface, InterfaceDeref, InterfaceDerefMut, SignalContext},
+    object_server::{
+        Authorizer, Interface, InterfaceDeref, InterfaceDerefMut, SignalContext, SubtreeHandler,
+    },
     utils::block_on,
     Error, Result,
 };
@@ -174,6 +176,44 @@ impl ObjectServer {
         block_on(self.azync.remove::<I, P>(path))
     }
 
+    /// Register a [`SubtreeHandler`] serving the objects below a given path.
+    ///
+    /// See [`crate::ObjectServer::at_subtree`] for details.
+    pub fn at_subtree<'p, P, H>(&self, path: P, handler: H) -> Result<bool>
+    where
+        H: SubtreeHandler,
+        P: TryInto<ObjectPath<'p>>,
+        P::Error: Into<Error>,
+    {
+        block_on(self.azync.at_subtree(path, handler))
+    }
+
+    /// Unregister the [`SubtreeHandler`] at a given path.
+    ///
+    /// Returns whether a handler was registered at this path.
+    pub fn remove_subtree<'p, P>(&self, path: P) -> Result<bool>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        P::Error: Into<Error>,
+    {
+        block_on(self.azync.remove_subtree(path))
+    }
+
+    /// Set the [`Authorizer`] checking the method calls before they're dispatched.
+    ///
+    /// See [`crate::ObjectServer::set_authorizer`] for details.
+    pub fn set_authorizer<A>(&self, authorizer: A)
+    where
+        A: Authorizer,
+    {
+        block_on(self.azync.set_authorizer(authorizer))
+    }
+
+    /// Remove the [`Authorizer`], if any.
+    pub fn remove_authorizer(&self) {
+        block_on(self.azync.remove_authorizer())
+    }
+
     /// Get the interface at the given path.
     ///
     /// # Errors
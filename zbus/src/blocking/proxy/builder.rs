@@ -1,4 +1,5 @@
 use static_assertions::assert_impl_all;
+use std::time::Duration;
 use zbus_names::{BusName, InterfaceName};
 use zvariant::ObjectPath;
 
@@ -52,6 +53,14 @@ impl<'a, T> Builder<'a, T> {
         Self(self.0.uncached_properties(properties))
     }
 
+    /// Set the timeout for method replies.
+    ///
+    /// See [`zbus::proxy::Builder::method_timeout`] for details.
+    #[must_use]
+    pub fn method_timeout(self, timeout: Duration) -> Self {
+        Self(self.0.method_timeout(timeout))
+    }
+
     /// Build a proxy from the builder.
     ///
     /// # Panics
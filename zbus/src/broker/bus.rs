@@ -0,0 +1,830 @@
+use async_broadcast::{broadcast, Receiver, Sender, TrySendError};
+use enumflags2::BitFlags;
+use futures_util::StreamExt;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Weak},
+};
+use tracing::{debug, instrument, trace, warn};
+use zbus_names::{
+    BusName, InterfaceName, MemberName, OwnedUniqueName, OwnedWellKnownName, UniqueName,
+    WellKnownName,
+};
+use zvariant::Value;
+
+use crate::{
+    async_lock::Mutex,
+    fdo::{self, ConnectionCredentials, ReleaseNameReply, RequestNameFlags, RequestNameReply},
+    message::{self, Body, Flags, Type},
+    Connection, DBusError, Executor, Guid, Message, MessageStream, OwnedMatchRule, Result, Task,
+};
+
+/// The name the broker itself owns on the bus.
+pub(super) const BUS_NAME: &str = "org.freedesktop.DBus";
+const BUS_PATH: &str = "/org/freedesktop/DBus";
+const BUS_INTERFACE: &str = "org.freedesktop.DBus";
+
+/// The maximum number of messages queued for delivery to a single peer.
+///
+/// Messages to a peer that doesn't read fast enough are dropped once this limit is reached, so that
+/// a single misbehaving peer can't stall the whole bus.
+const MAX_OUTGOING_QUEUED: usize = 1024;
+
+/// The routing state and the `org.freedesktop.DBus` implementation of a [`super::Broker`].
+#[derive(Debug)]
+pub(super) struct Bus {
+    guid: Guid,
+    executor: Executor<'static>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_peer_id: u64,
+    peers: HashMap<OwnedUniqueName, Peer>,
+    names: HashMap<OwnedWellKnownName, NameOwnership>,
+}
+
+#[derive(Debug)]
+struct Peer {
+    // Set on `Hello`. Until then, the peer can't send or receive anything else.
+    registered: bool,
+    credentials: ConnectionCredentials,
+    match_rules: Vec<OwnedMatchRule>,
+    outgoing: Sender<Message>,
+    reader_task: Option<Task<()>>,
+    _writer_task: Task<()>,
+}
+
+#[derive(Debug)]
+struct NameOwnership {
+    owner: OwnedUniqueName,
+    owner_flags: BitFlags<RequestNameFlags>,
+    queue: VecDeque<(OwnedUniqueName, BitFlags<RequestNameFlags>)>,
+}
+
+impl Bus {
+    pub(super) fn new(guid: Guid, executor: Executor<'static>) -> Self {
+        Self {
+            guid,
+            executor,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Start routing messages from and to the given (authenticated) connection.
+    pub(super) async fn add_peer(self: &Arc<Self>, conn: Connection, stream: MessageStream) {
+        let credentials = conn.peer_credentials().await.unwrap_or_default();
+        let (outgoing, outgoing_rx) = broadcast(MAX_OUTGOING_QUEUED);
+
+        let mut state = self.state.lock().await;
+        state.next_peer_id += 1;
+        let name = OwnedUniqueName::try_from(format!(":1.{}", state.next_peer_id))
+            .expect("invalid unique name");
+        trace!("New peer `{name}` connected");
+
+        let writer_task = self.executor.spawn(
+            write_messages(conn.clone(), outgoing_rx),
+            &format!("broker writer for {name}"),
+        );
+        let reader_task = self.executor.spawn(
+            read_messages(Arc::downgrade(self), name.clone(), stream),
+            &format!("broker reader for {name}"),
+        );
+        state.peers.insert(
+            name,
+            Peer {
+                registered: false,
+                credentials,
+                match_rules: vec![],
+                outgoing,
+                reader_task: Some(reader_task),
+                _writer_task: writer_task,
+            },
+        );
+    }
+
+    /// Stop routing messages from and to a peer and release everything it owned on the bus.
+    async fn remove_peer(&self, name: &UniqueName<'_>) {
+        let mut state = self.state.lock().await;
+        let mut peer = match state.peers.remove(name.as_str()) {
+            Some(peer) => peer,
+            None => return,
+        };
+        trace!("Peer `{name}` disconnected");
+        // This is called from the reader task itself so it shouldn't cancel itself.
+        if let Some(task) = peer.reader_task.take() {
+            task.detach();
+        }
+        if !peer.registered {
+            return;
+        }
+
+        let names: Vec<_> = state
+            .names
+            .iter()
+            .filter(|(_, n)| n.owner == *name || n.queue.iter().any(|(q, _)| *q == *name))
+            .map(|(n, _)| n.clone())
+            .collect();
+        for well_known_name in names {
+            state.release_name(name, &well_known_name);
+        }
+        state.emit_name_owner_changed(name.as_str(), Some(name), None);
+    }
+
+    /// Handle a message from the peer named `sender`.
+    ///
+    /// An error is returned if the peer violated the protocol and must be disconnected.
+    async fn handle_message(&self, sender: &UniqueName<'_>, msg: Message) -> Result<()> {
+        let mut state = self.state.lock().await;
+        let registered = match state.peers.get(sender.as_str()) {
+            Some(peer) => peer.registered,
+            None => return Ok(()),
+        };
+        let msg = with_sender(&msg, sender)?;
+        let hdr = msg.header();
+
+        if !registered {
+            if !is_bus_call(&msg, "Hello") {
+                return Err(crate::Error::Failure(
+                    "Client tried to send a message other than Hello without being registered"
+                        .into(),
+                ));
+            }
+            return state.hello(&msg);
+        }
+
+        match hdr.destination() {
+            Some(dest) if dest.as_str() == BUS_NAME => {
+                if msg.message_type() != Type::MethodCall {
+                    return Ok(());
+                }
+                let ret = state.call_bus_method(&msg, &self.guid);
+                if let Err(e) = ret {
+                    debug!("Error handling bus method call: {e}");
+                    let description = e.description().unwrap_or_default();
+                    state.reply_error(&msg, e.name().as_str(), description)?;
+                }
+            }
+            Some(dest) => match state.owner(dest) {
+                Some(owner) => state.deliver(&owner, &msg),
+                None if msg.message_type() == Type::MethodCall => {
+                    let (name, description) = match dest {
+                        BusName::Unique(_) => (
+                            "org.freedesktop.DBus.Error.NameHasNoOwner",
+                            format!("The name {dest} does not exist"),
+                        ),
+                        BusName::WellKnown(_) => (
+                            "org.freedesktop.DBus.Error.ServiceUnknown",
+                            format!("The name {dest} was not provided by any .service files"),
+                        ),
+                    };
+                    state.reply_error(&msg, name, &description)?;
+                }
+                None => trace!("Dropping message to non-existent destination `{dest}`"),
+            },
+            None => state.broadcast(&msg),
+        }
+
+        Ok(())
+    }
+}
+
+impl State {
+    fn hello(&mut self, call: &Message) -> Result<()> {
+        // SAFETY: The sender is always set by `Bus::handle_message` before we get here.
+        let name = call.header().sender().unwrap().to_owned();
+        if let Some(peer) = self.peers.get_mut(name.as_str()) {
+            peer.registered = true;
+        }
+        self.reply(call, &name.as_str())?;
+        self.emit_name_owner_changed(name.as_str(), None, Some(&name));
+        self.emit_bus_signal(Some(&name), "NameAcquired", &name.as_str())
+    }
+
+    fn call_bus_method(&mut self, call: &Message, guid: &Guid) -> fdo::Result<()> {
+        let hdr = call.header();
+        // SAFETY: The sender is always set by `Bus::handle_message` before we get here.
+        let sender = hdr.sender().unwrap().to_owned();
+        let member = hdr
+            .member()
+            .ok_or_else(|| fdo::Error::InvalidArgs("Missing member".into()))?;
+        let interface = hdr.interface().map(InterfaceName::as_str);
+        let body = call.body();
+
+        match (interface, member.as_str()) {
+            (Some(BUS_INTERFACE) | None, "Hello") => Err(fdo::Error::Failed(
+                "Already handled an Hello message".into(),
+            )),
+            (Some(BUS_INTERFACE) | None, "RequestName") => {
+                let (name, flags): (WellKnownName<'_>, BitFlags<RequestNameFlags>) = args(&body)?;
+                let reply = self.request_name(&sender, name, flags)?;
+                self.reply(call, &reply)
+            }
+            (Some(BUS_INTERFACE) | None, "ReleaseName") => {
+                let name: WellKnownName<'_> = args(&body)?;
+                let reply = self.release_name(&sender, &name);
+                self.reply(call, &reply)
+            }
+            (Some(BUS_INTERFACE) | None, "ListQueuedOwners") => {
+                let name: WellKnownName<'_> = args(&body)?;
+                let ownership = self.names.get(name.as_str()).ok_or_else(|| {
+                    fdo::Error::NameHasNoOwner(format!(
+                        "Could not get owners of name '{name}': no such name"
+                    ))
+                })?;
+                let owners: Vec<_> = std::iter::once(&ownership.owner)
+                    .chain(ownership.queue.iter().map(|(name, _)| name))
+                    .map(|name| name.as_str())
+                    .collect();
+                self.reply(call, &owners)
+            }
+            (Some(BUS_INTERFACE) | None, "ListNames") => {
+                let names: Vec<_> = std::iter::once(BUS_NAME)
+                    .chain(
+                        self.peers
+                            .iter()
+                            .filter(|(_, peer)| peer.registered)
+                            .map(|(name, _)| name.as_str()),
+                    )
+                    .chain(self.names.keys().map(|name| name.as_str()))
+                    .collect();
+                self.reply(call, &names)
+            }
+            (Some(BUS_INTERFACE) | None, "ListActivatableNames") => {
+                self.reply(call, &vec![BUS_NAME])
+            }
+            (Some(BUS_INTERFACE) | None, "NameHasOwner") => {
+                let name: BusName<'_> = args(&body)?;
+                let has_owner = self.owner(&name).is_some();
+                self.reply(call, &has_owner)
+            }
+            (Some(BUS_INTERFACE) | None, "GetNameOwner") => {
+                let name: BusName<'_> = args(&body)?;
+                let owner = self.owner(&name).ok_or_else(|| {
+                    fdo::Error::NameHasNoOwner(format!(
+                        "Could not get owner of name '{name}': no such name"
+                    ))
+                })?;
+                self.reply(call, &owner.as_str())
+            }
+            (Some(BUS_INTERFACE) | None, "StartServiceByName") => {
+                let (name, _flags): (WellKnownName<'_>, u32) = args(&body)?;
+                if self.names.contains_key(name.as_str()) {
+                    // DBUS_START_REPLY_ALREADY_RUNNING
+                    self.reply(call, &2u32)
+                } else {
+                    Err(fdo::Error::ServiceUnknown(format!(
+                        "The name {name} was not provided by any .service files"
+                    )))
+                }
+            }
+            (Some(BUS_INTERFACE) | None, "GetConnectionUnixUser") => {
+                let name: BusName<'_> = args(&body)?;
+                let uid = self.credentials(&name)?.unix_user_id().ok_or_else(|| {
+                    fdo::Error::Failed(format!("Could not determine UID for '{name}'"))
+                })?;
+                self.reply(call, &uid)
+            }
+            (Some(BUS_INTERFACE) | None, "GetConnectionUnixProcessID") => {
+                let name: BusName<'_> = args(&body)?;
+                let pid = self.credentials(&name)?.process_id().ok_or_else(|| {
+                    fdo::Error::UnixProcessIdUnknown(format!(
+                        "Could not determine PID for '{name}'"
+                    ))
+                })?;
+                self.reply(call, &pid)
+            }
+            (Some(BUS_INTERFACE) | None, "GetConnectionCredentials") => {
+                let name: BusName<'_> = args(&body)?;
+                let credentials = self.credentials(&name)?;
+                self.reply(call, credentials)
+            }
+            (Some(BUS_INTERFACE) | None, "AddMatch") => {
+                let rule: &str = args(&body)?;
+                let rule = OwnedMatchRule::try_from(rule)
+                    .map_err(|e| fdo::Error::MatchRuleInvalid(e.to_string()))?;
+                if let Some(peer) = self.peers.get_mut(sender.as_str()) {
+                    peer.match_rules.push(rule);
+                }
+                self.reply(call, &())
+            }
+            (Some(BUS_INTERFACE) | None, "RemoveMatch") => {
+                let rule: &str = args(&body)?;
+                let rule = OwnedMatchRule::try_from(rule)
+                    .map_err(|e| fdo::Error::MatchRuleInvalid(e.to_string()))?;
+                let peer = self.peers.get_mut(sender.as_str());
+                match peer.and_then(|peer| {
+                    let pos = peer.match_rules.iter().position(|r| *r == rule)?;
+                    Some(peer.match_rules.remove(pos))
+                }) {
+                    Some(_) => self.reply(call, &()),
+                    None => Err(fdo::Error::MatchRuleNotFound(
+                        "The given match rule wasn't found and can't be removed".into(),
+                    )),
+                }
+            }
+            (Some(BUS_INTERFACE) | None, "GetId") => self.reply(call, &guid.as_str()),
+            (Some(BUS_INTERFACE) | None, "ReloadConfig") => self.reply(call, &()),
+            (Some("org.freedesktop.DBus.Peer") | None, "Ping") => self.reply(call, &()),
+            (Some("org.freedesktop.DBus.Peer") | None, "GetMachineId") => {
+                let id = fdo::Peer.get_machine_id()?;
+                self.reply(call, &id)
+            }
+            (Some("org.freedesktop.DBus.Introspectable") | None, "Introspect") => {
+                self.reply(call, &INTROSPECTION)
+            }
+            (Some("org.freedesktop.DBus.Properties"), "Get") => {
+                let (interface, property): (InterfaceName<'_>, MemberName<'_>) = args(&body)?;
+                if interface != BUS_INTERFACE {
+                    return Err(fdo::Error::UnknownInterface(format!(
+                        "Unknown interface '{interface}'"
+                    )));
+                }
+                let value = match property.as_str() {
+                    "Features" => Value::from(Vec::<&str>::new()),
+                    "Interfaces" => Value::from(Vec::<&str>::new()),
+                    _ => {
+                        return Err(fdo::Error::UnknownProperty(format!(
+                            "Unknown property '{property}'"
+                        )))
+                    }
+                };
+                self.reply(call, &value)
+            }
+            (Some("org.freedesktop.DBus.Properties"), "GetAll") => {
+                let interface: InterfaceName<'_> = args(&body)?;
+                let mut properties = HashMap::new();
+                if interface == BUS_INTERFACE {
+                    properties.insert("Features", Value::from(Vec::<&str>::new()));
+                    properties.insert("Interfaces", Value::from(Vec::<&str>::new()));
+                }
+                self.reply(call, &properties)
+            }
+            (Some("org.freedesktop.DBus.Properties"), "Set") => Err(fdo::Error::PropertyReadOnly(
+                "Properties of the bus are read-only".into(),
+            )),
+            (_, member) => Err(fdo::Error::UnknownMethod(format!(
+                "Unknown method '{member}'"
+            ))),
+        }
+    }
+
+    fn request_name(
+        &mut self,
+        sender: &UniqueName<'_>,
+        name: WellKnownName<'_>,
+        flags: BitFlags<RequestNameFlags>,
+    ) -> fdo::Result<RequestNameReply> {
+        if name == BUS_NAME {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "Connection is not allowed to own the service \"{BUS_NAME}\""
+            )));
+        }
+
+        let ownership = match self.names.get_mut(name.as_str()) {
+            Some(ownership) => ownership,
+            None => {
+                self.names.insert(
+                    name.to_owned().into(),
+                    NameOwnership {
+                        owner: sender.to_owned().into(),
+                        owner_flags: flags,
+                        queue: VecDeque::new(),
+                    },
+                );
+                self.emit_name_owner_changed(name.as_str(), None, Some(sender));
+                self.emit_bus_signal(Some(sender), "NameAcquired", &name.as_str())?;
+
+                return Ok(RequestNameReply::PrimaryOwner);
+            }
+        };
+
+        if ownership.owner == *sender {
+            ownership.owner_flags = flags;
+
+            return Ok(RequestNameReply::AlreadyOwner);
+        }
+
+        ownership.queue.retain(|(queued, _)| *queued != *sender);
+        if flags.contains(RequestNameFlags::ReplaceExisting)
+            && ownership
+                .owner_flags
+                .contains(RequestNameFlags::AllowReplacement)
+        {
+            let old_owner = std::mem::replace(&mut ownership.owner, sender.to_owned().into());
+            let old_flags = std::mem::replace(&mut ownership.owner_flags, flags);
+            if !old_flags.contains(RequestNameFlags::DoNotQueue) {
+                ownership.queue.push_front((old_owner.clone(), old_flags));
+            }
+            self.emit_bus_signal(Some(&old_owner), "NameLost", &name.as_str())?;
+            self.emit_name_owner_changed(name.as_str(), Some(&old_owner), Some(sender));
+            self.emit_bus_signal(Some(sender), "NameAcquired", &name.as_str())?;
+
+            Ok(RequestNameReply::PrimaryOwner)
+        } else if flags.contains(RequestNameFlags::DoNotQueue) {
+            Ok(RequestNameReply::Exists)
+        } else {
+            ownership.queue.push_back((sender.to_owned().into(), flags));
+
+            Ok(RequestNameReply::InQueue)
+        }
+    }
+
+    fn release_name(
+        &mut self,
+        sender: &UniqueName<'_>,
+        name: &WellKnownName<'_>,
+    ) -> ReleaseNameReply {
+        let ownership = match self.names.get_mut(name.as_str()) {
+            Some(ownership) => ownership,
+            None => return ReleaseNameReply::NonExistent,
+        };
+
+        if ownership.owner != *sender {
+            let queue_len = ownership.queue.len();
+            ownership.queue.retain(|(queued, _)| *queued != *sender);
+
+            return if ownership.queue.len() == queue_len {
+                ReleaseNameReply::NotOwner
+            } else {
+                ReleaseNameReply::Released
+            };
+        }
+
+        let new_owner = match ownership.queue.pop_front() {
+            Some((owner, flags)) => {
+                ownership.owner = owner.clone();
+                ownership.owner_flags = flags;
+
+                Some(owner)
+            }
+            None => {
+                self.names.remove(name.as_str());
+
+                None
+            }
+        };
+        // The peer may be gone already so we ignore any errors.
+        let _ = self.emit_bus_signal(Some(sender), "NameLost", &name.as_str());
+        self.emit_name_owner_changed(name.as_str(), Some(sender), new_owner.as_deref());
+        if let Some(new_owner) = new_owner {
+            let _ = self.emit_bus_signal(Some(&new_owner), "NameAcquired", &name.as_str());
+        }
+
+        ReleaseNameReply::Released
+    }
+
+    /// The unique name of the owner of `name`.
+    fn owner(&self, name: &BusName<'_>) -> Option<OwnedUniqueName> {
+        if name.as_str() == BUS_NAME {
+            return OwnedUniqueName::try_from(BUS_NAME).ok();
+        }
+
+        match name {
+            BusName::Unique(name) => self
+                .peers
+                .get(name.as_str())
+                .filter(|peer| peer.registered)
+                .map(|_| name.to_owned().into()),
+            BusName::WellKnown(name) => self.names.get(name.as_str()).map(|n| n.owner.clone()),
+        }
+    }
+
+    fn credentials(&self, name: &BusName<'_>) -> fdo::Result<&ConnectionCredentials> {
+        self.owner(name)
+            .and_then(|owner| self.peers.get(owner.as_str()))
+            .map(|peer| &peer.credentials)
+            .ok_or_else(|| {
+                fdo::Error::NameHasNoOwner(format!(
+                    "Could not get credentials of name '{name}': no such name"
+                ))
+            })
+    }
+
+    /// Queue `msg` for delivery to the peer named `dest`.
+    fn deliver(&self, dest: &UniqueName<'_>, msg: &Message) {
+        let peer = match self.peers.get(dest.as_str()) {
+            Some(peer) if peer.registered => peer,
+            _ => return,
+        };
+
+        match peer.outgoing.try_broadcast(msg.clone()) {
+            Ok(_) => (),
+            Err(TrySendError::Full(_)) => {
+                warn!("Too many messages queued for `{dest}`, dropping message: {msg}")
+            }
+            Err(e) => debug!("Failed to queue message for `{dest}`: {e}"),
+        }
+    }
+
+    /// Queue `msg` for delivery to all the peers with a matching match rule.
+    fn broadcast(&self, msg: &Message) {
+        for (name, peer) in &self.peers {
+            if peer
+                .match_rules
+                .iter()
+                .any(|rule| self.rule_matches(rule, msg))
+            {
+                self.deliver(name, msg);
+            }
+        }
+    }
+
+    fn rule_matches(&self, rule: &OwnedMatchRule, msg: &Message) -> bool {
+        // `MatchRule::matches` can't resolve well-known names but we can.
+        if let Some(sender @ BusName::WellKnown(_)) = rule.sender() {
+            let hdr = msg.header();
+            match (self.owner(sender), hdr.sender()) {
+                (Some(owner), Some(msg_sender)) if owner == *msg_sender => (),
+                _ => return false,
+            }
+        }
+
+        rule.matches(msg).unwrap_or(false)
+    }
+
+    fn reply<B>(&self, call: &Message, body: &B) -> fdo::Result<()>
+    where
+        B: serde::ser::Serialize + zvariant::DynamicType,
+    {
+        if call
+            .primary_header()
+            .flags()
+            .contains(Flags::NoReplyExpected)
+        {
+            return Ok(());
+        }
+        let reply = Message::method_reply(call)?.sender(BUS_NAME)?.build(body)?;
+        self.deliver_reply(call, &reply);
+
+        Ok(())
+    }
+
+    fn reply_error(&self, call: &Message, name: &str, description: &str) -> Result<()> {
+        if call
+            .primary_header()
+            .flags()
+            .contains(Flags::NoReplyExpected)
+        {
+            return Ok(());
+        }
+        let reply = Message::method_error(call, name)?
+            .sender(BUS_NAME)?
+            .build(&description)?;
+        self.deliver_reply(call, &reply);
+
+        Ok(())
+    }
+
+    fn deliver_reply(&self, call: &Message, reply: &Message) {
+        if let Some(sender) = call.header().sender() {
+            self.deliver(sender, reply);
+        }
+    }
+
+    /// Emit a signal from the bus, to the peer named `dest` or to all interested peers.
+    fn emit_bus_signal<B>(
+        &self,
+        dest: Option<&UniqueName<'_>>,
+        member: &'static str,
+        body: &B,
+    ) -> Result<()>
+    where
+        B: serde::ser::Serialize + zvariant::DynamicType,
+    {
+        let mut builder = Message::signal(BUS_PATH, BUS_INTERFACE, member)?.sender(BUS_NAME)?;
+        if let Some(dest) = dest {
+            builder = builder.destination(dest.as_str())?;
+        }
+        let signal = builder.build(body)?;
+        match dest {
+            Some(dest) => self.deliver(dest, &signal),
+            None => self.broadcast(&signal),
+        }
+
+        Ok(())
+    }
+
+    fn emit_name_owner_changed(
+        &self,
+        name: &str,
+        old_owner: Option<&UniqueName<'_>>,
+        new_owner: Option<&UniqueName<'_>>,
+    ) {
+        let old_owner = old_owner.map(UniqueName::as_str).unwrap_or_default();
+        let new_owner = new_owner.map(UniqueName::as_str).unwrap_or_default();
+        if let Err(e) =
+            self.emit_bus_signal(None, "NameOwnerChanged", &(name, old_owner, new_owner))
+        {
+            warn!("Failed to emit NameOwnerChanged signal: {e}");
+        }
+    }
+}
+
+// Keep reading messages from a peer and route them, until it disconnects.
+#[instrument(skip(bus, stream))]
+async fn read_messages(bus: Weak<Bus>, name: OwnedUniqueName, mut stream: MessageStream) {
+    while let Some(msg) = stream.next().await {
+        let bus = match bus.upgrade() {
+            Some(bus) => bus,
+            None => return,
+        };
+        let res = match msg {
+            Ok(msg) => bus.handle_message(&name, msg).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            debug!("Disconnecting peer: {e}");
+
+            break;
+        }
+    }
+
+    if let Some(bus) = bus.upgrade() {
+        bus.remove_peer(&name).await;
+    }
+}
+
+// Keep writing the messages queued for a peer to it.
+async fn write_messages(conn: Connection, mut outgoing: Receiver<Message>) {
+    while let Some(msg) = outgoing.next().await {
+        if let Err(e) = conn.send(&msg).await {
+            debug!("Failed to send message to peer: {e}");
+
+            break;
+        }
+    }
+}
+
+fn args<'b, B>(body: &'b Body) -> fdo::Result<B>
+where
+    B: zvariant::DynamicDeserialize<'b>,
+{
+    body.deserialize()
+        .map_err(|e| fdo::Error::InvalidArgs(e.to_string()))
+}
+
+fn is_bus_call(msg: &Message, method: &str) -> bool {
+    let hdr = msg.header();
+
+    msg.message_type() == Type::MethodCall
+        && hdr.destination().map(|d| d.as_str()) == Some(BUS_NAME)
+        && hdr.member().map(|m| m.as_str()) == Some(method)
+        && hdr
+            .interface()
+            .map(|i| i.as_str() == BUS_INTERFACE)
+            .unwrap_or(true)
+}
+
+/// Make a copy of `msg` with the sender header field set to `sender`.
+///
+/// Peers can't be trusted to set (correctly) the sender of their messages so the bus always
+/// overwrites it.
+fn with_sender(msg: &Message, sender: &UniqueName<'_>) -> Result<Message> {
+    let hdr = msg.header();
+    if hdr.sender() == Some(sender) {
+        return Ok(msg.clone());
+    }
+
+    let body = msg.body();
+    let signature = body
+        .signature()
+        .map(|s| {
+            // `build_raw_body` strips the STRUCT delimiters of the signature it's given as it
+            // expects it to describe all arguments as a single STRUCT.
+            if s.starts_with(zvariant::STRUCT_SIG_START_CHAR) {
+                format!("({s})")
+            } else {
+                s.to_string()
+            }
+        })
+        .unwrap_or_default();
+    #[cfg(unix)]
+    let fds = {
+        use std::os::fd::AsFd;
+
+        body.data()
+            .fds()
+            .iter()
+            .map(|fd| fd.as_fd().try_clone_to_owned())
+            .collect::<std::io::Result<Vec<_>>>()?
+    };
+
+    // SAFETY: The body and signature are taken from a valid message.
+    unsafe {
+        message::Builder::from(hdr).sender(sender)?.build_raw_body(
+            body.data(),
+            signature.as_str(),
+            #[cfg(unix)]
+            fds,
+        )
+    }
+}
+
+const INTROSPECTION: &str = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+"http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+<node>
+  <interface name="org.freedesktop.DBus">
+    <method name="Hello">
+      <arg direction="out" type="s"/>
+    </method>
+    <method name="RequestName">
+      <arg direction="in" type="s"/>
+      <arg direction="in" type="u"/>
+      <arg direction="out" type="u"/>
+    </method>
+    <method name="ReleaseName">
+      <arg direction="in" type="s"/>
+      <arg direction="out" type="u"/>
+    </method>
+    <method name="StartServiceByName">
+      <arg direction="in" type="s"/>
+      <arg direction="in" type="u"/>
+      <arg direction="out" type="u"/>
+    </method>
+    <method name="NameHasOwner">
+      <arg direction="in" type="s"/>
+      <arg direction="out" type="b"/>
+    </method>
+    <method name="ListNames">
+      <arg direction="out" type="as"/>
+    </method>
+    <method name="ListActivatableNames">
+      <arg direction="out" type="as"/>
+    </method>
+    <method name="AddMatch">
+      <arg direction="in" type="s"/>
+    </method>
+    <method name="RemoveMatch">
+      <arg direction="in" type="s"/>
+    </method>
+    <method name="GetNameOwner">
+      <arg direction="in" type="s"/>
+      <arg direction="out" type="s"/>
+    </method>
+    <method name="ListQueuedOwners">
+      <arg direction="in" type="s"/>
+      <arg direction="out" type="as"/>
+    </method>
+    <method name="GetConnectionUnixUser">
+      <arg direction="in" type="s"/>
+      <arg direction="out" type="u"/>
+    </method>
+    <method name="GetConnectionUnixProcessID">
+      <arg direction="in" type="s"/>
+      <arg direction="out" type="u"/>
+    </method>
+    <method name="GetConnectionCredentials">
+      <arg direction="in" type="s"/>
+      <arg direction="out" type="a{sv}"/>
+    </method>
+    <method name="ReloadConfig">
+    </method>
+    <method name="GetId">
+      <arg direction="out" type="s"/>
+    </method>
+    <signal name="NameOwnerChanged">
+      <arg type="s"/>
+      <arg type="s"/>
+      <arg type="s"/>
+    </signal>
+    <signal name="NameLost">
+      <arg type="s"/>
+    </signal>
+    <signal name="NameAcquired">
+      <arg type="s"/>
+    </signal>
+    <property name="Features" type="as" access="read"/>
+    <property name="Interfaces" type="as" access="read"/>
+  </interface>
+  <interface name="org.freedesktop.DBus.Introspectable">
+    <method name="Introspect">
+      <arg direction="out" type="s"/>
+    </method>
+  </interface>
+  <interface name="org.freedesktop.DBus.Peer">
+    <method name="GetMachineId">
+      <arg direction="out" type="s"/>
+    </method>
+    <method name="Ping">
+    </method>
+  </interface>
+  <interface name="org.freedesktop.DBus.Properties">
+    <method name="Get">
+      <arg direction="in" type="s"/>
+      <arg direction="in" type="s"/>
+      <arg direction="out" type="v"/>
+    </method>
+    <method name="GetAll">
+      <arg direction="in" type="s"/>
+      <arg direction="out" type="a{sv}"/>
+    </method>
+    <method name="Set">
+      <arg direction="in" type="s"/>
+      <arg direction="in" type="s"/>
+      <arg direction="in" type="v"/>
+    </method>
+  </interface>
+</node>
+"#;
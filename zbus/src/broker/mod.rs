@@ -0,0 +1,346 @@
+//! An embeddable D-Bus message bus broker.
+//!
+//! This module provides [`Broker`], a minimal message bus that runs in-process. It accepts clients
+//! on an [`Address`], authenticates them, assigns them unique names and routes messages between
+//! them. It also implements the `org.freedesktop.DBus` interface itself so clients can own
+//! well-known names, subscribe to signals through match rules etc.
+//!
+//! This is mainly useful for testing D-Bus services in isolation, and for applications that want
+//! to use D-Bus as an IPC mechanism between their own components without depending on a system
+//! broker. Features like service activation, policies and eavesdropping are not supported.
+//!
+//! # Example
+//!
+//! ```
+//!# use std::error::Error;
+//!# zbus::block_on(async {
+//! use zbus::{broker::Broker, connection, fdo::DBusProxy};
+//!
+//! let dir = std::env::temp_dir();
+//! let broker = Broker::builder(format!("unix:tmpdir={}", dir.display()).as_str())?
+//!     .build()
+//!     .await?;
+//!
+//! let conn = connection::Builder::address(broker.address().clone())?
+//!     .name("org.zbus.BrokerExample")?
+//!     .build()
+//!     .await?;
+//! let dbus = DBusProxy::new(&conn).await?;
+//! let owner = dbus
+//!     .get_name_owner("org.zbus.BrokerExample".try_into()?)
+//!     .await?;
+//! assert_eq!(owner, *conn.unique_name().unwrap());
+//!# Ok::<(), Box<dyn Error + Send + Sync>>(())
+//!# }).unwrap();
+//! ```
+
+use static_assertions::assert_impl_all;
+use std::sync::{Arc, Weak};
+use tracing::{debug, instrument};
+
+use crate::{
+    address::Address, connection::Listener, AuthMechanism, Error, Executor, Guid, Result, Task,
+};
+
+mod bus;
+use bus::Bus;
+
+/// An in-process D-Bus message bus broker.
+///
+/// The broker keeps serving clients as long as this object is alive. Use [`Broker::builder`] to
+/// create one.
+#[derive(Debug)]
+pub struct Broker {
+    address: Address,
+    guid: Guid,
+    _bus: Arc<Bus>,
+    _accept_task: Task<()>,
+}
+
+assert_impl_all!(Broker: Send, Sync, Unpin);
+
+impl Broker {
+    /// Create a builder for a broker listening on the given address.
+    ///
+    /// Supported addresses are `unix:path=`, `unix:abstract=` (on Linux), `unix:dir=`,
+    /// `unix:tmpdir=` and `tcp:`. A `port` of `0` in a `tcp:` address will let the OS choose a
+    /// free port.
+    pub fn builder<A>(address: A) -> Result<Builder>
+    where
+        A: TryInto<Address>,
+        A::Error: Into<Error>,
+    {
+        Builder::new(address)
+    }
+
+    /// The address clients can connect to.
+    ///
+    /// This differs from the address the broker was created with when the latter was a listenable
+    /// address, e.g `unix:tmpdir=` or a `tcp:` address with a `port` of `0`.
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// The GUID of the broker.
+    pub fn guid(&self) -> &Guid {
+        &self.guid
+    }
+}
+
+/// A builder for [`Broker`].
+#[derive(Debug)]
+#[must_use]
+pub struct Builder {
+    address: Address,
+    guid: Option<Guid>,
+    auth_mechanisms: Option<Vec<AuthMechanism>>,
+}
+
+assert_impl_all!(Builder: Send, Sync, Unpin);
+
+impl Builder {
+    /// Create a builder for a broker listening on the given address.
+    ///
+    /// See [`Broker::builder`] for details.
+    pub fn new<A>(address: A) -> Result<Self>
+    where
+        A: TryInto<Address>,
+        A::Error: Into<Error>,
+    {
+        Ok(Self {
+            address: address.try_into().map_err(Into::into)?,
+            guid: None,
+            auth_mechanisms: None,
+        })
+    }
+
+    /// Set the GUID of the broker.
+    ///
+    /// If not set, a random one is generated.
+    pub fn guid(mut self, guid: Guid) -> Self {
+        self.guid = Some(guid);
+
+        self
+    }
+
+    /// Specify the mechanisms to accept for authenticating clients.
+    ///
+    /// By default, only `EXTERNAL` is accepted on unix sockets and only `DBUS_COOKIE_SHA1` on TCP.
+    pub fn auth_mechanisms(mut self, auth_mechanisms: &[AuthMechanism]) -> Self {
+        self.auth_mechanisms = Some(auth_mechanisms.to_vec());
+
+        self
+    }
+
+    /// Bind the address and start serving clients.
+    pub async fn build(self) -> Result<Broker> {
+        let mut builder = Listener::builder(self.address)?.message_stream();
+        if let Some(guid) = self.guid {
+            builder = builder.guid(guid);
+        }
+        if let Some(auth_mechanisms) = self.auth_mechanisms {
+            builder = builder.auth_mechanisms(&auth_mechanisms);
+        }
+        let listener = builder.build().await?;
+        let address = listener.address().clone();
+        let guid = listener.guid().clone();
+
+        let executor = Executor::new();
+        let bus = Arc::new(Bus::new(guid.clone(), executor.clone()));
+        let accept_task = executor.spawn(
+            accept_clients(listener, Arc::downgrade(&bus)),
+            "broker accept loop",
+        );
+        #[cfg(not(feature = "tokio"))]
+        crate::connection::start_internal_executor(&executor, true)?;
+
+        Ok(Broker {
+            address,
+            guid,
+            _bus: bus,
+            _accept_task: accept_task,
+        })
+    }
+}
+
+// Keep accepting new clients and hand them over to the bus once authenticated.
+#[instrument(skip_all)]
+async fn accept_clients(mut listener: Listener, bus: Weak<Bus>) {
+    loop {
+        match listener.accept_with_stream().await {
+            Ok((conn, stream)) => match bus.upgrade() {
+                Some(bus) => bus.add_peer(conn, stream).await,
+                None => return,
+            },
+            Err(e) => debug!("Failed to accept a new client: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use ntest::timeout;
+    use test_log::test;
+
+    use super::Broker;
+    use crate::{
+        connection, dbus_interface, dbus_proxy,
+        fdo::{DBusProxy, RequestNameFlags, RequestNameReply},
+        names::BusName,
+        AuthMechanism, Connection, Message, Result, SignalContext,
+    };
+
+    struct Echo;
+
+    #[dbus_interface(name = "org.zbus.BrokerTest")]
+    impl Echo {
+        fn echo(&self, s: &str) -> String {
+            s.to_string()
+        }
+
+        #[dbus_interface(signal)]
+        async fn echoed(ctxt: &SignalContext<'_>, s: &str) -> zbus::Result<()>;
+    }
+
+    #[dbus_proxy(
+        interface = "org.zbus.BrokerTest",
+        default_service = "org.zbus.BrokerTest",
+        default_path = "/org/zbus/BrokerTest"
+    )]
+    trait Echo {
+        fn echo(&self, s: &str) -> zbus::Result<String>;
+
+        #[dbus_proxy(signal)]
+        fn echoed(&self, s: &str) -> zbus::Result<()>;
+    }
+
+    async fn client(broker: &Broker) -> Result<Connection> {
+        connection::Builder::address(broker.address().clone())?
+            .build()
+            .await
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn name_ownership() {
+        crate::utils::block_on(test_name_ownership()).unwrap();
+    }
+
+    async fn test_name_ownership() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = Broker::builder(format!("unix:dir={}", dir.path().display()).as_str())?
+            .build()
+            .await?;
+
+        let conn1 = client(&broker).await?;
+        let conn2 = client(&broker).await?;
+        let name1 = conn1.unique_name().unwrap().to_owned();
+        let name2 = conn2.unique_name().unwrap().to_owned();
+        assert_ne!(name1, name2);
+
+        let dbus1 = DBusProxy::new(&conn1).await?;
+        let dbus2 = DBusProxy::new(&conn2).await?;
+        assert_eq!(dbus1.get_id().await?, *broker.guid());
+
+        let well_known = "org.zbus.BrokerTest";
+        let mut owner_changed = dbus2
+            .receive_name_owner_changed_with_args(&[(0, well_known)])
+            .await?;
+        assert_eq!(
+            dbus1
+                .request_name(well_known.try_into()?, RequestNameFlags::DoNotQueue.into())
+                .await?,
+            RequestNameReply::PrimaryOwner
+        );
+        assert_eq!(
+            dbus2
+                .request_name(well_known.try_into()?, Default::default())
+                .await?,
+            RequestNameReply::InQueue
+        );
+        let args = owner_changed.next().await.unwrap();
+        let args = args.args()?;
+        assert!(args.old_owner().is_none());
+        assert_eq!(args.new_owner().as_ref(), Some(&*name1));
+
+        let name = BusName::try_from(well_known)?;
+        assert_eq!(dbus2.get_name_owner(name.clone()).await?, name1);
+        assert_eq!(
+            dbus2.list_queued_owners(well_known.try_into()?).await?,
+            vec![name1.clone(), name2.clone()]
+        );
+        let names = dbus2.list_names().await?;
+        for name in ["org.freedesktop.DBus", well_known, &name1, &name2] {
+            assert!(
+                names.iter().any(|n| n.as_str() == name),
+                "{name} not listed"
+            );
+        }
+
+        // Once the owner goes away, the next in the queue gets the name.
+        drop(dbus1);
+        drop(conn1);
+        let args = owner_changed.next().await.unwrap();
+        let args = args.args()?;
+        assert_eq!(args.old_owner().as_ref(), Some(&*name1));
+        assert_eq!(args.new_owner().as_ref(), Some(&*name2));
+        assert_eq!(dbus2.get_name_owner(name.clone()).await?, name2);
+
+        // The unique name of the disconnected client is also gone.
+        assert!(!dbus2.name_has_owner(name1.as_ref().into()).await?);
+        assert!(dbus2.get_name_owner(name1.as_ref().into()).await.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn routing() {
+        crate::utils::block_on(test_routing()).unwrap();
+    }
+
+    async fn test_routing() -> Result<()> {
+        let broker = Broker::builder("tcp:host=127.0.0.1,port=0")?
+            .auth_mechanisms(&[AuthMechanism::Anonymous])
+            .build()
+            .await?;
+
+        let service = connection::Builder::address(broker.address().clone())?
+            .auth_mechanisms(&[AuthMechanism::Anonymous])
+            .serve_at("/org/zbus/BrokerTest", Echo)?
+            .name("org.zbus.BrokerTest")?
+            .build()
+            .await?;
+        let client = connection::Builder::address(broker.address().clone())?
+            .auth_mechanisms(&[AuthMechanism::Anonymous])
+            .build()
+            .await?;
+
+        let proxy = EchoProxy::new(&client).await?;
+        assert_eq!(proxy.echo("hello").await?, "hello");
+
+        let mut echoed = proxy.receive_echoed().await?;
+        let iface = service
+            .object_server()
+            .interface::<_, Echo>("/org/zbus/BrokerTest")
+            .await?;
+        Echo::echoed(iface.signal_context(), "world").await?;
+        let signal = echoed.next().await.unwrap();
+        assert_eq!(signal.args()?.s(), &"world");
+        let signal = Message::from(signal);
+        assert_eq!(
+            signal.header().sender().unwrap(),
+            &*service.unique_name().unwrap()
+        );
+
+        // Calls to names nobody owns fail.
+        let proxy = EchoProxy::builder(&client)
+            .destination("org.zbus.NoSuchName")?
+            .build()
+            .await?;
+        assert!(proxy.echo("hello").await.is_err());
+
+        Ok(())
+    }
+}
@@ -8,7 +8,9 @@ use std::net::TcpStream;
 use std::os::unix::net::UnixStream;
 use std::{
     collections::{HashMap, HashSet, VecDeque},
+    future::Future,
     sync::Arc,
+    time::Duration,
 };
 #[cfg(feature = "tokio")]
 use tokio::net::TcpStream;
@@ -16,6 +18,7 @@ use tokio::net::TcpStream;
 use tokio::net::UnixStream;
 #[cfg(feature = "tokio-vsock")]
 use tokio_vsock::VsockStream;
+use tracing::debug;
 #[cfg(windows)]
 use uds_windows::UnixStream;
 #[cfg(all(feature = "vsock", not(feature = "tokio")))]
@@ -24,15 +27,19 @@ use vsock::VsockStream;
 use zvariant::{ObjectPath, Str};
 
 use crate::{
-    address::{self, Address},
+    address::{self, AddressList},
     async_lock::RwLock,
     names::{InterfaceName, UniqueName, WellKnownName},
-    object_server::Interface,
-    Connection, Error, Executor, Guid, Result,
+    object_server::{Authorizer, Interface},
+    Connection, Error, Executor, Guid, MessageStream, Result,
 };
 
 use super::{
     handshake::{AuthMechanism, Authenticated},
+    interceptor::Interceptor,
+    peer_policy::{AuthenticatedPeer, PeerPolicy},
+    reconnect::{Reconnect, ReconnectPolicy},
+    sasl::SaslMechanism,
     socket::{BoxedSplit, ReadHalf, Socket, Split, WriteHalf},
 };
 
@@ -47,7 +54,7 @@ enum Target {
         feature = "tokio-vsock"
     ))]
     VsockStream(VsockStream),
-    Address(Address),
+    Address(AddressList),
     Socket(Split<Box<dyn ReadHalf>, Box<dyn WriteHalf>>),
 }
 
@@ -61,16 +68,24 @@ type Interfaces<'a> =
 pub struct Builder<'a> {
     target: Option<Target>,
     max_queued: Option<usize>,
+    method_timeout: Option<Duration>,
     guid: Option<&'a Guid>,
     p2p: bool,
     internal_executor: bool,
     #[derivative(Debug = "ignore")]
     interfaces: Interfaces<'a>,
+    #[derivative(Debug = "ignore")]
+    authorizer: Option<Arc<dyn Authorizer>>,
     names: HashSet<WellKnownName<'a>>,
     auth_mechanisms: Option<VecDeque<AuthMechanism>>,
+    sasl_mechanisms: Vec<Arc<dyn SaslMechanism>>,
+    #[derivative(Debug = "ignore")]
+    peer_policy: Option<PeerPolicy>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
     unique_name: Option<UniqueName<'a>>,
     cookie_context: Option<super::handshake::CookieContext<'a>>,
     cookie_id: Option<usize>,
+    reconnect: Option<ReconnectPolicy>,
 }
 
 assert_impl_all!(Builder<'_>: Send, Sync, Unpin);
@@ -78,16 +93,27 @@ assert_impl_all!(Builder<'_>: Send, Sync, Unpin);
 impl<'a> Builder<'a> {
     /// Create a builder for the session/user message bus connection.
     pub fn session() -> Result<Self> {
-        Ok(Self::new(Target::Address(Address::session()?)))
+        Ok(Self::new(Target::Address(AddressList::session()?)))
     }
 
     /// Create a builder for the system-wide message bus connection.
     pub fn system() -> Result<Self> {
-        Ok(Self::new(Target::Address(Address::system()?)))
+        Ok(Self::new(Target::Address(AddressList::system()?)))
+    }
+
+    /// Create a builder for the connection to the message bus that started this process.
+    ///
+    /// Services activated by a bus should use this to connect to it. See
+    /// [`AddressList::starter`] for how the bus address is determined.
+    pub fn starter() -> Result<Self> {
+        Ok(Self::new(Target::Address(AddressList::starter()?)))
     }
 
     /// Create a builder for connection that will use the given [D-Bus bus address].
     ///
+    /// The address can also be a `;`-separated list of addresses (see [`AddressList`]), in which
+    /// case they're tried in order until a connection is established.
+    ///
     /// # Example
     ///
     /// Here is an example of connecting to an IBus service:
@@ -119,7 +145,7 @@ impl<'a> Builder<'a> {
     /// [D-Bus bus address]: https://dbus.freedesktop.org/doc/dbus-specification.html#addresses
     pub fn address<A>(address: A) -> Result<Self>
     where
-        A: TryInto<Address>,
+        A: TryInto<AddressList>,
         A::Error: Into<Error>,
     {
         Ok(Self::new(Target::Address(
@@ -170,6 +196,25 @@ impl<'a> Builder<'a> {
         self
     }
 
+    /// Register a custom [`SaslMechanism`] to use during authentication.
+    ///
+    /// Custom mechanisms are tried (or, for servers, accepted) in addition to the ones set through
+    /// [`Builder::auth_mechanisms`]. Clients try them first, in the order they were registered.
+    pub fn sasl_mechanism<M>(mut self, mechanism: M) -> Self
+    where
+        M: SaslMechanism,
+    {
+        self.sasl_mechanisms.push(Arc::new(mechanism));
+
+        self
+    }
+
+    pub(super) fn shared_sasl_mechanisms(mut self, mechanisms: &[Arc<dyn SaslMechanism>]) -> Self {
+        self.sasl_mechanisms.extend(mechanisms.iter().cloned());
+
+        self
+    }
+
     /// The cookie context to use during authentication.
     ///
     /// This is only used when the `cookie` authentication mechanism is enabled and only valid for
@@ -218,6 +263,72 @@ impl<'a> Builder<'a> {
         self
     }
 
+    /// Set the policy deciding which clients the to-be-created server connection accepts.
+    ///
+    /// Once a client successfully authenticated, `policy` is called with its credentials and the
+    /// mechanism and identity it authenticated with. The client is only accepted if the returned
+    /// future resolves to `true`. Otherwise, it's rejected, as if it had failed to authenticate.
+    ///
+    /// This is only used for server connections (see [`Builder::server`]).
+    ///
+    /// # Example
+    ///
+    /// Only accepting clients from the `wheel` group (GID 10):
+    ///
+    /// ```no_run
+    ///# use std::error::Error;
+    ///# zbus::block_on(async {
+    /// use zbus::{connection::Builder, Guid};
+    ///# #[cfg(unix)]
+    ///# {
+    /// use std::os::unix::net::UnixListener;
+    ///
+    /// let listener = UnixListener::bind("/run/myservice/admin")?;
+    /// let (stream, _) = listener.accept()?;
+    /// let guid = Guid::generate();
+    /// let _conn = Builder::unix_stream(stream)
+    ///     .server(&guid)
+    ///     .p2p()
+    ///     .peer_policy(|peer| async move {
+    ///         peer.credentials()
+    ///             .unix_group_ids()
+    ///             .map_or(false, |gids| gids.contains(&10))
+    ///     })
+    ///     .build()
+    ///     .await?;
+    ///# }
+    ///# Ok::<(), Box<dyn Error + Send + Sync>>(())
+    ///# }).unwrap();
+    /// ```
+    pub fn peer_policy<F, Fut>(mut self, policy: F) -> Self
+    where
+        F: Fn(AuthenticatedPeer) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.peer_policy = Some(Arc::new(move |peer| Box::pin(policy(peer))));
+
+        self
+    }
+
+    /// Register an [`Interceptor`] on the to-be-created connection.
+    ///
+    /// Interceptors get to observe, rewrite, drop or answer each message sent or received on the
+    /// connection, in the order they were registered.
+    pub fn interceptor<I>(mut self, interceptor: I) -> Self
+    where
+        I: Interceptor,
+    {
+        self.interceptors.push(Arc::new(interceptor));
+
+        self
+    }
+
+    pub(super) fn shared_interceptors(mut self, interceptors: &[Arc<dyn Interceptor>]) -> Self {
+        self.interceptors.extend(interceptors.iter().cloned());
+
+        self
+    }
+
     /// Set the capacity of the main (unfiltered) queue.
     ///
     /// Since typically you'd want to set this at instantiation time, you can set it through the
@@ -249,6 +360,68 @@ impl<'a> Builder<'a> {
         self
     }
 
+    /// Set the default timeout for method replies.
+    ///
+    /// Method calls made through the connection, and through the [`crate::Proxy`] instances
+    /// created for it, fail with [`Error::Timeout`] if no reply is received within this duration.
+    /// By default, there is no timeout and method calls wait for their reply indefinitely.
+    ///
+    /// The timeout can be overridden for each proxy through
+    /// [`crate::proxy::Builder::method_timeout`], and for each call through
+    /// [`Connection::call_method_with_timeout`] and [`crate::Proxy::call_with_timeout`].
+    pub fn method_timeout(mut self, timeout: Duration) -> Self {
+        self.method_timeout = Some(timeout);
+
+        self
+    }
+
+    /// Re-establish the connection, following `policy`, whenever the socket is lost.
+    ///
+    /// Once the connection is re-established, its state on the bus is restored: the `Hello` call is
+    /// made again (so the connection gets a new unique name), the match rules of the signal streams
+    /// are added again and the names owned by the connection are requested again. The objects
+    /// served by the [`zbus::ObjectServer`] remain available.
+    ///
+    /// If a name can't be owned again, e.g because another peer took it in the meantime, the
+    /// connection receives a `NameLost` signal for it, as if it came from the bus. The
+    /// [`crate::connection::NameOwnership`] of the name reports it as lost.
+    ///
+    /// While the connection is being re-established, sending messages fails and the method calls
+    /// waiting for a reply fail with the error that caused the loss of the socket. The other
+    /// message streams are kept open. Use [`Connection::receive_state_changes`] to track the state
+    /// of the connection.
+    ///
+    /// This is only supported for connections to an address, as the builder can't open a new
+    /// socket otherwise. [`Builder::build`] fails with [`Error::Unsupported`] if the connection is
+    /// built on a socket or is a server.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// # use zbus::block_on;
+    /// use std::time::Duration;
+    /// use zbus::connection::{Builder, ReconnectPolicy};
+    ///
+    /// # block_on(async {
+    /// let conn = Builder::system()?
+    ///     .reconnect(ReconnectPolicy::new().max_delay(Duration::from_secs(5)))
+    ///     .build()
+    ///     .await?;
+    ///
+    /// // Do something useful with `conn`..
+    /// #     drop(conn);
+    /// #     Ok::<(), zbus::Error>(())
+    /// # }).unwrap();
+    /// #
+    /// # Ok::<_, Box<dyn Error + Send + Sync>>(())
+    /// ```
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+
+        self
+    }
+
     /// Enable or disable the internal executor thread.
     ///
     /// The thread is enabled by default.
@@ -279,6 +452,19 @@ impl<'a> Builder<'a> {
         Ok(self)
     }
 
+    /// Set the [`Authorizer`] checking the method calls before they're dispatched.
+    ///
+    /// This is similar to [`zbus::ObjectServer::set_authorizer`], except that the authorizer is set
+    /// before any interface is served, so no method call can get through unchecked.
+    pub fn authorizer<A>(mut self, authorizer: A) -> Self
+    where
+        A: Authorizer,
+    {
+        self.authorizer = Some(Arc::new(authorizer));
+
+        self
+    }
+
     /// Register a well-known name for this connection on the bus.
     ///
     /// This is similar to [`zbus::Connection::request_name`], except the name is requested as part
@@ -325,24 +511,47 @@ impl<'a> Builder<'a> {
     /// Until server-side bus connection is supported, attempting to build such a connection will
     /// result in [`Error::Unsupported`] error.
     pub async fn build(self) -> Result<Connection> {
+        self.build_with_stream(false).await.map(|(conn, _)| conn)
+    }
+
+    pub(crate) async fn build_with_stream(
+        self,
+        msg_stream: bool,
+    ) -> Result<(Connection, Option<MessageStream>)> {
         let executor = Executor::new();
         #[cfg(not(feature = "tokio"))]
         let internal_executor = self.internal_executor;
         // Box the future as it's large and can cause stack overflow.
-        let conn = Box::pin(executor.run(self.build_(executor.clone()))).await?;
+        let ret = Box::pin(executor.run(self.build_(executor.clone(), msg_stream))).await?;
 
         #[cfg(not(feature = "tokio"))]
         start_internal_executor(&executor, internal_executor)?;
 
-        Ok(conn)
+        Ok(ret)
     }
 
-    async fn build_(mut self, executor: Executor<'static>) -> Result<Connection> {
+    async fn build_(
+        mut self,
+        executor: Executor<'static>,
+        msg_stream: bool,
+    ) -> Result<(Connection, Option<MessageStream>)> {
+        let reconnect = match (self.reconnect, &self.target) {
+            (None, _) => None,
+            (Some(policy), Some(Target::Address(address))) if self.guid.is_none() => {
+                Some(Reconnect {
+                    policy,
+                    address: address.clone(),
+                    auth_mechanisms: self.auth_mechanisms.clone(),
+                    sasl_mechanisms: self.sasl_mechanisms.clone(),
+                })
+            }
+            (Some(_), _) => return Err(Error::Unsupported),
+        };
         let mut stream = self.stream_for_target().await?;
         let mut auth = match self.guid {
             None => {
                 // SASL Handshake
-                Authenticated::client(stream, self.auth_mechanisms).await?
+                Authenticated::client(stream, self.auth_mechanisms, &self.sasl_mechanisms).await?
             }
             Some(guid) => {
                 if !self.p2p {
@@ -363,6 +572,8 @@ impl<'a> Builder<'a> {
                     #[cfg(windows)]
                     client_sid,
                     self.auth_mechanisms,
+                    &self.sasl_mechanisms,
+                    self.peer_policy,
                     self.cookie_id,
                     self.cookie_context.unwrap_or_default(),
                 )
@@ -373,14 +584,25 @@ impl<'a> Builder<'a> {
         let socket_read = auth.socket_read.take().unwrap();
         let already_received_bytes = auth.already_received_bytes.take().unwrap();
 
-        let mut conn = Connection::new(auth, !self.p2p, executor).await?;
+        let mut conn = Connection::new(
+            auth,
+            !self.p2p,
+            self.method_timeout,
+            executor,
+            reconnect,
+            self.interceptors,
+        )
+        .await?;
         conn.set_max_queued(self.max_queued.unwrap_or(DEFAULT_MAX_QUEUED));
         if let Some(unique_name) = self.unique_name {
             conn.set_unique_name(unique_name)?;
         }
 
-        if !self.interfaces.is_empty() {
+        if !self.interfaces.is_empty() || self.authorizer.is_some() {
             let object_server = conn.sync_object_server(false, None);
+            if let Some(authorizer) = self.authorizer {
+                object_server.set_authorizer_ready(authorizer).await;
+            }
             for (path, interfaces) in self.interfaces {
                 for (name, iface) in interfaces {
                     let future = object_server.at_ready(path.to_owned(), name, || iface);
@@ -397,6 +619,9 @@ impl<'a> Builder<'a> {
             listener.await;
         }
 
+        // Subscribe before the socket reader task is started, so we don't miss any messages.
+        let msg_stream = msg_stream.then(|| MessageStream::from(&conn));
+
         // Start the socket reader task.
         conn.init_socket_reader(socket_read, already_received_bytes);
 
@@ -409,7 +634,7 @@ impl<'a> Builder<'a> {
             conn.request_name(name).await?;
         }
 
-        Ok(conn)
+        Ok((conn, msg_stream))
     }
 
     fn new(target: Target) -> Self {
@@ -417,14 +642,20 @@ impl<'a> Builder<'a> {
             target: Some(target),
             p2p: false,
             max_queued: None,
+            method_timeout: None,
             guid: None,
             internal_executor: true,
             interfaces: HashMap::new(),
+            authorizer: None,
             names: HashSet::new(),
             auth_mechanisms: None,
+            sasl_mechanisms: vec![],
+            peer_policy: None,
+            interceptors: vec![],
             unique_name: None,
             cookie_id: None,
             cookie_context: None,
+            reconnect: None,
         }
     }
 
@@ -446,27 +677,40 @@ impl<'a> Builder<'a> {
             Target::VsockStream(stream) => Split::new_boxed(Async::new(stream)?),
             #[cfg(feature = "tokio-vsock")]
             Target::VsockStream(stream) => Split::new_boxed(stream),
-            Target::Address(address) => match address.connect().await? {
-                #[cfg(any(unix, not(feature = "tokio")))]
-                address::Stream::Unix(stream) => Split::new_boxed(stream),
-                address::Stream::Tcp(stream) => Split::new_boxed(stream),
-                #[cfg(any(
-                    all(feature = "vsock", not(feature = "tokio")),
-                    feature = "tokio-vsock"
-                ))]
-                address::Stream::Vsock(stream) => Split::new_boxed(stream),
-            },
+            Target::Address(address) => connect_address(address).await?,
             Target::Socket(stream) => stream,
         })
     }
 }
 
+/// Connect to the first address of `addresses` accepting the connection.
+pub(super) async fn connect_address(addresses: AddressList) -> Result<BoxedSplit> {
+    let (address, stream) = addresses.connect().await?;
+    debug!("Connected to `{address}`");
+
+    Ok(match stream {
+        #[cfg(any(unix, not(feature = "tokio")))]
+        address::Stream::Unix(stream) => Split::new_boxed(stream),
+        address::Stream::Tcp(stream) => Split::new_boxed(stream),
+        #[cfg(unix)]
+        address::Stream::UnixExec(stream) => Split::new_boxed(stream),
+        #[cfg(any(
+            all(feature = "vsock", not(feature = "tokio")),
+            feature = "tokio-vsock"
+        ))]
+        address::Stream::Vsock(stream) => Split::new_boxed(stream),
+    })
+}
+
 /// Start the internal executor thread.
 ///
 /// Returns a dummy task that keep the executor ticking thread from exiting due to absence of any
 /// tasks until socket reader task kicks in.
 #[cfg(not(feature = "tokio"))]
-fn start_internal_executor(executor: &Executor<'static>, internal_executor: bool) -> Result<()> {
+pub(crate) fn start_internal_executor(
+    executor: &Executor<'static>,
+    internal_executor: bool,
+) -> Result<()> {
     if internal_executor {
         let executor = executor.clone();
         std::thread::Builder::new()
@@ -7,8 +7,9 @@ use std::{
     fmt::{self, Debug},
     path::PathBuf,
     str::FromStr,
+    sync::Arc,
 };
-use tracing::{instrument, trace};
+use tracing::{debug, instrument, trace};
 use zvariant::Str;
 
 use sha1::{Digest, Sha1};
@@ -19,7 +20,11 @@ use xdg_home::home_dir;
 use crate::win32;
 use crate::{file::FileLines, guid::Guid, Error, Result};
 
-use super::socket::{BoxedSplit, ReadHalf, WriteHalf};
+use super::{
+    peer_policy::{AuthenticatedPeer, PeerPolicy},
+    sasl::{SaslClient, SaslMechanism, SaslServer, SaslServerStep},
+    socket::{BoxedSplit, ReadHalf, WriteHalf},
+};
 
 /// Authentication mechanisms
 ///
@@ -40,6 +45,32 @@ pub enum AuthMechanism {
     Anonymous,
 }
 
+impl AuthMechanism {
+    fn name(&self) -> &'static str {
+        match self {
+            AuthMechanism::External => "EXTERNAL",
+            AuthMechanism::Cookie => "DBUS_COOKIE_SHA1",
+            AuthMechanism::Anonymous => "ANONYMOUS",
+        }
+    }
+}
+
+// A mechanism to authenticate with, either built-in or a custom `SaslMechanism`.
+#[derive(Clone, Debug)]
+enum Mechanism {
+    BuiltIn(AuthMechanism),
+    Custom(Arc<dyn SaslMechanism>),
+}
+
+impl Mechanism {
+    fn name(&self) -> &str {
+        match self {
+            Mechanism::BuiltIn(mech) => mech.name(),
+            Mechanism::Custom(mech) => mech.name(),
+        }
+    }
+}
+
 /// The result of a finalized handshake
 ///
 /// The result of a finalized [`ClientHandshake`] or [`ServerHandshake`]. It can be passed to
@@ -66,19 +97,26 @@ impl Authenticated {
     pub async fn client(
         socket: BoxedSplit,
         mechanisms: Option<VecDeque<AuthMechanism>>,
+        sasl_mechanisms: &[Arc<dyn SaslMechanism>],
     ) -> Result<Self> {
-        ClientHandshake::new(socket, mechanisms).perform().await
+        ClientHandshake::new(socket, mechanisms)
+            .sasl_mechanisms(sasl_mechanisms)
+            .perform()
+            .await
     }
 
     /// Create a server-side `Authenticated` for the given `socket`.
     ///
     /// The function takes `client_uid` on Unix only. On Windows, it takes `client_sid` instead.
+    #[allow(clippy::too_many_arguments)]
     pub async fn server(
         socket: BoxedSplit,
         guid: Guid,
         #[cfg(unix)] client_uid: Option<u32>,
         #[cfg(windows)] client_sid: Option<String>,
         auth_mechanisms: Option<VecDeque<AuthMechanism>>,
+        sasl_mechanisms: &[Arc<dyn SaslMechanism>],
+        peer_policy: Option<PeerPolicy>,
         cookie_id: Option<usize>,
         cookie_context: CookieContext<'_>,
     ) -> Result<Self> {
@@ -93,6 +131,8 @@ impl Authenticated {
             cookie_id,
             cookie_context,
         )?
+        .sasl_mechanisms(sasl_mechanisms)
+        .peer_policy(peer_policy)
         .perform()
         .await
     }
@@ -120,13 +160,13 @@ enum ClientHandshakeStep {
 #[derive(Debug)]
 #[allow(clippy::upper_case_acronyms)]
 enum Command {
-    Auth(Option<AuthMechanism>, Option<Vec<u8>>),
+    Auth(Option<String>, Option<Vec<u8>>),
     Cancel,
     Begin,
     Data(Option<Vec<u8>>),
     Error(String),
     NegotiateUnixFD,
-    Rejected(Vec<AuthMechanism>),
+    Rejected(Vec<String>),
     Ok(Guid),
     AgreeUnixFD,
 }
@@ -148,6 +188,8 @@ enum Command {
 pub struct ClientHandshake {
     common: HandshakeCommon,
     step: ClientHandshakeStep,
+    // The client side of the current mechanism, if it's a custom one.
+    sasl_client: Option<Box<dyn SaslClient>>,
 }
 
 #[async_trait]
@@ -173,32 +215,81 @@ impl ClientHandshake {
         ClientHandshake {
             common: HandshakeCommon::new(socket, mechanisms, None),
             step: ClientHandshakeStep::Init,
+            sasl_client: None,
         }
     }
 
-    fn mechanism_init(&mut self) -> Result<(ClientHandshakeStep, Command)> {
+    /// Also try the given custom mechanisms, before the built-in ones.
+    pub fn sasl_mechanisms(mut self, mechanisms: &[Arc<dyn SaslMechanism>]) -> Self {
+        self.common.add_sasl_mechanisms(mechanisms);
+
+        self
+    }
+
+    async fn mechanism_init(&mut self) -> Result<(ClientHandshakeStep, Command)> {
         use ClientHandshakeStep::*;
-        let mech = self.common.mechanism()?;
-        match mech {
-            AuthMechanism::Anonymous => Ok((
-                WaitingForOK,
-                Command::Auth(Some(*mech), Some("zbus".into())),
-            )),
-            AuthMechanism::External => Ok((
-                WaitingForOK,
-                Command::Auth(Some(*mech), Some(sasl_auth_id()?.into_bytes())),
-            )),
-            AuthMechanism::Cookie => Ok((
-                WaitingForData,
-                Command::Auth(Some(*mech), Some(sasl_auth_id()?.into_bytes())),
-            )),
+        loop {
+            let mech = match self.common.mechanism()? {
+                Mechanism::BuiltIn(mech) => *mech,
+                Mechanism::Custom(mech) => {
+                    let mech = mech.clone();
+                    let name = mech.name().to_string();
+                    match mech.client() {
+                        Ok(mut client) => {
+                            let response = client.initial_response().await?;
+                            self.sasl_client = Some(client);
+
+                            return Ok((WaitingForOK, Command::Auth(Some(name), response)));
+                        }
+                        Err(e) => {
+                            debug!("Skipping `{name}` mechanism: {e}");
+                            self.common.mechanisms.pop_front();
+
+                            continue;
+                        }
+                    }
+                }
+            };
+            let name = Some(mech.to_string());
+
+            return match mech {
+                AuthMechanism::Anonymous => {
+                    Ok((WaitingForOK, Command::Auth(name, Some("zbus".into()))))
+                }
+                AuthMechanism::External => Ok((
+                    WaitingForOK,
+                    Command::Auth(name, Some(sasl_auth_id()?.into_bytes())),
+                )),
+                AuthMechanism::Cookie => Ok((
+                    WaitingForData,
+                    Command::Auth(name, Some(sasl_auth_id()?.into_bytes())),
+                )),
+            };
         }
     }
 
-    async fn mechanism_data(&mut self, data: Vec<u8>) -> Result<(ClientHandshakeStep, Command)> {
+    async fn mechanism_data(
+        &mut self,
+        data: Option<Vec<u8>>,
+    ) -> Result<(ClientHandshakeStep, Command)> {
         let mech = self.common.mechanism()?;
         match mech {
-            AuthMechanism::Cookie => {
+            Mechanism::Custom(_) => {
+                let client = self
+                    .sasl_client
+                    .as_mut()
+                    .ok_or_else(|| Error::Handshake("Unexpected mechanism DATA".into()))?;
+                let response = client.step(&data.unwrap_or_default()).await?;
+
+                Ok((
+                    ClientHandshakeStep::WaitingForOK,
+                    Command::Data(Some(response)),
+                ))
+            }
+            Mechanism::BuiltIn(AuthMechanism::Cookie) => {
+                let data = data.ok_or_else(|| {
+                    Error::Handshake("Received DATA with no data from server".into())
+                })?;
                 let context = std::str::from_utf8(&data)
                     .map_err(|_| Error::Handshake("Cookie context was not valid UTF-8".into()))?;
                 let mut split = context.split_ascii_whitespace();
@@ -231,7 +322,7 @@ impl ClientHandshake {
     }
 }
 
-fn random_ascii(len: usize) -> String {
+pub(crate) fn random_ascii(len: usize) -> String {
     use rand::{distributions::Alphanumeric, thread_rng, Rng};
     use std::iter;
 
@@ -386,7 +477,7 @@ impl Handshake for ClientHandshake {
                 Init => {
                     trace!("Initializing");
                     #[allow(clippy::let_and_return)]
-                    let ret = self.mechanism_init()?;
+                    let ret = self.mechanism_init().await?;
                     // The dbus daemon on some platforms requires sending the zero byte as a
                     // separate message with SCM_CREDS.
                     #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
@@ -432,7 +523,7 @@ impl Handshake for ClientHandshake {
                 }
                 MechanismInit => {
                     trace!("Initializing auth mechanisms");
-                    self.mechanism_init()?
+                    self.mechanism_init().await?
                 }
                 WaitingForData | WaitingForOK => {
                     trace!("Waiting for DATA or OK from server");
@@ -440,14 +531,12 @@ impl Handshake for ClientHandshake {
                     match (self.step, reply) {
                         (_, Command::Data(data)) => {
                             trace!("Received DATA from server");
-                            let data = data.ok_or_else(|| {
-                                Error::Handshake("Received DATA with no data from server".into())
-                            })?;
                             self.mechanism_data(data).await?
                         }
                         (_, Command::Rejected(_)) => {
                             trace!("Received REJECT from server. Will try next auth mechanism..");
                             self.common.mechanisms.pop_front();
+                            self.sasl_client = None;
                             self.step = MechanismInit;
                             continue;
                         }
@@ -516,6 +605,8 @@ enum ServerHandshakeStep {
     WaitingForNull,
     WaitingForAuth,
     WaitingForData(AuthMechanism),
+    // Waiting for the response to a challenge of a custom mechanism.
+    WaitingForSaslData,
     WaitingForBegin,
     Done,
 }
@@ -535,7 +626,8 @@ enum ServerHandshakeStep {
 /// [`try_finish`]: struct.ServerHandshake.html#method.try_finish
 /// [`Authenticated`]: struct.Authenticated.html
 /// [`Connection::new_authenticated`]: ../struct.Connection.html#method.new_authenticated
-#[derive(Debug)]
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
 pub struct ServerHandshake<'s> {
     common: HandshakeCommon,
     step: ServerHandshakeStep,
@@ -545,6 +637,13 @@ pub struct ServerHandshake<'s> {
     client_sid: Option<String>,
     cookie_id: Option<usize>,
     cookie_context: CookieContext<'s>,
+    // The server side of the current mechanism, if it's a custom one.
+    sasl_server: Option<Box<dyn SaslServer>>,
+    #[derivative(Debug = "ignore")]
+    peer_policy: Option<PeerPolicy>,
+    // The mechanism and identity the client is authenticating with, for the peer policy.
+    claimed_mechanism: Option<String>,
+    claimed_identity: Option<String>,
 }
 
 impl<'s> ServerHandshake<'s> {
@@ -576,10 +675,41 @@ impl<'s> ServerHandshake<'s> {
             client_sid,
             cookie_id,
             cookie_context,
+            sasl_server: None,
+            peer_policy: None,
+            claimed_mechanism: None,
+            claimed_identity: None,
         })
     }
 
+    /// Only accept the clients `policy` resolves to `true` for, once they're authenticated.
+    pub fn peer_policy(mut self, policy: Option<PeerPolicy>) -> Self {
+        self.peer_policy = policy;
+
+        self
+    }
+
+    /// Also accept the given custom mechanisms.
+    pub fn sasl_mechanisms(mut self, mechanisms: &[Arc<dyn SaslMechanism>]) -> Self {
+        self.common.add_sasl_mechanisms(mechanisms);
+
+        self
+    }
+
     async fn auth_ok(&mut self) -> Result<()> {
+        if let Some(policy) = self.peer_policy.clone() {
+            let peer = AuthenticatedPeer {
+                credentials: self.common.socket.read_mut().peer_credentials().await?,
+                mechanism: self.claimed_mechanism.clone().unwrap_or_default(),
+                identity: self.claimed_identity.clone(),
+            };
+            if !policy(peer).await {
+                trace!("Peer rejected by the policy");
+
+                return self.rejected_error().await;
+            }
+        }
+
         let cmd = Command::Ok(self.guid().clone());
         trace!("Sending authentication OK");
         self.common.write_command(cmd).await?;
@@ -589,9 +719,17 @@ impl<'s> ServerHandshake<'s> {
     }
 
     async fn check_external_auth(&mut self, sasl_id: &[u8]) -> Result<()> {
+        if sasl_id.is_empty() {
+            // No identity claimed, the one from the credentials is used. Same as an empty `DATA`.
+            self.claimed_identity = None;
+
+            return self.auth_ok().await;
+        }
+
         let auth_ok = {
             let id = std::str::from_utf8(sasl_id)
                 .map_err(|e| Error::Handshake(format!("Invalid ID: {e}")))?;
+            self.claimed_identity = Some(id.to_string());
             #[cfg(unix)]
             {
                 let uid = id
@@ -628,6 +766,7 @@ impl<'s> ServerHandshake<'s> {
             self.rejected_error().await?;
             return Ok(());
         }
+        self.claimed_identity = Some(id.to_string());
         let server_challenge = random_ascii(16);
         let data = format!("{} {} {server_challenge}", self.cookie_context.0, cookie.id);
         let cmd = Command::Data(Some(data.into_bytes()));
@@ -660,17 +799,72 @@ impl<'s> ServerHandshake<'s> {
         }
     }
 
+    async fn start_sasl_auth(
+        &mut self,
+        mech: &dyn SaslMechanism,
+        response: Option<&[u8]>,
+    ) -> Result<()> {
+        match mech.server() {
+            Ok(server) => {
+                self.sasl_server = Some(server);
+
+                self.sasl_auth_step(response).await
+            }
+            Err(e) => {
+                debug!("Rejecting `{}` mechanism: {e}", mech.name());
+
+                self.rejected_error().await
+            }
+        }
+    }
+
+    async fn sasl_auth_step(&mut self, response: Option<&[u8]>) -> Result<()> {
+        let server = match self.sasl_server.as_mut() {
+            Some(server) => server,
+            None => return self.rejected_error().await,
+        };
+        match server.step(response).await {
+            Ok(SaslServerStep::Challenge(challenge)) => {
+                trace!("Sending SASL challenge");
+                self.common
+                    .write_command(Command::Data(Some(challenge)))
+                    .await?;
+                self.step = ServerHandshakeStep::WaitingForSaslData;
+
+                Ok(())
+            }
+            Ok(SaslServerStep::Accept) => {
+                self.sasl_server = None;
+
+                self.auth_ok().await
+            }
+            Ok(SaslServerStep::Reject) => self.rejected_error().await,
+            Err(e) => {
+                debug!("SASL authentication failed: {e}");
+
+                self.rejected_error().await
+            }
+        }
+    }
+
     async fn unsupported_command_error(&mut self) -> Result<()> {
         let cmd = Command::Error("Unsupported command".to_string());
         trace!("Sending authentication error");
         self.common.write_command(cmd).await?;
         self.step = ServerHandshakeStep::WaitingForAuth;
+        self.sasl_server = None;
 
         Ok(())
     }
 
     async fn rejected_error(&mut self) -> Result<()> {
-        let mechanisms = self.common.mechanisms.iter().cloned().collect();
+        self.sasl_server = None;
+        let mechanisms = self
+            .common
+            .mechanisms
+            .iter()
+            .map(|m| m.name().to_string())
+            .collect();
         let cmd = Command::Rejected(mechanisms);
         trace!("Sending authentication error");
         self.common.write_command(cmd).await?;
@@ -715,22 +909,33 @@ impl Handshake for ServerHandshake<'_> {
                     let reply = self.common.read_command().await?;
                     match reply {
                         Command::Auth(mech, resp) => {
-                            let mech = mech.filter(|m| self.common.mechanisms.contains(m));
+                            let mech = mech.and_then(|name| {
+                                self.common
+                                    .mechanisms
+                                    .iter()
+                                    .find(|m| m.name() == name)
+                                    .cloned()
+                            });
+                            self.claimed_mechanism = mech.as_ref().map(|m| m.name().to_string());
+                            self.claimed_identity = None;
 
                             match (mech, &resp) {
-                                (Some(mech), None) => {
+                                (Some(Mechanism::Custom(mech)), resp) => {
+                                    self.start_sasl_auth(&*mech, resp.as_deref()).await?;
+                                }
+                                (Some(Mechanism::BuiltIn(mech)), None) => {
                                     trace!("Sending data request");
                                     self.common.write_command(Command::Data(None)).await?;
                                     self.step = ServerHandshakeStep::WaitingForData(mech);
                                 }
-                                (Some(AuthMechanism::Anonymous), Some(_)) => {
+                                (Some(Mechanism::BuiltIn(AuthMechanism::Anonymous)), Some(_)) => {
                                     self.auth_ok().await?;
                                 }
-                                (Some(AuthMechanism::External), Some(sasl_id)) => {
-                                    self.check_external_auth(sasl_id).await?;
+                                (Some(Mechanism::BuiltIn(AuthMechanism::External)), Some(id)) => {
+                                    self.check_external_auth(id).await?;
                                 }
-                                (Some(AuthMechanism::Cookie), Some(sasl_id)) => {
-                                    self.check_cookie_auth(sasl_id).await?;
+                                (Some(Mechanism::BuiltIn(AuthMechanism::Cookie)), Some(id)) => {
+                                    self.check_cookie_auth(id).await?;
                                 }
                                 _ => self.rejected_error().await?,
                             }
@@ -757,6 +962,23 @@ impl Handshake for ServerHandshake<'_> {
                         (_, _) => self.unsupported_command_error().await?,
                     }
                 }
+                ServerHandshakeStep::WaitingForSaslData => {
+                    trace!("Waiting for the response to the SASL challenge");
+                    let reply = self.common.read_command().await?;
+                    match reply {
+                        Command::Data(data) => {
+                            let data = data.unwrap_or_default();
+                            self.sasl_auth_step(Some(&data)).await?;
+                        }
+                        Command::Cancel | Command::Error(_) => self.rejected_error().await?,
+                        Command::Begin => {
+                            return Err(Error::Handshake(
+                                "Received BEGIN while not authenticated".to_string(),
+                            ));
+                        }
+                        _ => self.unsupported_command_error().await?,
+                    }
+                }
                 ServerHandshakeStep::WaitingForBegin => {
                     trace!("Waiting for Begin command from the client");
                     let reply = self.common.read_command().await?;
@@ -811,12 +1033,7 @@ impl Handshake for ServerHandshake<'_> {
 
 impl fmt::Display for AuthMechanism {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mech = match self {
-            AuthMechanism::External => "EXTERNAL",
-            AuthMechanism::Cookie => "DBUS_COOKIE_SHA1",
-            AuthMechanism::Anonymous => "ANONYMOUS",
-        };
-        write!(f, "{mech}")
+        f.write_str(self.name())
     }
 }
 
@@ -856,15 +1073,7 @@ impl fmt::Display for Command {
             Command::Error(expl) => write!(f, "ERROR {expl}"),
             Command::NegotiateUnixFD => write!(f, "NEGOTIATE_UNIX_FD"),
             Command::Rejected(mechs) => {
-                write!(
-                    f,
-                    "REJECTED {}",
-                    mechs
-                        .iter()
-                        .map(|m| m.to_string())
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                )
+                write!(f, "REJECTED {}", mechs.join(" "))
             }
             Command::Ok(guid) => write!(f, "OK {guid}"),
             Command::AgreeUnixFD => write!(f, "AGREE_UNIX_FD"),
@@ -886,11 +1095,7 @@ impl FromStr for Command {
         let mut words = s.split_ascii_whitespace();
         let cmd = match words.next() {
             Some("AUTH") => {
-                let mech = if let Some(m) = words.next() {
-                    Some(m.parse()?)
-                } else {
-                    None
-                };
+                let mech = words.next().map(String::from);
                 let resp = match words.next() {
                     Some(resp) => Some(hex::decode(resp)?),
                     None => None,
@@ -910,7 +1115,7 @@ impl FromStr for Command {
             Some("ERROR") => Command::Error(s.into()),
             Some("NEGOTIATE_UNIX_FD") => Command::NegotiateUnixFD,
             Some("REJECTED") => {
-                let mechs = words.map(|m| m.parse()).collect::<Result<_>>()?;
+                let mechs = words.map(String::from).collect();
                 Command::Rejected(mechs)
             }
             Some("OK") => {
@@ -934,7 +1139,7 @@ pub struct HandshakeCommon {
     server_guid: Option<Guid>,
     cap_unix_fd: bool,
     // the current AUTH mechanism is front, ordered by priority
-    mechanisms: VecDeque<AuthMechanism>,
+    mechanisms: VecDeque<Mechanism>,
 }
 
 impl HandshakeCommon {
@@ -949,7 +1154,14 @@ impl HandshakeCommon {
             recv_buffer: Vec::new(),
             server_guid,
             cap_unix_fd: false,
-            mechanisms,
+            mechanisms: mechanisms.into_iter().map(Mechanism::BuiltIn).collect(),
+        }
+    }
+
+    // Custom mechanisms come first: they're only registered to be used.
+    fn add_sasl_mechanisms(&mut self, mechanisms: &[Arc<dyn SaslMechanism>]) {
+        for mech in mechanisms.iter().rev() {
+            self.mechanisms.push_front(Mechanism::Custom(mech.clone()));
         }
     }
 
@@ -1016,7 +1228,7 @@ impl HandshakeCommon {
         line.parse()
     }
 
-    fn mechanism(&self) -> Result<&AuthMechanism> {
+    fn mechanism(&self) -> Result<&Mechanism> {
         self.mechanisms
             .front()
             .ok_or_else(|| Error::Handshake("Exhausted available AUTH mechanisms".into()))
@@ -1161,6 +1373,25 @@ mod tests {
         crate::utils::block_on(server.perform()).unwrap();
     }
 
+    #[test]
+    #[timeout(15000)]
+    fn empty_external_identity() {
+        let (_p0, p1) = create_async_socket_pair();
+        let mut server = ServerHandshake::new(
+            Split::new_boxed(p1),
+            Guid::generate(),
+            Some(Uid::effective().into()),
+            None,
+            None,
+            CookieContext::default(),
+        )
+        .unwrap();
+
+        crate::utils::block_on(server.check_external_auth(b"")).unwrap();
+        assert!(server.claimed_identity.is_none());
+        assert!(matches!(server.step, ServerHandshakeStep::WaitingForBegin));
+    }
+
     #[test]
     #[timeout(15000)]
     fn anonymous_handshake() {
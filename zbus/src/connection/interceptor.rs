@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use std::{fmt, sync::Arc};
+
+use crate::Message;
+
+/// Intercepts the messages going through a [`Connection`].
+///
+/// Interceptors are registered through [`Builder::interceptor`]. They see every message sent on
+/// the connection before it's written to the socket, and every message received before it's
+/// dispatched to the [`MessageStream`]s and the [`ObjectServer`]. This includes the messages zbus
+/// exchanges on its own, e.g with the bus.
+///
+/// Both methods let the message through as is by default, so an interceptor only needs to
+/// implement the direction it's interested in.
+///
+/// # Example
+///
+/// Logging the method calls sent, and failing the ones to a specific method without sending them:
+///
+/// ```no_run
+///# zbus::block_on(async {
+/// use zbus::{
+///     connection::{Builder, Interception, Interceptor},
+///     message::Type,
+///     Message,
+/// };
+///
+/// let _conn = Builder::session()?
+///     .interceptor(Outgoing)
+///     .build()
+///     .await?;
+///
+/// struct Outgoing;
+///
+/// #[async_trait::async_trait]
+/// impl Interceptor for Outgoing {
+///     async fn outgoing(&self, msg: Message) -> Interception {
+///         if msg.message_type() != Type::MethodCall {
+///             return Interception::Continue(msg);
+///         }
+///
+///         let header = msg.header();
+///         println!("Calling {:?}", header.member());
+///         if header.member().map_or(false, |m| m == "Frobnicate") {
+///             let reply = Message::method_error(&msg, "org.example.Error.Injected")
+///                 .and_then(|builder| builder.build(&()));
+///             if let Ok(reply) = reply {
+///                 return Interception::Reply(reply);
+///             }
+///         }
+///
+///         Interception::Continue(msg)
+///     }
+/// }
+///# Ok::<(), zbus::Error>(())
+///# }).unwrap();
+/// ```
+///
+/// [`Connection`]: crate::Connection
+/// [`Builder::interceptor`]: super::Builder::interceptor
+/// [`MessageStream`]: crate::MessageStream
+/// [`ObjectServer`]: crate::ObjectServer
+#[async_trait]
+pub trait Interceptor: Send + Sync + 'static {
+    /// Intercept a message about to be sent.
+    ///
+    /// Replying to the message with [`Interception::Reply`] doesn't send it. Instead, the reply is
+    /// handled as if it had been received from the peer.
+    async fn outgoing(&self, msg: Message) -> Interception {
+        Interception::Continue(msg)
+    }
+
+    /// Intercept a message just received.
+    ///
+    /// Replying to the message with [`Interception::Reply`] sends the reply to the peer, instead of
+    /// dispatching the message.
+    async fn incoming(&self, msg: Message) -> Interception {
+        Interception::Continue(msg)
+    }
+}
+
+/// What an [`Interceptor`] does with a message.
+#[derive(Debug, Clone)]
+pub enum Interception {
+    /// Let the message, possibly rewritten, through to the next interceptor.
+    Continue(Message),
+    /// Drop the message.
+    Drop,
+    /// Answer the message with this reply, and drop it.
+    Reply(Message),
+}
+
+impl fmt::Debug for dyn Interceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Interceptor")
+    }
+}
+
+/// Pass `msg` through the `interceptors` chain, in order.
+pub(crate) async fn intercept(
+    interceptors: &[Arc<dyn Interceptor>],
+    mut msg: Message,
+    outgoing: bool,
+) -> Interception {
+    for interceptor in interceptors {
+        let interception = if outgoing {
+            interceptor.outgoing(msg).await
+        } else {
+            interceptor.incoming(msg).await
+        };
+        msg = match interception {
+            Interception::Continue(msg) => msg,
+            interception => return interception,
+        };
+    }
+
+    Interception::Continue(msg)
+}
@@ -0,0 +1,1374 @@
+//! Accepting peer-to-peer connections from multiple clients.
+
+#[cfg(not(feature = "tokio"))]
+use async_io::Async;
+use futures_core::Stream;
+use futures_util::{
+    future::{select, Either},
+    stream::FuturesUnordered,
+    StreamExt,
+};
+use static_assertions::assert_impl_all;
+use std::{
+    future::Future,
+    io,
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tracing::debug;
+use zvariant::{OwnedObjectPath, Str};
+
+use crate::{
+    address::{Address, TcpAddress},
+    object_server::Interface,
+    utils::sleep,
+    AuthMechanism, Connection, Error, Guid, MessageStream, Result,
+};
+
+use super::{
+    handshake::{random_ascii, CookieContext},
+    interceptor::Interceptor,
+    peer_policy::{AuthenticatedPeer, PeerPolicy},
+    sasl::SaslMechanism,
+};
+
+type Handshake = Pin<Box<dyn Future<Output = Result<(Connection, Option<MessageStream>)>> + Send>>;
+// The default time clients get to complete the handshake, as in the reference implementation.
+const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+type ServeAt =
+    Box<dyn Fn(super::Builder<'static>) -> Result<super::Builder<'static>> + Send + Sync>;
+
+/// A listener for peer-to-peer connections.
+///
+/// `Listener` binds an [`Address`] and accepts clients on it. Each client is authenticated, using
+/// the same GUID and authentication configuration for all clients, and the resulting
+/// [`Connection`]s are yielded by the [`Stream`] implementation of `Listener`. Clients are
+/// authenticated concurrently so a slow client doesn't hold up the others, and clients that don't
+/// complete the handshake in time are dropped (see [`Builder::handshake_timeout`]).
+///
+/// Errors while accepting or authenticating a client are yielded as `Err` items and the stream
+/// keeps going afterwards. The stream never ends.
+///
+/// Services started through systemd socket activation can listen on the socket systemd passed them
+/// through [`Builder::systemd`] or [`Builder::systemd_named`].
+///
+/// # Example
+///
+/// ```
+///# use std::error::Error;
+///# zbus::block_on(async {
+/// use futures_util::StreamExt;
+/// use zbus::connection::{self, Listener};
+///
+/// let dir = std::env::temp_dir();
+/// let mut listener = Listener::bind(format!("unix:tmpdir={}", dir.display()).as_str()).await?;
+/// let address = listener.address().clone();
+///
+/// let (client, server) = futures_util::try_join!(
+///     connection::Builder::address(address.clone())?
+///         .p2p()
+///         .build(),
+///     async { listener.next().await.unwrap() },
+/// )?;
+/// assert_eq!(client.server_guid(), *listener.guid());
+///# drop(server);
+///# Ok::<(), Box<dyn Error + Send + Sync>>(())
+///# }).unwrap();
+/// ```
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+pub struct Listener {
+    socket: ListeningSocket,
+    address: Address,
+    guid: Guid,
+    auth_mechanisms: Vec<AuthMechanism>,
+    sasl_mechanisms: Arc<Vec<Arc<dyn SaslMechanism>>>,
+    #[derivative(Debug = "ignore")]
+    peer_policy: Option<PeerPolicy>,
+    interceptors: Arc<Vec<Arc<dyn Interceptor>>>,
+    cookie_context: Option<Str<'static>>,
+    cookie_id: Option<usize>,
+    max_queued: Option<usize>,
+    handshake_timeout: Duration,
+    msg_stream: bool,
+    #[derivative(Debug = "ignore")]
+    serve_at: Arc<Vec<ServeAt>>,
+    #[derivative(Debug = "ignore")]
+    handshakes: FuturesUnordered<Handshake>,
+}
+
+assert_impl_all!(Listener: Send, Unpin);
+
+impl Listener {
+    /// Bind the given address, with the default configuration.
+    ///
+    /// See [`Builder::new`] for the supported addresses.
+    pub async fn bind<A>(address: A) -> Result<Self>
+    where
+        A: TryInto<Address>,
+        A::Error: Into<Error>,
+    {
+        Builder::new(address)?.build().await
+    }
+
+    /// Create a builder for a listener on the given address.
+    ///
+    /// See [`Builder::new`] for the supported addresses.
+    pub fn builder<A>(address: A) -> Result<Builder>
+    where
+        A: TryInto<Address>,
+        A::Error: Into<Error>,
+    {
+        Builder::new(address)
+    }
+
+    /// The address clients can connect to.
+    ///
+    /// This differs from the address the listener was created with when the latter was a
+    /// listenable address, e.g `unix:tmpdir=` or a `tcp:` address with a `port` of `0`. For
+    /// `nonce-tcp:` listeners, it includes the `noncefile` clients have to read the nonce from.
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// The GUID shared by all connections accepted by this listener.
+    pub fn guid(&self) -> &Guid {
+        &self.guid
+    }
+
+    /// Accept the next client, along with a stream of all the messages it sends.
+    ///
+    /// The listener must have been built with [`Builder::message_stream`].
+    pub(crate) async fn accept_with_stream(&mut self) -> Result<(Connection, MessageStream)> {
+        let (conn, stream) = futures_util::future::poll_fn(|cx| self.poll_accept(cx)).await?;
+        let stream = stream.ok_or_else(|| {
+            Error::Failure("Listener wasn't built with `Builder::message_stream`".into())
+        })?;
+
+        Ok((conn, stream))
+    }
+
+    /// Accept the next client, along with a stream of all the messages it sends if the listener was
+    /// built with [`Builder::message_stream`].
+    pub(crate) fn poll_accept(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(Connection, Option<MessageStream>)>> {
+        loop {
+            match self.socket.poll_accept(cx) {
+                Poll::Ready(Ok(incoming)) => self.handshake(incoming),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                Poll::Pending => break,
+            }
+        }
+
+        match self.handshakes.poll_next_unpin(cx) {
+            Poll::Ready(Some(res)) => Poll::Ready(res),
+            // No handshakes in progress.
+            Poll::Ready(None) | Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn handshake(&mut self, incoming: Incoming) {
+        let serve_at = self.serve_at.clone();
+        let guid = self.guid.clone();
+        let auth_mechanisms = self.auth_mechanisms.clone();
+        let sasl_mechanisms = self.sasl_mechanisms.clone();
+        let peer_policy = self.peer_policy.clone();
+        let interceptors = self.interceptors.clone();
+        let cookie_context = self.cookie_context.clone();
+        let cookie_id = self.cookie_id;
+        let max_queued = self.max_queued;
+        let handshake_timeout = self.handshake_timeout;
+        let msg_stream = self.msg_stream;
+
+        let handshake = async move {
+            let builder = match incoming {
+                Incoming::Stream(builder) => *builder,
+                Incoming::NonceTcp(stream, nonce) => {
+                    super::Builder::tcp_stream(verify_nonce(stream, &nonce).await?)
+                }
+            };
+            let mut builder = serve_at
+                .iter()
+                .try_fold(builder, |builder, serve_at| serve_at(builder))?
+                .server(&guid)
+                .p2p()
+                .auth_mechanisms(&auth_mechanisms)
+                .shared_sasl_mechanisms(&sasl_mechanisms)
+                .shared_interceptors(&interceptors);
+            if let Some(policy) = peer_policy {
+                builder = builder.peer_policy(move |peer| policy(peer));
+            }
+            if let Some(cookie_context) = cookie_context {
+                builder = builder.cookie_context(cookie_context)?;
+            }
+            if let Some(cookie_id) = cookie_id {
+                builder = builder.cookie_id(cookie_id);
+            }
+            if let Some(max_queued) = max_queued {
+                builder = builder.max_queued(max_queued);
+            }
+
+            builder.build_with_stream(msg_stream).await
+        };
+        self.handshakes.push(Box::pin(async move {
+            match select(Box::pin(handshake), sleep(handshake_timeout)).await {
+                Either::Left((res, _)) => res,
+                Either::Right(_) => Err(Error::Handshake(format!(
+                    "Client didn't complete the handshake within {handshake_timeout:?}"
+                ))),
+            }
+        }));
+    }
+}
+
+impl Stream for Listener {
+    type Item = Result<Connection>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_accept(cx)
+            .map(|res| Some(res.map(|(conn, _)| conn)))
+    }
+}
+
+/// A builder for [`Listener`].
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+#[must_use]
+pub struct Builder {
+    socket: Socket,
+    guid: Option<Guid>,
+    auth_mechanisms: Option<Vec<AuthMechanism>>,
+    sasl_mechanisms: Vec<Arc<dyn SaslMechanism>>,
+    #[derivative(Debug = "ignore")]
+    peer_policy: Option<PeerPolicy>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    cookie_context: Option<Str<'static>>,
+    cookie_id: Option<usize>,
+    max_queued: Option<usize>,
+    handshake_timeout: Option<Duration>,
+    msg_stream: bool,
+    #[derivative(Debug = "ignore")]
+    serve_at: Vec<ServeAt>,
+}
+
+assert_impl_all!(Builder: Send, Sync, Unpin);
+
+impl Builder {
+    /// Create a builder for a listener on the given address.
+    ///
+    /// Supported addresses are `unix:path=`, `unix:abstract=` (on Linux), `unix:dir=`,
+    /// `unix:tmpdir=`, `tcp:`, `nonce-tcp:` and `vsock:` (if the `vsock` or `tokio-vsock` feature
+    /// is enabled). A `port` of `0` in a `tcp:` or `nonce-tcp:` address will let the OS choose a
+    /// free port.
+    ///
+    /// For `nonce-tcp:` addresses, a random nonce is written to the `noncefile`, which must not
+    /// exist yet. The file is only readable by the current user (on unix) and is removed once the
+    /// listener is dropped. Clients that don't send the nonce first are rejected.
+    pub fn new<A>(address: A) -> Result<Self>
+    where
+        A: TryInto<Address>,
+        A::Error: Into<Error>,
+    {
+        let address = address.try_into().map_err(Into::into)?;
+
+        Ok(Self::with_socket(Socket::Address(address)))
+    }
+
+    /// Create a builder for a listener on a socket passed by systemd, through socket activation.
+    ///
+    /// `index` is the position of the socket among the ones passed to the process, i.e the order
+    /// of the `ListenStream=` directives in the socket unit. Only unix and TCP stream sockets are
+    /// supported.
+    ///
+    /// # Errors
+    ///
+    /// If systemd didn't pass a socket at `index` to this process, or if it was already taken by
+    /// another listener. [`Builder::build`] fails if the socket isn't a listening stream socket.
+    #[cfg(unix)]
+    pub fn systemd(index: usize) -> Result<Self> {
+        let fd = systemd_socket_fd(SystemdSocket::Index(index), |var| std::env::var(var).ok())?;
+
+        take_fd(fd).map(|fd| Self::with_socket(Socket::Fd(fd)))
+    }
+
+    /// Create a builder for a listener on a socket passed by systemd, through socket activation.
+    ///
+    /// `name` is the name of the socket, as set by the `FileDescriptorName=` directive of the
+    /// socket unit (the name of the unit by default).
+    ///
+    /// See [`Builder::systemd`] for details.
+    #[cfg(unix)]
+    pub fn systemd_named(name: &str) -> Result<Self> {
+        let fd = systemd_socket_fd(SystemdSocket::Name(name), |var| std::env::var(var).ok())?;
+
+        take_fd(fd).map(|fd| Self::with_socket(Socket::Fd(fd)))
+    }
+
+    fn with_socket(socket: Socket) -> Self {
+        Self {
+            socket,
+            guid: None,
+            auth_mechanisms: None,
+            sasl_mechanisms: vec![],
+            peer_policy: None,
+            interceptors: vec![],
+            cookie_context: None,
+            cookie_id: None,
+            max_queued: None,
+            handshake_timeout: None,
+            msg_stream: false,
+            serve_at: vec![],
+        }
+    }
+
+    /// The GUID to use for all connections.
+    ///
+    /// If not set, a random one is generated.
+    pub fn guid(mut self, guid: Guid) -> Self {
+        self.guid = Some(guid);
+
+        self
+    }
+
+    /// Specify the mechanisms to accept for authenticating clients.
+    ///
+    /// By default, only `EXTERNAL` is accepted on unix sockets and only `DBUS_COOKIE_SHA1` on other
+    /// transports.
+    pub fn auth_mechanisms(mut self, auth_mechanisms: &[AuthMechanism]) -> Self {
+        self.auth_mechanisms = Some(auth_mechanisms.to_vec());
+
+        self
+    }
+
+    /// Register a custom [`SaslMechanism`] to accept for authenticating clients.
+    ///
+    /// See [`super::Builder::sasl_mechanism`] for details.
+    pub fn sasl_mechanism<M>(mut self, mechanism: M) -> Self
+    where
+        M: SaslMechanism,
+    {
+        self.sasl_mechanisms.push(Arc::new(mechanism));
+
+        self
+    }
+
+    /// Set the policy deciding which clients are accepted.
+    ///
+    /// See [`super::Builder::peer_policy`] for details.
+    pub fn peer_policy<F, Fut>(mut self, policy: F) -> Self
+    where
+        F: Fn(AuthenticatedPeer) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.peer_policy = Some(Arc::new(move |peer| Box::pin(policy(peer))));
+
+        self
+    }
+
+    /// Register an [`Interceptor`] on the accepted connections.
+    ///
+    /// See [`super::Builder::interceptor`] for details.
+    pub fn interceptor<I>(mut self, interceptor: I) -> Self
+    where
+        I: Interceptor,
+    {
+        self.interceptors.push(Arc::new(interceptor));
+
+        self
+    }
+
+    /// The cookie context to use during authentication.
+    ///
+    /// See [`super::Builder::cookie_context`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the given string is not a valid cookie context.
+    pub fn cookie_context<C>(mut self, context: C) -> Result<Self>
+    where
+        C: Into<Str<'static>>,
+    {
+        let context = context.into();
+        // Only to validate it.
+        CookieContext::try_from(context.clone())?;
+        self.cookie_context = Some(context);
+
+        Ok(self)
+    }
+
+    /// The ID of the cookie to use during authentication.
+    ///
+    /// See [`super::Builder::cookie_id`] for details.
+    pub fn cookie_id(mut self, id: usize) -> Self {
+        self.cookie_id = Some(id);
+
+        self
+    }
+
+    /// Set the max number of messages to queue on each of the connections.
+    ///
+    /// See [`super::Builder::max_queued`] for details.
+    pub fn max_queued(mut self, max: usize) -> Self {
+        self.max_queued = Some(max);
+
+        self
+    }
+
+    /// Set the time clients get to complete the handshake.
+    ///
+    /// This covers everything from the client connecting to its connection being ready, including
+    /// the authentication. Clients that take longer are dropped and an [`Error::Handshake`] is
+    /// yielded for them. Defaults to 30 seconds.
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = Some(timeout);
+
+        self
+    }
+
+    /// Register a D-Bus [`Interface`] to be served at a given path on each connection.
+    ///
+    /// Since each connection needs its own instance of the interface, this takes a function that
+    /// creates one. Much like [`super::Builder::serve_at`], this ensures the interface is available
+    /// as soon as the client is authenticated.
+    pub fn serve_at<P, I, F>(mut self, path: P, iface: F) -> Result<Self>
+    where
+        I: Interface,
+        F: Fn() -> I + Send + Sync + 'static,
+        P: TryInto<OwnedObjectPath>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        self.serve_at
+            .push(Box::new(move |builder: super::Builder<'static>| {
+                builder.serve_at(path.clone(), iface())
+            }));
+
+        Ok(self)
+    }
+
+    /// Also create a stream of all the messages each connection receives.
+    ///
+    /// Unlike creating a [`MessageStream`] from the accepted connections, this ensures that none of
+    /// the messages received right after the handshake are missed.
+    pub(crate) fn message_stream(mut self) -> Self {
+        self.msg_stream = true;
+
+        self
+    }
+
+    /// Bind the address and start listening for clients.
+    pub async fn build(self) -> Result<Listener> {
+        let (socket, address) = match self.socket {
+            Socket::Address(address) => ListeningSocket::bind(address)?,
+            #[cfg(unix)]
+            Socket::Fd(fd) => ListeningSocket::adopt(fd)?,
+        };
+        let auth_mechanisms = self
+            .auth_mechanisms
+            .unwrap_or_else(|| socket.default_auth_mechanisms());
+
+        Ok(Listener {
+            socket,
+            address,
+            guid: self.guid.unwrap_or_else(Guid::generate),
+            auth_mechanisms,
+            sasl_mechanisms: Arc::new(self.sasl_mechanisms),
+            peer_policy: self.peer_policy,
+            interceptors: Arc::new(self.interceptors),
+            cookie_context: self.cookie_context,
+            cookie_id: self.cookie_id,
+            max_queued: self.max_queued,
+            handshake_timeout: self.handshake_timeout.unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT),
+            msg_stream: self.msg_stream,
+            serve_at: Arc::new(self.serve_at),
+            handshakes: FuturesUnordered::new(),
+        })
+    }
+}
+
+// The socket to listen on.
+#[derive(Debug)]
+enum Socket {
+    Address(Address),
+    // An already listening socket.
+    #[cfg(unix)]
+    Fd(std::os::fd::OwnedFd),
+}
+
+#[cfg(not(feature = "tokio"))]
+type TcpListener = Async<std::net::TcpListener>;
+#[cfg(feature = "tokio")]
+type TcpListener = tokio::net::TcpListener;
+
+#[derive(Debug)]
+enum ListeningSocket {
+    #[cfg(all(unix, not(feature = "tokio")))]
+    Unix {
+        listener: Async<std::os::unix::net::UnixListener>,
+        // The socket file to remove once we're done.
+        path: Option<PathBuf>,
+    },
+    #[cfg(all(unix, feature = "tokio"))]
+    Unix {
+        listener: tokio::net::UnixListener,
+        // The socket file to remove once we're done.
+        path: Option<PathBuf>,
+    },
+    Tcp(TcpListener),
+    NonceTcp {
+        listener: TcpListener,
+        nonce: NonceFile,
+    },
+    #[cfg(all(feature = "vsock", not(feature = "tokio")))]
+    Vsock(Async<vsock::VsockListener>),
+    #[cfg(feature = "tokio-vsock")]
+    Vsock(tokio_vsock::VsockListener),
+}
+
+impl ListeningSocket {
+    /// Bind the given address, returning the socket and the address clients can connect to.
+    fn bind(address: Address) -> Result<(Self, Address)> {
+        match address {
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                use std::os::unix::ffi::OsStrExt;
+
+                if path.as_bytes().first() == Some(&0) {
+                    let listener = bind_abstract(&path.as_bytes()[1..])?;
+
+                    Ok((Self::unix(listener, None)?, Address::Unix(path)))
+                } else {
+                    let listener = std::os::unix::net::UnixListener::bind(&path)?;
+                    let path = PathBuf::from(path);
+
+                    Ok((
+                        Self::unix(listener, Some(path.clone()))?,
+                        Address::Unix(path.into()),
+                    ))
+                }
+            }
+            #[cfg(unix)]
+            Address::UnixDir(dir) | Address::UnixTmpDir(dir) => {
+                let path = PathBuf::from(dir).join(format!("dbus-{}", random_ascii(10)));
+                let listener = std::os::unix::net::UnixListener::bind(&path)?;
+
+                Ok((
+                    Self::unix(listener, Some(path.clone()))?,
+                    Address::Unix(path.into()),
+                ))
+            }
+            Address::Tcp(addr) => {
+                let listener = std::net::TcpListener::bind((addr.host(), addr.port()))?;
+                let port = listener.local_addr()?.port();
+
+                Ok((
+                    Self::Tcp(tcp_listener(listener)?),
+                    Address::Tcp(TcpAddress { port, ..addr }),
+                ))
+            }
+            Address::NonceTcp { addr, nonce_file } => {
+                let listener = std::net::TcpListener::bind((addr.host(), addr.port()))?;
+                let port = listener.local_addr()?.port();
+                let nonce = NonceFile::create(&nonce_file)?;
+                let address = Address::NonceTcp {
+                    addr: TcpAddress { port, ..addr },
+                    nonce_file,
+                };
+                let listener = tcp_listener(listener)?;
+
+                Ok((Self::NonceTcp { listener, nonce }, address))
+            }
+            #[cfg(all(feature = "vsock", not(feature = "tokio")))]
+            Address::Vsock(addr) => {
+                let listener = vsock::VsockListener::bind_with_cid_port(addr.cid, addr.port)?;
+                let port = listener.local_addr()?.port();
+
+                Ok((
+                    Self::Vsock(Async::new(listener)?),
+                    Address::Vsock(crate::address::VsockAddress { port, ..addr }),
+                ))
+            }
+            #[cfg(feature = "tokio-vsock")]
+            Address::Vsock(addr) => {
+                let listener = tokio_vsock::VsockListener::bind(addr.cid, addr.port)?;
+                let port = listener.local_addr()?.port();
+
+                Ok((
+                    Self::Vsock(listener),
+                    Address::Vsock(crate::address::VsockAddress { port, ..addr }),
+                ))
+            }
+            address => Err(Error::Address(format!(
+                "Address `{address}` can't be listened on"
+            ))),
+        }
+    }
+
+    #[cfg(unix)]
+    fn unix(listener: std::os::unix::net::UnixListener, path: Option<PathBuf>) -> Result<Self> {
+        #[cfg(not(feature = "tokio"))]
+        let listener = Async::new(listener)?;
+        #[cfg(feature = "tokio")]
+        let listener = {
+            listener.set_nonblocking(true)?;
+            tokio::net::UnixListener::from_std(listener)?
+        };
+
+        Ok(Self::Unix { listener, path })
+    }
+
+    /// Listen on an already listening socket, returning the socket and the address clients can
+    /// connect to.
+    #[cfg(unix)]
+    fn adopt(fd: std::os::fd::OwnedFd) -> Result<(Self, Address)> {
+        use nix::sys::socket::{
+            getsockname, getsockopt, sockopt, AddressFamily, SockType, SockaddrLike,
+            SockaddrStorage,
+        };
+        use std::os::fd::AsRawFd;
+
+        // Make sure we got what `ListenStream=` gives us, rather than failing in obscure ways later.
+        let sock_type = getsockopt(&fd, sockopt::SockType).map_err(|e| {
+            Error::Failure(format!("The passed file descriptor is not a socket: {e}"))
+        })?;
+        if sock_type != SockType::Stream {
+            return Err(Error::Failure(format!(
+                "The passed socket is of type {sock_type:?}, only stream sockets are supported"
+            )));
+        }
+        if !getsockopt(&fd, sockopt::AcceptConn)? {
+            return Err(Error::Failure(
+                "The passed socket is not listening for connections".into(),
+            ));
+        }
+
+        let addr = getsockname::<SockaddrStorage>(fd.as_raw_fd())?;
+        match addr.family() {
+            Some(AddressFamily::Unix) => {
+                // SAFETY: The family is checked above.
+                let addr = addr.as_unix_addr().unwrap();
+                let address = match addr.path() {
+                    Some(path) => Address::Unix(path.into()),
+                    None => abstract_address(addr)?,
+                };
+                // The socket file isn't ours to remove.
+                let listener = std::os::unix::net::UnixListener::from(fd);
+
+                Ok((Self::unix(listener, None)?, address))
+            }
+            Some(AddressFamily::Inet) | Some(AddressFamily::Inet6) => {
+                let listener = std::net::TcpListener::from(fd);
+                let addr = listener.local_addr()?;
+                let family = if addr.is_ipv4() {
+                    crate::address::TcpAddressFamily::Ipv4
+                } else {
+                    crate::address::TcpAddressFamily::Ipv6
+                };
+                let address = Address::Tcp(TcpAddress {
+                    host: addr.ip().to_string(),
+                    bind: None,
+                    port: addr.port(),
+                    family: Some(family),
+                });
+
+                Ok((Self::Tcp(tcp_listener(listener)?), address))
+            }
+            family => Err(Error::Address(format!(
+                "Unsupported socket family: {family:?}"
+            ))),
+        }
+    }
+
+    fn default_auth_mechanisms(&self) -> Vec<AuthMechanism> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix { .. } => vec![AuthMechanism::External],
+            _ => vec![AuthMechanism::Cookie],
+        }
+    }
+
+    fn poll_accept(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Incoming>> {
+        let builder = match self {
+            #[cfg(all(unix, not(feature = "tokio")))]
+            Self::Unix { listener, .. } => poll_accept(listener, cx, |l| l.accept())
+                .map_ok(|(stream, _)| super::Builder::unix_stream(stream)),
+            #[cfg(all(unix, feature = "tokio"))]
+            Self::Unix { listener, .. } => listener
+                .poll_accept(cx)
+                .map_ok(|(stream, _)| super::Builder::unix_stream(stream)),
+            #[cfg(not(feature = "tokio"))]
+            Self::Tcp(listener) => poll_accept(listener, cx, |l| l.accept())
+                .map_ok(|(stream, _)| super::Builder::tcp_stream(stream)),
+            #[cfg(feature = "tokio")]
+            Self::Tcp(listener) => listener
+                .poll_accept(cx)
+                .map_ok(|(stream, _)| super::Builder::tcp_stream(stream)),
+            #[cfg(all(feature = "vsock", not(feature = "tokio")))]
+            Self::Vsock(listener) => poll_accept(listener, cx, |l| l.accept())
+                .map_ok(|(stream, _)| super::Builder::vsock_stream(stream)),
+            #[cfg(feature = "tokio-vsock")]
+            Self::Vsock(listener) => listener
+                .poll_accept(cx)
+                .map_ok(|(stream, _)| super::Builder::vsock_stream(stream)),
+            #[cfg(not(feature = "tokio"))]
+            Self::NonceTcp { listener, nonce } => {
+                return poll_accept(listener, cx, |l| l.accept())
+                    .map_ok(|(stream, _)| Incoming::NonceTcp(stream, nonce.nonce))
+            }
+            #[cfg(feature = "tokio")]
+            Self::NonceTcp { listener, nonce } => {
+                return listener
+                    .poll_accept(cx)
+                    .map_ok(|(stream, _)| Incoming::NonceTcp(stream, nonce.nonce))
+            }
+        };
+
+        builder.map_ok(|builder| Incoming::Stream(Box::new(builder)))
+    }
+}
+
+// An accepted client.
+enum Incoming {
+    // Ready for the handshake.
+    Stream(Box<super::Builder<'static>>),
+    // Has to send the nonce first.
+    #[cfg(not(feature = "tokio"))]
+    NonceTcp(std::net::TcpStream, [u8; NONCE_LEN]),
+    #[cfg(feature = "tokio")]
+    NonceTcp(tokio::net::TcpStream, [u8; NONCE_LEN]),
+}
+
+// The length of the nonce of `nonce-tcp:` sockets, as set by the D-Bus specification.
+const NONCE_LEN: usize = 16;
+
+/// The nonce of a `nonce-tcp:` listener and the file it's written to.
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+struct NonceFile {
+    #[derivative(Debug = "ignore")]
+    nonce: [u8; NONCE_LEN],
+    path: PathBuf,
+}
+
+impl NonceFile {
+    /// Generate a nonce and write it to a new file at `path`.
+    fn create(path: &[u8]) -> Result<Self> {
+        use rand::RngCore;
+        use std::io::Write;
+
+        #[cfg(unix)]
+        let path = {
+            use std::os::unix::ffi::OsStrExt;
+            PathBuf::from(std::ffi::OsStr::from_bytes(path))
+        };
+        #[cfg(windows)]
+        let path = std::str::from_utf8(path)
+            .map(PathBuf::from)
+            .map_err(|_| Error::Address("nonce file path is invalid UTF-8".to_owned()))?;
+
+        let mut nonce = [0; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        // Refuse to reuse an existing file, it could have been planted by someone else.
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path)?;
+        let nonce_file = Self { nonce, path };
+        file.write_all(&nonce)?;
+
+        Ok(nonce_file)
+    }
+}
+
+impl Drop for NonceFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                debug!("Failed to remove nonce file `{}`: {e}", self.path.display());
+            }
+        }
+    }
+}
+
+// Read the nonce the client has to send before anything else and check it's ours.
+#[cfg(not(feature = "tokio"))]
+async fn verify_nonce(
+    stream: std::net::TcpStream,
+    nonce: &[u8; NONCE_LEN],
+) -> Result<std::net::TcpStream> {
+    let mut stream = Async::new(stream)?;
+    let mut received = [0; NONCE_LEN];
+    futures_util::AsyncReadExt::read_exact(&mut stream, &mut received).await?;
+    check_nonce(&received, nonce)?;
+
+    stream.into_inner().map_err(Into::into)
+}
+
+#[cfg(feature = "tokio")]
+async fn verify_nonce(
+    mut stream: tokio::net::TcpStream,
+    nonce: &[u8; NONCE_LEN],
+) -> Result<tokio::net::TcpStream> {
+    let mut received = [0; NONCE_LEN];
+    tokio::io::AsyncReadExt::read_exact(&mut stream, &mut received).await?;
+    check_nonce(&received, nonce)?;
+
+    Ok(stream)
+}
+
+fn check_nonce(received: &[u8; NONCE_LEN], nonce: &[u8; NONCE_LEN]) -> Result<()> {
+    // Compare all the bytes, not to leak how much of the nonce was right through timing.
+    let diff = received
+        .iter()
+        .zip(nonce)
+        .fold(0, |diff, (r, n)| diff | (r ^ n));
+    if diff != 0 {
+        return Err(Error::Handshake("Invalid nonce".into()));
+    }
+
+    Ok(())
+}
+
+impl Drop for ListeningSocket {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Self::Unix {
+            path: Some(path), ..
+        } = self
+        {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    debug!("Failed to remove socket file `{}`: {e}", path.display())
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn tcp_listener(listener: std::net::TcpListener) -> Result<TcpListener> {
+    #[cfg(not(feature = "tokio"))]
+    let listener = Async::new(listener)?;
+    #[cfg(feature = "tokio")]
+    let listener = {
+        listener.set_nonblocking(true)?;
+        tokio::net::TcpListener::from_std(listener)?
+    };
+
+    Ok(listener)
+}
+
+#[cfg(not(feature = "tokio"))]
+fn poll_accept<L, S>(
+    listener: &Async<L>,
+    cx: &mut Context<'_>,
+    accept: impl Fn(&L) -> io::Result<S>,
+) -> Poll<io::Result<S>> {
+    loop {
+        match accept(listener.get_ref()) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                std::task::ready!(listener.poll_readable(cx))?
+            }
+            res => return Poll::Ready(res),
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_abstract(name: &[u8]) -> Result<std::os::unix::net::UnixListener> {
+    use nix::sys::socket::{bind, listen, socket, AddressFamily, SockFlag, SockType, UnixAddr};
+    use std::os::fd::AsRawFd;
+
+    let fd = socket(
+        AddressFamily::Unix,
+        SockType::Stream,
+        SockFlag::SOCK_CLOEXEC,
+        None,
+    )?;
+    bind(fd.as_raw_fd(), &UnixAddr::new_abstract(name)?)?;
+    // The same backlog as the one std uses.
+    listen(&fd, 128)?;
+
+    Ok(fd.into())
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn bind_abstract(_name: &[u8]) -> Result<std::os::unix::net::UnixListener> {
+    Err(Error::Address(
+        "abstract unix sockets are not supported on this platform".into(),
+    ))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn abstract_address(addr: &nix::sys::socket::UnixAddr) -> Result<Address> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = addr
+        .as_abstract()
+        .ok_or_else(|| Error::Address("unnamed unix sockets can't be listened on".into()))?;
+    let mut path = std::ffi::OsString::from("\0");
+    path.push(std::ffi::OsStr::from_bytes(name));
+
+    Ok(Address::Unix(path))
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn abstract_address(_addr: &nix::sys::socket::UnixAddr) -> Result<Address> {
+    Err(Error::Address(
+        "unnamed unix sockets can't be listened on".into(),
+    ))
+}
+
+// The first file descriptor passed by systemd.
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: std::os::fd::RawFd = 3;
+
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy)]
+enum SystemdSocket<'n> {
+    Index(usize),
+    Name(&'n str),
+}
+
+/// The file descriptor of a socket passed by systemd, as described by the `LISTEN_*` environment
+/// variables.
+#[cfg(unix)]
+fn systemd_socket_fd<V>(socket: SystemdSocket<'_>, var: V) -> Result<std::os::fd::RawFd>
+where
+    V: Fn(&str) -> Option<String>,
+{
+    // The variables are meant for the process systemd started, not for its children.
+    let pid = var("LISTEN_PID").and_then(|pid| pid.parse::<u32>().ok());
+    if pid != Some(std::process::id()) {
+        return Err(Error::Failure(
+            "No sockets were passed to this process by systemd".into(),
+        ));
+    }
+    let count = var("LISTEN_FDS")
+        .and_then(|count| count.parse::<usize>().ok())
+        .ok_or_else(|| Error::Failure("Invalid or missing `LISTEN_FDS`".into()))?;
+
+    let index = match socket {
+        SystemdSocket::Index(index) => index,
+        SystemdSocket::Name(name) => var("LISTEN_FDNAMES")
+            .unwrap_or_default()
+            .split(':')
+            .position(|n| n == name)
+            .ok_or_else(|| {
+                Error::Failure(format!("No socket named `{name}` was passed by systemd"))
+            })?,
+    };
+    if index >= count {
+        return Err(Error::Failure(format!(
+            "No socket at index {index} was passed by systemd ({count} passed)"
+        )));
+    }
+
+    Ok(SD_LISTEN_FDS_START + index as std::os::fd::RawFd)
+}
+
+// Take ownership of an inherited file descriptor, making sure it's only done once.
+#[cfg(unix)]
+fn take_fd(fd: std::os::fd::RawFd) -> Result<std::os::fd::OwnedFd> {
+    use std::{
+        os::fd::{FromRawFd, OwnedFd},
+        sync::Mutex,
+    };
+
+    static TAKEN: Mutex<Vec<std::os::fd::RawFd>> = Mutex::new(Vec::new());
+
+    let mut taken = TAKEN.lock().expect("poisoned lock");
+    if taken.contains(&fd) {
+        return Err(Error::Failure(format!(
+            "The socket passed as file descriptor {fd} is already in use"
+        )));
+    }
+    taken.push(fd);
+    // SAFETY: The file descriptor was passed to us and we only take it once.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // systemd doesn't set `FD_CLOEXEC` so we replace the descriptor with a duplicate that has it
+    // set, so it doesn't leak into our child processes.
+    fd.try_clone().map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use ntest::timeout;
+    use std::time::Duration;
+    use test_log::test;
+
+    use super::Listener;
+    use crate::{connection::Builder, dbus_interface, AuthMechanism, Connection, Message, Result};
+
+    struct Counter(u32);
+
+    #[dbus_interface(name = "org.zbus.ListenerTest")]
+    impl Counter {
+        fn next(&mut self) -> u32 {
+            self.0 += 1;
+
+            self.0
+        }
+    }
+
+    async fn next(conn: &Connection) -> Result<u32> {
+        conn.call_method(
+            None::<()>,
+            "/org/zbus/ListenerTest",
+            Some("org.zbus.ListenerTest"),
+            "Next",
+            &(),
+        )
+        .await
+        .and_then(|m: Message| m.body().deserialize())
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn multiple_clients() {
+        crate::utils::block_on(test_multiple_clients()).unwrap();
+    }
+
+    async fn test_multiple_clients() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let mut listener =
+            Listener::builder(format!("unix:tmpdir={}", dir.path().display()).as_str())?
+                .serve_at("/org/zbus/ListenerTest", || Counter(0))?
+                .build()
+                .await?;
+        let address = listener.address().clone();
+
+        let (client1, client2, server1, server2) = futures_util::try_join!(
+            async { Builder::address(address.clone())?.p2p().build().await },
+            async { Builder::address(address.clone())?.p2p().build().await },
+            async {
+                let server1 = listener.next().await.unwrap()?;
+                let server2 = listener.next().await.unwrap()?;
+
+                Ok((server1, server2))
+            },
+        )
+        .map(|(c1, c2, (s1, s2))| (c1, c2, s1, s2))?;
+        for conn in [&client1, &client2] {
+            assert_eq!(conn.server_guid(), *listener.guid());
+        }
+        for conn in [&server1, &server2] {
+            assert_eq!(conn.server_guid(), *listener.guid());
+        }
+
+        // Each connection gets its own instance of the interface.
+        assert_eq!(next(&client1).await?, 1);
+        assert_eq!(next(&client1).await?, 2);
+        assert_eq!(next(&client2).await?, 1);
+
+        // The socket file is removed with the listener.
+        let path = match listener.address() {
+            crate::Address::Unix(path) => std::path::PathBuf::from(path),
+            _ => unreachable!(),
+        };
+        assert!(path.exists());
+        drop(listener);
+        assert!(!path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn tcp() {
+        crate::utils::block_on(test_tcp()).unwrap();
+    }
+
+    async fn test_tcp() -> Result<()> {
+        let mut listener = Listener::builder("tcp:host=127.0.0.1,port=0")?
+            .auth_mechanisms(&[AuthMechanism::Anonymous])
+            .build()
+            .await?;
+        let address = listener.address().clone();
+        match &address {
+            crate::Address::Tcp(addr) => assert_ne!(addr.port(), 0),
+            _ => unreachable!(),
+        }
+
+        let (client, server) = futures_util::try_join!(
+            async {
+                Builder::address(address.clone())?
+                    .auth_mechanisms(&[AuthMechanism::Anonymous])
+                    .p2p()
+                    .build()
+                    .await
+            },
+            async { listener.next().await.unwrap() },
+        )?;
+        server
+            .object_server()
+            .at("/org/zbus/ListenerTest", Counter(41))
+            .await?;
+        assert_eq!(next(&client).await?, 42);
+
+        Ok(())
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn handshake_timeout() {
+        crate::utils::block_on(test_handshake_timeout()).unwrap();
+    }
+
+    async fn test_handshake_timeout() -> Result<()> {
+        let mut listener = Listener::builder("tcp:host=127.0.0.1,port=0")?
+            .auth_mechanisms(&[AuthMechanism::Anonymous])
+            .handshake_timeout(Duration::from_millis(100))
+            .build()
+            .await?;
+        let port = match listener.address() {
+            crate::Address::Tcp(addr) => addr.port(),
+            _ => unreachable!(),
+        };
+
+        // A client that connects but never says anything.
+        let _stream = std::net::TcpStream::connect(("127.0.0.1", port))?;
+        match listener.next().await.unwrap() {
+            Err(crate::Error::Handshake(e)) => {
+                assert_eq!(e, "Client didn't complete the handshake within 100ms")
+            }
+            _ => panic!("silent client accepted"),
+        }
+
+        // Other clients are still accepted.
+        let address = listener.address().clone();
+        let (_client, _server) = futures_util::try_join!(
+            async {
+                Builder::address(address)?
+                    .auth_mechanisms(&[AuthMechanism::Anonymous])
+                    .p2p()
+                    .build()
+                    .await
+            },
+            async { listener.next().await.unwrap() },
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn nonce_tcp() {
+        crate::utils::block_on(test_nonce_tcp()).unwrap();
+    }
+
+    async fn test_nonce_tcp() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let nonce_file = dir.path().join("nonce");
+        let mut listener = Listener::builder(
+            format!(
+                "nonce-tcp:host=127.0.0.1,port=0,noncefile={}",
+                nonce_file.display()
+            )
+            .as_str(),
+        )?
+        .auth_mechanisms(&[AuthMechanism::Anonymous])
+        .serve_at("/org/zbus/ListenerTest", || Counter(0))?
+        .build()
+        .await?;
+        let address = listener.address().clone();
+        let port = match &address {
+            crate::Address::NonceTcp { addr, .. } => addr.port(),
+            _ => unreachable!(),
+        };
+        assert_ne!(port, 0);
+        assert_eq!(std::fs::read(&nonce_file)?.len(), 16);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&nonce_file)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // The nonce file is never reused.
+        let err = Listener::bind(
+            format!(
+                "nonce-tcp:host=127.0.0.1,port=0,noncefile={}",
+                nonce_file.display()
+            )
+            .as_str(),
+        )
+        .await
+        .unwrap_err();
+        match err {
+            crate::Error::InputOutput(e) => assert_eq!(e.kind(), std::io::ErrorKind::AlreadyExists),
+            e => panic!("unexpected error: {e}"),
+        }
+
+        // A client not sending the nonce is rejected.
+        let (_, res) = futures_util::join!(
+            async {
+                let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+                std::io::Write::write_all(&mut stream, &[0; 16]).unwrap();
+
+                stream
+            },
+            async { listener.next().await.unwrap() },
+        );
+        match res {
+            Err(crate::Error::Handshake(e)) => assert_eq!(e, "Invalid nonce"),
+            _ => panic!("client with an invalid nonce accepted"),
+        }
+
+        let (client, _server) = futures_util::try_join!(
+            async {
+                Builder::address(address.clone())?
+                    .auth_mechanisms(&[AuthMechanism::Anonymous])
+                    .p2p()
+                    .build()
+                    .await
+            },
+            async { listener.next().await.unwrap() },
+        )?;
+        assert_eq!(next(&client).await?, 1);
+
+        drop(listener);
+        assert!(!nonce_file.exists());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn systemd_socket_fd() {
+        use super::{systemd_socket_fd, SystemdSocket};
+
+        let pid = std::process::id().to_string();
+        let env = |pid: &str| {
+            let pid = pid.to_string();
+            move |var: &str| match var {
+                "LISTEN_PID" => Some(pid.clone()),
+                "LISTEN_FDS" => Some("2".to_string()),
+                "LISTEN_FDNAMES" => Some("bus:other".to_string()),
+                _ => None,
+            }
+        };
+
+        assert_eq!(
+            systemd_socket_fd(SystemdSocket::Index(0), env(&pid)).unwrap(),
+            3
+        );
+        assert_eq!(
+            systemd_socket_fd(SystemdSocket::Index(1), env(&pid)).unwrap(),
+            4
+        );
+        assert_eq!(
+            systemd_socket_fd(SystemdSocket::Name("bus"), env(&pid)).unwrap(),
+            3
+        );
+        assert_eq!(
+            systemd_socket_fd(SystemdSocket::Name("other"), env(&pid)).unwrap(),
+            4
+        );
+        assert!(systemd_socket_fd(SystemdSocket::Index(2), env(&pid)).is_err());
+        assert!(systemd_socket_fd(SystemdSocket::Name("missing"), env(&pid)).is_err());
+        // The sockets were passed to another process.
+        assert!(systemd_socket_fd(SystemdSocket::Index(0), env("1")).is_err());
+        assert!(systemd_socket_fd(SystemdSocket::Index(0), |_| None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn adopt_socket() {
+        crate::utils::block_on(test_adopt_socket()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_adopt_socket() -> Result<()> {
+        use super::Socket;
+        use std::os::fd::OwnedFd;
+
+        // A unix socket, as passed by systemd.
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bus");
+        let socket = std::os::unix::net::UnixListener::bind(&path)?;
+        let mut listener = super::Builder::with_socket(Socket::Fd(OwnedFd::from(socket)))
+            .serve_at("/org/zbus/ListenerTest", || Counter(0))?
+            .build()
+            .await?;
+        assert_eq!(
+            listener.address(),
+            &crate::Address::Unix(path.clone().into())
+        );
+        let address = listener.address().clone();
+        let (client, _server) = futures_util::try_join!(
+            async { Builder::address(address)?.p2p().build().await },
+            async { listener.next().await.unwrap() },
+        )?;
+        assert_eq!(next(&client).await?, 1);
+        // The socket file belongs to systemd.
+        drop(listener);
+        assert!(path.exists());
+
+        // A TCP socket.
+        let socket = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = socket.local_addr()?.port();
+        let mut listener = super::Builder::with_socket(Socket::Fd(OwnedFd::from(socket)))
+            .auth_mechanisms(&[AuthMechanism::Anonymous])
+            .serve_at("/org/zbus/ListenerTest", || Counter(0))?
+            .build()
+            .await?;
+        assert_eq!(
+            listener.address().to_string(),
+            format!("tcp:host=127.0.0.1,port={port},family=ipv4")
+        );
+        let address = listener.address().clone();
+        let (client, _server) = futures_util::try_join!(
+            async {
+                Builder::address(address)?
+                    .auth_mechanisms(&[AuthMechanism::Anonymous])
+                    .p2p()
+                    .build()
+                    .await
+            },
+            async { listener.next().await.unwrap() },
+        )?;
+        assert_eq!(next(&client).await?, 1);
+
+        // Only listening stream sockets are supported.
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0")?;
+        let err = super::Builder::with_socket(Socket::Fd(OwnedFd::from(socket)))
+            .build()
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The passed socket is of type Datagram, only stream sockets are supported"
+        );
+        let (socket, _) = std::os::unix::net::UnixStream::pair()?;
+        let err = super::Builder::with_socket(Socket::Fd(OwnedFd::from(socket)))
+            .build()
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The passed socket is not listening for connections"
+        );
+        let file = tempfile::tempfile().unwrap();
+        let err = super::Builder::with_socket(Socket::Fd(OwnedFd::from(file)))
+            .build()
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("The passed file descriptor is not a socket"));
+
+        Ok(())
+    }
+}
@@ -13,53 +13,88 @@ use std::{
     num::NonZeroU32,
     ops::Deref,
     pin::Pin,
-    sync::{Arc, Weak},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Weak,
+    },
     task::{Context, Poll},
+    time::Duration,
 };
 use tracing::{debug, info_span, instrument, trace, trace_span, warn, Instrument};
 use zbus_names::{BusName, ErrorName, InterfaceName, MemberName, OwnedUniqueName, WellKnownName};
 use zvariant::ObjectPath;
 
 use futures_core::Future;
-use futures_util::StreamExt;
+use futures_util::{FutureExt, StreamExt};
 
 use crate::{
     async_lock::Mutex,
     blocking,
     fdo::{self, ConnectionCredentials, RequestNameFlags, RequestNameReply},
-    message::{Flags, Message, Type},
+    message::{Flags, Message, Sequence, Type},
     proxy::CacheProperties,
-    DBusError, Error, Executor, Guid, MatchRule, MessageStream, ObjectServer, OwnedMatchRule,
-    Result, Task,
+    utils::{sleep, Sleep},
+    DBusError, Error, Executor, Guid, MatchRule, MessageStream, ObjectServer, OverflowPolicy,
+    OwnedMatchRule, Result, Task,
 };
 
 mod builder;
+#[cfg(not(feature = "tokio"))]
+pub(crate) use builder::start_internal_executor;
 pub use builder::Builder;
 
+pub mod listener;
+pub use listener::Listener;
+
+mod name_ownership;
+pub use name_ownership::{NameOwnership, NameOwnershipEvent};
+
 pub mod socket;
 pub use socket::Socket;
 
 mod socket_reader;
-use socket_reader::SocketReader;
+use socket_reader::{LossySender, MsgSenders, RecvSequence, SocketReader};
+
+mod reconnect;
+use reconnect::Reconnect;
+pub use reconnect::{ConnectionState, ConnectionStateStream, ReconnectPolicy};
 
 pub(crate) mod handshake;
 use handshake::Authenticated;
 
+mod sasl;
+pub use sasl::{SaslClient, SaslMechanism, SaslServer, SaslServerStep};
+
+mod peer_policy;
+pub use peer_policy::AuthenticatedPeer;
+
+mod interceptor;
+use interceptor::intercept;
+pub use interceptor::{Interception, Interceptor};
+
+mod stats;
+pub use stats::{ConnectionStats, MessageCounts, QueueStats};
+pub(crate) use stats::{Counters, Gauge, Tracked};
+
 const DEFAULT_MAX_QUEUED: usize = 64;
 const DEFAULT_MAX_METHOD_RETURN_QUEUED: usize = 8;
 
 /// Inner state shared by Connection and WeakConnection
 #[derive(Debug)]
 pub(crate) struct ConnectionInner {
-    server_guid: Guid,
+    // Both change when the connection is re-established.
+    server_guid: std::sync::RwLock<Guid>,
     #[cfg(unix)]
     cap_unix_fd: bool,
     bus_conn: bool,
-    unique_name: OnceCell<OwnedUniqueName>,
-    registered_names: Mutex<HashMap<WellKnownName<'static>, NameStatus>>,
+    unique_name: std::sync::RwLock<Option<OwnedUniqueName>>,
+    method_timeout: Option<Duration>,
+    registered_names: Mutex<HashMap<WellKnownName<'static>, RegisteredName>>,
 
     activity_event: Arc<Event>,
     socket_write: Mutex<Box<dyn socket::WriteHalf>>,
+    pub(crate) stats: Arc<Counters>,
+    pub(crate) interceptors: Vec<Arc<dyn Interceptor>>,
 
     // Our executor
     executor: Executor<'static>,
@@ -70,12 +105,20 @@ pub(crate) struct ConnectionInner {
 
     pub(crate) msg_receiver: InactiveReceiver<Result<Message>>,
     pub(crate) method_return_receiver: InactiveReceiver<Result<Message>>,
-    msg_senders: Arc<Mutex<HashMap<Option<OwnedMatchRule>, MsgBroadcaster>>>,
+    msg_senders: Arc<Mutex<MsgSenders>>,
+    recv_seq: Arc<RecvSequence>,
 
     subscriptions: Mutex<Subscriptions>,
 
     object_server: OnceCell<blocking::ObjectServer>,
     object_server_dispatch_task: OnceCell<Task<()>>,
+
+    // Set if the connection is to be re-established when the socket is lost.
+    reconnect: Option<Reconnect>,
+    state: std::sync::Mutex<ConnectionState>,
+    state_sender: Broadcaster<ConnectionState>,
+    state_receiver: InactiveReceiver<ConnectionState>,
+    closed: AtomicBool,
 }
 
 type Subscriptions = HashMap<OwnedMatchRule, (u64, InactiveReceiver<Result<Message>>)>;
@@ -108,7 +151,8 @@ pub(crate) type MsgBroadcaster = Broadcaster<Result<Message>>;
 /// method. When the queue is full, no more messages can be received until room is created for more.
 /// This is why it's important to ensure that all [`crate::MessageStream`] and
 /// [`crate::blocking::MessageIterator`] instances are continuously polled and iterated on,
-/// respectively.
+/// respectively. Alternatively, streams can drop messages instead through an
+/// [`crate::OverflowPolicy`].
 ///
 /// For sending messages you can either use [`Connection::send`] method.
 ///
@@ -206,14 +250,29 @@ assert_impl_all!(Connection: Send, Sync, Unpin);
 pub(crate) struct PendingMethodCall {
     stream: Option<MessageStream>,
     serial: NonZeroU32,
+    timeout: Option<Sleep>,
+    timed_out: bool,
+    // Counts the call as pending until the reply is received.
+    pending: Option<Tracked>,
+}
+
+impl PendingMethodCall {
+    /// Override the timeout for the reply, starting from now.
+    pub(crate) fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout.map(sleep);
+    }
 }
 
 impl Future for PendingMethodCall {
     type Output = Result<Message>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.poll_before(cx, None).map(|ret| {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.as_mut().poll_before(cx, None).map(|ret| {
             ret.map(|(_, r)| r).unwrap_or_else(|| {
+                if self.timed_out {
+                    return Err(Error::Timeout);
+                }
+
                 Err(crate::Error::InputOutput(
                     io::Error::new(ErrorKind::BrokenPipe, "socket closed").into(),
                 ))
@@ -248,6 +307,7 @@ impl OrderedFuture for PendingMethodCall {
                             _ => continue,
                         };
                         this.stream = None;
+                        this.pending = None;
                         return Poll::Ready(Some((ordering, res)));
                     }
                     Poll::Ready(PollResult::Item {
@@ -263,7 +323,20 @@ impl OrderedFuture for PendingMethodCall {
                     Poll::Ready(PollResult::Terminated) => {
                         return Poll::Ready(None);
                     }
-                    Poll::Pending => return Poll::Pending,
+                    Poll::Pending => {
+                        if let Some(timeout) = &mut this.timeout {
+                            if timeout.poll_unpin(cx).is_ready() {
+                                // Dropping the stream unsubscribes us from the method returns.
+                                this.stream = None;
+                                this.pending = None;
+                                this.timed_out = true;
+
+                                return Poll::Ready(None);
+                            }
+                        }
+
+                        return Poll::Pending;
+                    }
                 }
             }
         }
@@ -273,17 +346,58 @@ impl OrderedFuture for PendingMethodCall {
 
 impl Connection {
     /// Send `msg` to the peer.
+    ///
+    /// The message goes through the [`Interceptor`]s of the connection first, if any.
     pub async fn send(&self, msg: &Message) -> Result<()> {
-        let data = msg.data();
+        if self.inner.interceptors.is_empty() {
+            return self.write_msg(msg).await;
+        }
+
+        match intercept(&self.inner.interceptors, msg.clone(), true).await {
+            Interception::Continue(msg) => self.write_msg(&msg).await,
+            Interception::Drop => {
+                trace!("Message dropped by an interceptor: {:?}", msg);
+
+                Ok(())
+            }
+            Interception::Reply(reply) => {
+                // Handle the reply as if the peer had sent it, after the messages received so far.
+                trace!("Message answered by an interceptor: {:?}", msg);
+                let position = Sequence::new(self.inner.recv_seq.next());
+                let reply = reply.with_recv_position(position)?;
+                self.inner
+                    .msg_senders
+                    .lock()
+                    .await
+                    .dispatch(&reply, &self.inner.stats)
+                    .await;
+
+                Ok(())
+            }
+        }
+    }
+
+    async fn write_msg(&self, msg: &Message) -> Result<()> {
         #[cfg(unix)]
-        if !data.fds().is_empty() && !self.inner.cap_unix_fd {
+        if !msg.data().fds().is_empty() && !self.inner.cap_unix_fd {
             return Err(Error::Unsupported);
         }
+        let mut write = self.inner.socket_write.lock().await;
+
+        self.write_msg_to(&mut write, msg).await
+    }
+
+    // Write `msg` to the given socket, which isn't necessarily the current one.
+    async fn write_msg_to(
+        &self,
+        write: &mut Box<dyn socket::WriteHalf>,
+        msg: &Message,
+    ) -> Result<()> {
+        let data = msg.data();
         let serial = msg.primary_header().serial_num();
 
         trace!("Sending message: {:?}", msg);
         self.inner.activity_event.notify(usize::MAX);
-        let mut write = self.inner.socket_write.lock().await;
         let mut pos = 0;
         while pos < data.len() {
             #[cfg(unix)]
@@ -301,6 +415,7 @@ impl Connection {
                 .await?;
         }
         trace!("Sent message with serial: {}", serial);
+        self.inner.stats.message_sent(msg);
 
         Ok(())
     }
@@ -343,6 +458,49 @@ impl Connection {
         .await
     }
 
+    /// Send a method call, failing with [`Error::Timeout`] if no reply is received within
+    /// `timeout`.
+    ///
+    /// This overrides the [default timeout][dt] of the connection for this call only. See
+    /// [`Connection::call_method`] for details.
+    ///
+    /// [dt]: Connection::method_timeout
+    pub async fn call_method_with_timeout<'d, 'p, 'i, 'm, D, P, I, M, B>(
+        &self,
+        destination: Option<D>,
+        path: P,
+        interface: Option<I>,
+        method_name: M,
+        timeout: Duration,
+        body: &B,
+    ) -> Result<Message>
+    where
+        D: TryInto<BusName<'d>>,
+        P: TryInto<ObjectPath<'p>>,
+        I: TryInto<InterfaceName<'i>>,
+        M: TryInto<MemberName<'m>>,
+        D::Error: Into<Error>,
+        P::Error: Into<Error>,
+        I::Error: Into<Error>,
+        M::Error: Into<Error>,
+        B: serde::ser::Serialize + zvariant::DynamicType,
+    {
+        let mut reply = self
+            .call_method_raw(
+                destination,
+                path,
+                interface,
+                method_name,
+                BitFlags::empty(),
+                body,
+            )
+            .await?
+            .expect("no reply");
+        reply.set_timeout(Some(timeout));
+
+        reply.await
+    }
+
     /// Send a method call.
     ///
     /// Send the given message, which must be a method call, over the connection and return an
@@ -388,6 +546,17 @@ impl Connection {
         }
         let msg = builder.build(body)?;
 
+        let reply = self.expect_reply(&msg);
+        self.send(&msg).await?;
+        if flags.contains(Flags::NoReplyExpected) {
+            Ok(None)
+        } else {
+            Ok(Some(reply))
+        }
+    }
+
+    // Start listening for the reply to the method call `msg`, before it's sent.
+    fn expect_reply(&self, msg: &Message) -> PendingMethodCall {
         let msg_receiver = self.inner.method_return_receiver.activate_cloned();
         let stream = Some(MessageStream::for_subscription_channel(
             msg_receiver,
@@ -395,12 +564,16 @@ impl Connection {
             None,
             self,
         ));
-        let serial = msg.primary_header().serial_num();
-        self.send(&msg).await?;
-        if flags.contains(Flags::NoReplyExpected) {
-            Ok(None)
-        } else {
-            Ok(Some(PendingMethodCall { stream, serial }))
+
+        PendingMethodCall {
+            stream,
+            serial: msg.primary_header().serial_num(),
+            timeout: self.inner.method_timeout.map(sleep),
+            timed_out: false,
+            pending: Some(Tracked::new(
+                self.inner.stats.clone(),
+                Gauge::PendingMethodCalls,
+            )),
         }
     }
 
@@ -540,6 +713,9 @@ impl Connection {
     /// lost if another peer requests the same name. You can use [`fdo::NameLostStream`] to be
     /// notified when the name is lost
     ///
+    /// Use [`Connection::own_name`] instead if you want to keep track of the ownership of the name
+    /// after this call.
+    ///
     /// # Example
     ///
     /// ```
@@ -615,13 +791,13 @@ impl Connection {
         let mut names = self.inner.registered_names.lock().await;
 
         match names.get(&well_known_name) {
-            Some(NameStatus::Owner(_)) => return Ok(RequestNameReply::AlreadyOwner),
-            Some(NameStatus::Queued(_)) => return Ok(RequestNameReply::InQueue),
+            Some((_, NameStatus::Owner(_))) => return Ok(RequestNameReply::AlreadyOwner),
+            Some((_, NameStatus::Queued(_))) => return Ok(RequestNameReply::InQueue),
             None => (),
         }
 
         if !self.is_bus() {
-            names.insert(well_known_name.to_owned(), NameStatus::Owner(None));
+            names.insert(well_known_name.to_owned(), (flags, NameStatus::Owner(None)));
 
             return Ok(RequestNameReply::PrimaryOwner);
         }
@@ -643,8 +819,8 @@ impl Connection {
                 async move {
                     loop {
                         let signal = lost_stream.next().await;
-                        let inner = match weak_conn.upgrade() {
-                            Some(conn) => conn.inner.clone(),
+                        let conn = match weak_conn.upgrade() {
+                            Some(conn) => conn,
                             None => break,
                         };
 
@@ -655,10 +831,14 @@ impl Connection {
                                         "Connection `{}` lost name `{}`",
                                         // SAFETY: This is bus connection so unique name can't be
                                         // None.
-                                        inner.unique_name.get().unwrap(),
+                                        conn.unique_name().unwrap(),
                                         well_known_name
                                     );
-                                    inner.registered_names.lock().await.remove(&well_known_name);
+                                    conn.inner
+                                        .registered_names
+                                        .lock()
+                                        .await
+                                        .remove(&well_known_name);
 
                                     break;
                                 }
@@ -702,7 +882,7 @@ impl Connection {
                                 Some(signal) => match signal.args() {
                                     Ok(args) if args.name == well_known_name => {
                                         let mut names = inner.registered_names.lock().await;
-                                        if let Some(status) = names.get_mut(&well_known_name) {
+                                        if let Some((_, status)) = names.get_mut(&well_known_name) {
                                             let task = name_lost_fut.map(|fut| {
                                                 inner.executor.spawn(fut, &lost_task_name)
                                             });
@@ -738,11 +918,69 @@ impl Connection {
             RequestNameReply::Exists => return Err(Error::NameTaken),
         };
 
-        names.insert(well_known_name.to_owned(), status);
+        names.insert(well_known_name.to_owned(), (flags, status));
 
         Ok(reply)
     }
 
+    /// Register a well-known name for this connection and keep track of its ownership.
+    ///
+    /// This is the same as [`Connection::request_name_with_flags`] but instead of the reply of the
+    /// bus, returns a [`NameOwnership`] handle. The handle is a stream of
+    /// [`NameOwnershipEvent`]s, through which you find out when the name is later acquired (e.g
+    /// after a queued request) or lost (e.g to another peer when the name allows replacement).
+    ///
+    /// The name is released when the returned handle is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # zbus::block_on(async {
+    /// use zbus::{
+    ///     connection::NameOwnershipEvent, fdo::RequestNameFlags, Connection,
+    /// };
+    /// use futures_util::stream::StreamExt;
+    ///
+    /// let name = "org.freedesktop.zbus.OwnNameTest";
+    /// let conn1 = Connection::session().await?;
+    /// let mut ownership1 = conn1
+    ///     .own_name(name, RequestNameFlags::AllowReplacement.into())
+    ///     .await?;
+    /// assert_eq!(ownership1.next().await, Some(NameOwnershipEvent::Acquired));
+    ///
+    /// let conn2 = Connection::session().await?;
+    /// let mut ownership2 = conn2
+    ///     .own_name(name, RequestNameFlags::ReplaceExisting.into())
+    ///     .await?;
+    /// assert_eq!(ownership2.next().await, Some(NameOwnershipEvent::Acquired));
+    /// // `conn1` allowed replacement so it lost the name and went back to the queue.
+    /// assert_eq!(ownership1.next().await, Some(NameOwnershipEvent::Lost));
+    ///
+    /// // Once `conn2` gives up the name, `conn1` gets it back.
+    /// drop(ownership2);
+    /// assert_eq!(ownership1.next().await, Some(NameOwnershipEvent::Acquired));
+    /// # Ok::<(), zbus::Error>(())
+    /// # }).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `zbus::Error::NameTaken` if the name is already owned by another peer and
+    /// [`RequestNameFlags::DoNotQueue`] was specified.
+    pub async fn own_name<'w, W>(
+        &self,
+        well_known_name: W,
+        flags: BitFlags<RequestNameFlags>,
+    ) -> Result<NameOwnership>
+    where
+        W: TryInto<WellKnownName<'w>>,
+        W::Error: Into<Error>,
+    {
+        let well_known_name = well_known_name.try_into().map_err(Into::into)?;
+
+        NameOwnership::new(self, well_known_name.into_owned(), flags).await
+    }
+
     /// Deregister a previously registered well-known name for this service on the bus.
     ///
     /// Use this method to deregister a well-known name, registered through
@@ -788,8 +1026,15 @@ impl Connection {
     ///
     /// The unique name is assigned by the message bus or set manually using
     /// [`Connection::set_unique_name`].
-    pub fn unique_name(&self) -> Option<&OwnedUniqueName> {
-        self.inner.unique_name.get()
+    ///
+    /// For connections built with [`Builder::reconnect`], this is the unique name assigned on the
+    /// current socket.
+    pub fn unique_name(&self) -> Option<OwnedUniqueName> {
+        self.inner
+            .unique_name
+            .read()
+            .expect("poisoned lock")
+            .clone()
     }
 
     /// Sets the unique name of the connection (if not already set).
@@ -805,10 +1050,9 @@ impl Connection {
         U::Error: Into<Error>,
     {
         let name = unique_name.try_into().map_err(Into::into)?;
-        self.inner
-            .unique_name
-            .set(name)
-            .expect("unique name already set");
+        let mut unique_name = self.inner.unique_name.write().expect("poisoned lock");
+        assert!(unique_name.is_none(), "unique name already set");
+        *unique_name = Some(name);
 
         Ok(())
     }
@@ -823,9 +1067,85 @@ impl Connection {
         self.inner.msg_receiver.clone().set_capacity(max);
     }
 
+    /// A snapshot of the statistics of the connection.
+    ///
+    /// The message counts cover the whole life of the connection, including the messages zbus
+    /// sends and receives on its own (e.g to register match rules with the bus). See
+    /// [`fdo::Stats`] for exposing these statistics on the bus.
+    pub async fn stats(&self) -> ConnectionStats {
+        let mut stats = self.inner.stats.snapshot();
+        stats.match_rules = self.inner.subscriptions.lock().await.len();
+        let senders = self.inner.msg_senders.lock().await;
+        let shared = senders.shared.iter().map(|(rule, sender)| QueueStats {
+            match_rule: rule.clone(),
+            overflow_policy: OverflowPolicy::Block,
+            len: sender.len(),
+            max_queued: sender.capacity(),
+            streams: sender.receiver_count(),
+            dropped_messages: 0,
+        });
+        let lossy = senders
+            .lossy
+            .iter()
+            .filter(|lossy| !lossy.sender.is_closed())
+            .map(|lossy| QueueStats {
+                match_rule: Some(lossy.rule.clone()),
+                overflow_policy: lossy.policy,
+                len: lossy.sender.len(),
+                max_queued: lossy.sender.capacity(),
+                streams: lossy.sender.receiver_count(),
+                dropped_messages: lossy.dropped,
+            });
+        stats.queues = shared.chain(lossy).collect();
+
+        stats
+    }
+
+    /// The default timeout for method replies.
+    ///
+    /// `None` means method calls wait for their reply indefinitely. See
+    /// [`Builder::method_timeout`] for details.
+    pub fn method_timeout(&self) -> Option<Duration> {
+        self.inner.method_timeout
+    }
+
     /// The server's GUID.
-    pub fn server_guid(&self) -> &str {
-        self.inner.server_guid.as_str()
+    ///
+    /// For connections built with [`Builder::reconnect`], this is the GUID of the server on the
+    /// current socket.
+    pub fn server_guid(&self) -> Guid {
+        self.inner
+            .server_guid
+            .read()
+            .expect("poisoned lock")
+            .clone()
+    }
+
+    /// The current state of the connection.
+    pub fn state(&self) -> ConnectionState {
+        *self.inner.state.lock().expect("poisoned lock")
+    }
+
+    /// Create a stream of the changes to the state of the connection.
+    ///
+    /// This is mostly useful for connections built with [`Builder::reconnect`], to find out when
+    /// the connection is lost and re-established.
+    pub fn receive_state_changes(&self) -> ConnectionStateStream {
+        ConnectionStateStream::new(self.inner.state_receiver.activate_cloned())
+    }
+
+    pub(crate) fn set_state(&self, state: ConnectionState) {
+        let mut current = self.inner.state.lock().expect("poisoned lock");
+        if *current == state {
+            return;
+        }
+        *current = state;
+        // Only fails if there are no active receivers.
+        let _ = self.inner.state_sender.try_broadcast(state);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::SeqCst)
     }
 
     /// The underlying executor.
@@ -944,8 +1264,13 @@ impl Connection {
                     let mut stream = match weak_conn.upgrade() {
                         Some(conn) => {
                             let mut builder = MatchRule::builder().msg_type(Type::MethodCall);
-                            if let Some(unique_name) = conn.unique_name() {
-                                builder = builder.destination(&**unique_name).expect("unique name");
+                            // The unique name changes when the connection is re-established so
+                            // it's checked below instead.
+                            let reconnects = conn.inner.reconnect.is_some();
+                            if let Some(unique_name) = conn.unique_name().filter(|_| !reconnects) {
+                                builder = builder
+                                    .destination(unique_name.into_inner())
+                                    .expect("unique name");
                             }
                             let rule = builder.build();
                             match conn.add_match(rule.into(), None).await {
@@ -978,8 +1303,22 @@ impl Connection {
                         if let Some(conn) = weak_conn.upgrade() {
                             let hdr = msg.header();
                             match hdr.destination() {
-                                // Unique name is already checked by the match rule.
-                                Some(BusName::Unique(_)) | None => (),
+                                // Unique name is already checked by the match rule, unless the
+                                // connection reconnects.
+                                Some(BusName::Unique(dest)) => {
+                                    let ours = conn
+                                        .unique_name()
+                                        .map_or(true, |name| name.as_str() == dest.as_str());
+                                    if conn.inner.reconnect.is_some() && !ours {
+                                        trace!(
+                                            "Got a method call for a different destination: {}",
+                                            dest
+                                        );
+
+                                        continue;
+                                    }
+                                }
+                                None => (),
                                 Some(BusName::WellKnown(dest)) => {
                                     let names = conn.inner.registered_names.lock().await;
                                     // destination doesn't matter if no name has been registered
@@ -1066,6 +1405,7 @@ impl Connection {
                     .msg_senders
                     .lock()
                     .await
+                    .shared
                     .insert(Some(rule), sender);
 
                 Ok(receiver)
@@ -1084,6 +1424,29 @@ impl Connection {
         }
     }
 
+    /// Subscribe to `rule` through a queue dedicated to the caller, dropping messages according to
+    /// `policy` when it's full.
+    ///
+    /// Returns the receiving end of the queue, and the counter of the messages dropped that are
+    /// yet to be reported. The subscription is released through [`Connection::remove_match`], as
+    /// for [`Connection::add_match`].
+    pub(crate) async fn add_lossy_match(
+        &self,
+        rule: OwnedMatchRule,
+        max_queued: Option<usize>,
+        policy: OverflowPolicy,
+    ) -> Result<(Receiver<Result<Message>>, Arc<AtomicUsize>)> {
+        // Only the subscription is needed, not the shared queue.
+        drop(self.add_match(rule.clone(), None).await?);
+
+        let (sender, receiver) = broadcast(max_queued.unwrap_or(DEFAULT_MAX_QUEUED));
+        let lossy = LossySender::new(rule, sender, policy);
+        let missed = lossy.missed.clone();
+        self.inner.msg_senders.lock().await.lossy.push(lossy);
+
+        Ok((receiver, missed))
+    }
+
     pub(crate) async fn remove_match(&self, rule: OwnedMatchRule) -> Result<bool> {
         use std::collections::hash_map::Entry;
         let mut subscriptions = self.inner.subscriptions.lock().await;
@@ -1109,6 +1472,7 @@ impl Connection {
                         .msg_senders
                         .lock()
                         .await
+                        .shared
                         .remove(&Some(rule.into()));
                 }
                 Ok(true)
@@ -1131,11 +1495,10 @@ impl Connection {
             .await?;
         let name = dbus_proxy.hello().await?;
 
-        self.inner
-            .unique_name
-            .set(name)
-            // programmer (probably our) error if this fails.
-            .expect("Attempted to set unique_name twice");
+        let mut unique_name = self.inner.unique_name.write().expect("poisoned lock");
+        // programmer (probably our) error if this fails.
+        assert!(unique_name.is_none(), "Attempted to set unique_name twice");
+        *unique_name = Some(name);
 
         Ok(())
     }
@@ -1143,7 +1506,10 @@ impl Connection {
     pub(crate) async fn new(
         auth: Authenticated,
         bus_connection: bool,
+        method_timeout: Option<Duration>,
         executor: Executor<'static>,
+        reconnect: Option<Reconnect>,
+        interceptors: Vec<Arc<dyn Interceptor>>,
     ) -> Result<Self> {
         #[cfg(unix)]
         let cap_unix_fd = auth.cap_unix_fd;
@@ -1159,8 +1525,8 @@ impl Connection {
         }
         // The unfiltered message channel.
         let (msg_sender, msg_receiver) = create_msg_broadcast_channel!(DEFAULT_MAX_QUEUED);
-        let mut msg_senders = HashMap::new();
-        msg_senders.insert(None, msg_sender);
+        let mut msg_senders = MsgSenders::default();
+        msg_senders.shared.insert(None, msg_sender);
 
         // The special method return & error channel.
         let (method_return_sender, method_return_receiver) =
@@ -1169,30 +1535,43 @@ impl Connection {
             .msg_type(Type::MethodReturn)
             .build()
             .into();
-        msg_senders.insert(Some(rule), method_return_sender.clone());
+        msg_senders
+            .shared
+            .insert(Some(rule), method_return_sender.clone());
         let rule = MatchRule::builder().msg_type(Type::Error).build().into();
-        msg_senders.insert(Some(rule), method_return_sender);
+        msg_senders.shared.insert(Some(rule), method_return_sender);
         let msg_senders = Arc::new(Mutex::new(msg_senders));
         let subscriptions = Mutex::new(HashMap::new());
+        let (mut state_sender, state_receiver) = broadcast(DEFAULT_MAX_QUEUED);
+        state_sender.set_overflow(true);
 
         let connection = Self {
             inner: Arc::new(ConnectionInner {
                 activity_event: Arc::new(Event::new()),
                 socket_write: Mutex::new(auth.socket_write),
-                server_guid: auth.server_guid,
+                stats: Arc::new(Counters::default()),
+                interceptors,
+                server_guid: std::sync::RwLock::new(auth.server_guid),
                 #[cfg(unix)]
                 cap_unix_fd,
                 bus_conn: bus_connection,
-                unique_name: OnceCell::new(),
+                unique_name: std::sync::RwLock::new(None),
+                method_timeout,
                 subscriptions,
                 object_server: OnceCell::new(),
                 object_server_dispatch_task: OnceCell::new(),
                 executor,
                 socket_reader_task: OnceCell::new(),
                 msg_senders,
+                recv_seq: Arc::new(RecvSequence::default()),
                 msg_receiver,
                 method_return_receiver,
                 registered_names: Mutex::new(HashMap::new()),
+                reconnect,
+                state: std::sync::Mutex::new(ConnectionState::Connected),
+                state_sender,
+                state_receiver: state_receiver.deactivate(),
+                closed: AtomicBool::new(false),
             }),
         };
 
@@ -1209,6 +1588,89 @@ impl Connection {
         Builder::system()?.build().await
     }
 
+    /// Create a `Connection` to the message bus that started this process.
+    ///
+    /// See [`Builder::starter`] for details.
+    pub async fn starter() -> Result<Self> {
+        Builder::starter()?.build().await
+    }
+
+    /// Create a pair of peer-to-peer connections, connected to each other.
+    ///
+    /// The connections go through a socket pair of their own, and are authenticated and ready to
+    /// use when returned. This is mostly useful for testing, e.g a [`dbus_interface`]
+    /// implementation, without a bus. File descriptors can be passed between the two.
+    ///
+    /// The first connection is the client side of the pair, the second the server side. Use
+    /// [`Connection::pair_with`] to set up the server side, e.g to serve some interfaces.
+    ///
+    /// [`dbus_interface`]: macro@crate::dbus_interface
+    #[cfg(unix)]
+    pub async fn pair() -> Result<(Self, Self)> {
+        Self::pair_with(Ok).await
+    }
+
+    /// Create a pair of peer-to-peer connections, connected to each other.
+    ///
+    /// Same as [`Connection::pair`] except that `server` gets to set up the builder of the server
+    /// side of the pair, before it's built. Interfaces registered through [`Builder::serve_at`]
+    /// are available as soon as this method returns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///# zbus::block_on(async {
+    /// use zbus::{dbus_interface, dbus_proxy, Connection};
+    ///
+    /// struct Greeter;
+    ///
+    /// #[dbus_interface(name = "org.zbus.Greeter")]
+    /// impl Greeter {
+    ///     fn greet(&self, name: &str) -> String {
+    ///         format!("Hello {name}!")
+    ///     }
+    /// }
+    ///
+    /// #[dbus_proxy(
+    ///     interface = "org.zbus.Greeter",
+    ///     default_service = "org.zbus.Greeter",
+    ///     default_path = "/org/zbus/Greeter"
+    /// )]
+    /// trait Greeter {
+    ///     fn greet(&self, name: &str) -> zbus::Result<String>;
+    /// }
+    ///
+    ///# #[cfg(unix)]
+    ///# {
+    /// let (client, _server) =
+    ///     Connection::pair_with(|server| server.serve_at("/org/zbus/Greeter", Greeter)).await?;
+    /// let greeter = GreeterProxy::new(&client).await?;
+    /// assert_eq!(greeter.greet("Maria").await?, "Hello Maria!");
+    ///# }
+    ///# Ok::<(), zbus::Error>(())
+    ///# }).unwrap();
+    /// ```
+    #[cfg(unix)]
+    pub async fn pair_with<F>(server: F) -> Result<(Self, Self)>
+    where
+        F: FnOnce(Builder<'static>) -> Result<Builder<'static>>,
+    {
+        #[cfg(not(feature = "tokio"))]
+        use std::os::unix::net::UnixStream;
+        #[cfg(feature = "tokio")]
+        use tokio::net::UnixStream;
+
+        let guid = Guid::generate();
+        let (client_socket, server_socket) = UnixStream::pair()?;
+        let server = server(Builder::unix_stream(server_socket))?
+            .server(&guid)
+            .p2p();
+
+        let client = Builder::unix_stream(client_socket).p2p().build();
+
+        futures_util::future::try_join(client, server.build()).await
+    }
+
     /// Returns a listener, notified on various connection activity.
     ///
     /// This function is meant for the caller to implement idle or timeout on inactivity.
@@ -1223,7 +1685,8 @@ impl Connection {
     ///
     /// # Caveats
     ///
-    /// Currently `unix_group_ids` and `linux_security_label` fields are not populated.
+    /// Currently `unix_group_ids` and `linux_security_label` fields are only populated on Linux
+    /// and Android.
     pub async fn peer_credentials(&self) -> io::Result<ConnectionCredentials> {
         self.inner
             .socket_write
@@ -1237,6 +1700,7 @@ impl Connection {
     ///
     /// After this call, all reading and writing operations will fail.
     pub async fn close(self) -> Result<()> {
+        self.inner.closed.store(true, Ordering::SeqCst);
         self.inner.activity_event.notify(usize::MAX);
         self.inner
             .socket_write
@@ -1253,17 +1717,32 @@ impl Connection {
         already_read: Vec<u8>,
     ) {
         let inner = &self.inner;
+        let reader = SocketReader::new(
+            socket_read,
+            inner.msg_senders.clone(),
+            already_read,
+            inner.recv_seq.clone(),
+            inner.activity_event.clone(),
+            inner.stats.clone(),
+        )
+        .with_interceptors(self);
+        let weak_conn = WeakConnection::from(self);
+        let task = if inner.reconnect.is_some() {
+            inner
+                .executor
+                .spawn(reconnect::supervise(weak_conn, reader), "socket reader")
+        } else {
+            let read = async move {
+                reader.receive_msg().await;
+                if let Some(conn) = weak_conn.upgrade() {
+                    conn.set_state(ConnectionState::Closed);
+                }
+            };
+            inner.executor.spawn(read, "socket reader")
+        };
         inner
             .socket_reader_task
-            .set(
-                SocketReader::new(
-                    socket_read,
-                    inner.msg_senders.clone(),
-                    already_read,
-                    inner.activity_event.clone(),
-                )
-                .spawn(&inner.executor),
-            )
+            .set(task)
             .expect("Attempted to set `socket_reader_task` twice");
     }
 }
@@ -1295,6 +1774,9 @@ impl From<&Connection> for WeakConnection {
     }
 }
 
+// The flags the name was requested with, needed to request it again after reconnecting.
+type RegisteredName = (BitFlags<RequestNameFlags>, NameStatus);
+
 #[derive(Debug)]
 enum NameStatus {
     // The task waits for name lost signal if owner allows replacement.
@@ -1309,6 +1791,8 @@ mod tests {
     use ntest::timeout;
     use test_log::test;
 
+    use zvariant::OwnedValue;
+
     use crate::{fdo::DBusProxy, AuthMechanism};
 
     use super::*;
@@ -1448,29 +1932,775 @@ mod tests {
 
     #[cfg(unix)]
     async fn test_unix_p2p() -> Result<()> {
-        let (server1, client1) = unix_p2p_pipe().await?;
-        let (server2, client2) = unix_p2p_pipe().await?;
+        let (server1, client1) = Connection::pair().await?;
+        let (server2, client2) = Connection::pair().await?;
 
         test_p2p(server1, client1, server2, client2).await
     }
 
     #[cfg(unix)]
-    async fn unix_p2p_pipe() -> Result<(Connection, Connection)> {
+    #[test]
+    #[timeout(15000)]
+    fn pair() {
+        crate::utils::block_on(test_pair()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_pair() -> Result<()> {
+        use std::{
+            io::{Read, Write},
+            os::unix::net::UnixStream,
+        };
+
+        struct Pair;
+
+        #[crate::dbus_interface(name = "org.zbus.Pair")]
+        impl Pair {
+            fn write(&self, fd: zvariant::OwnedFd) -> crate::fdo::Result<()> {
+                let mut stream = UnixStream::from(std::os::fd::OwnedFd::from(fd));
+                stream
+                    .write_all(b"Hello")
+                    .map_err(|e| crate::fdo::Error::IOError(e.to_string()))
+            }
+        }
+
+        let (client, _server) =
+            Connection::pair_with(|server| server.serve_at("/org/zbus/Pair", Pair)).await?;
+
+        // The interface is served right away and file descriptors go through.
+        let (mut ours, theirs) = UnixStream::pair().unwrap();
+        client
+            .call_method(
+                None::<()>,
+                "/org/zbus/Pair",
+                Some("org.zbus.Pair"),
+                "Write",
+                &zvariant::Fd::from(&theirs),
+            )
+            .await?;
+        drop(theirs);
+        let mut hello = String::new();
+        ours.read_to_string(&mut hello).unwrap();
+        assert_eq!(hello, "Hello");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn peer_policy() {
+        crate::utils::block_on(test_peer_policy()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_peer_policy() -> Result<()> {
         #[cfg(not(feature = "tokio"))]
         use std::os::unix::net::UnixStream;
         #[cfg(feature = "tokio")]
         use tokio::net::UnixStream;
-        #[cfg(all(windows, not(feature = "tokio")))]
-        use uds_windows::UnixStream;
 
+        let uid = nix::unistd::Uid::effective().as_raw();
+        let gid = nix::unistd::Gid::effective().as_raw();
         let guid = Guid::generate();
-
         let (p0, p1) = UnixStream::pair().unwrap();
+        let (peers_tx, peers_rx) = std::sync::mpsc::channel();
+        let (_client, _server) = futures_util::try_join!(
+            Builder::unix_stream(p1).p2p().build(),
+            Builder::unix_stream(p0)
+                .server(&guid)
+                .p2p()
+                .peer_policy(move |peer| {
+                    peers_tx.send(peer).unwrap();
 
-        futures_util::try_join!(
+                    async { true }
+                })
+                .build(),
+        )?;
+        let peer = peers_rx.try_recv().unwrap();
+        assert_eq!(peer.mechanism(), "EXTERNAL");
+        assert_eq!(peer.identity(), Some(uid.to_string().as_str()));
+        assert_eq!(peer.credentials().unix_user_id(), Some(uid));
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        {
+            assert_eq!(peer.credentials().process_id(), Some(std::process::id()));
+            assert!(peer.credentials().unix_group_ids().unwrap().contains(&gid));
+        }
+        #[cfg(not(any(target_os = "android", target_os = "linux")))]
+        let _ = gid;
+
+        // A rejected client exhausts its mechanisms.
+        let (p0, p1) = UnixStream::pair().unwrap();
+        let (client, server) = futures_util::join!(
             Builder::unix_stream(p1).p2p().build(),
-            Builder::unix_stream(p0).server(&guid).p2p().build(),
+            Builder::unix_stream(p0)
+                .server(&guid)
+                .p2p()
+                .auth_mechanisms(&[AuthMechanism::External, AuthMechanism::Anonymous])
+                .peer_policy(|peer| async move { peer.mechanism() == "DBUS_COOKIE_SHA1" })
+                .build(),
+        );
+        match client.unwrap_err() {
+            Error::Handshake(e) => assert_eq!(e, "Exhausted available AUTH mechanisms"),
+            e => panic!("unexpected error: {e}"),
+        }
+        assert!(server.is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn stats() {
+        crate::utils::block_on(test_stats()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_stats() -> Result<()> {
+        let (client, server) = Connection::pair_with(|server| {
+            server.serve_at("/org/freedesktop/zbus/Stats", fdo::Stats)
+        })
+        .await?;
+
+        let proxy = fdo::StatsProxy::builder(&client)
+            .path("/org/freedesktop/zbus/Stats")?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        let server_stats = proxy.stats().await?;
+        let value = |key: &str| server_stats[key].clone();
+        assert_eq!(value("IncomingMethodCalls"), OwnedValue::from(1u64));
+        assert_eq!(value("OutgoingMessages"), OwnedValue::from(0u64));
+        assert_eq!(value("PendingMethodCalls"), OwnedValue::from(0u32));
+
+        // Nobody is listening for signals on the client.
+        server
+            .emit_signal(None::<()>, "/", "org.zbus.Stats", "Dropped", &())
+            .await?;
+        proxy.stats().await?;
+
+        let client_stats = client.stats().await;
+        let server_stats = server.stats().await;
+        assert_eq!(client_stats.sent(), server_stats.received());
+        // The server might not have accounted for its last reply yet.
+        assert_eq!(server_stats.sent().messages_of_type(Type::Signal), 1);
+        let sent = client_stats.sent();
+        assert_eq!(sent.messages(), 2);
+        assert_eq!(sent.messages_of_type(Type::MethodCall), 2);
+        assert!(sent.bytes() > 0);
+        let received = client_stats.received();
+        assert_eq!(received.messages_of_type(Type::MethodReturn), 2);
+        assert_eq!(received.messages_of_type(Type::Signal), 1);
+        assert_eq!(client_stats.dropped_messages(), 1);
+        assert_eq!(client_stats.pending_method_calls(), 0);
+
+        let streams = client_stats.message_streams();
+        let stream = MessageStream::from(&client);
+        let client_stats = client.stats().await;
+        assert_eq!(client_stats.message_streams(), streams + 1);
+        let unfiltered = client_stats
+            .queues()
+            .iter()
+            .find(|queue| queue.match_rule().is_none())
+            .unwrap();
+        assert_eq!(unfiltered.streams(), 1);
+        assert_eq!(unfiltered.max_queued(), client.max_queued());
+        assert!(unfiltered.is_empty());
+        drop(stream);
+        assert_eq!(client.stats().await.message_streams(), streams);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn overflow_policy() {
+        crate::utils::block_on(test_overflow_policy()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_overflow_policy() -> Result<()> {
+        let (client, server) = Connection::pair_with(|server| {
+            server.serve_at("/org/freedesktop/zbus/Stats", fdo::Stats)
+        })
+        .await?;
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface("org.zbus.Overflow")?
+            .build();
+        let mut oldest = MessageStream::for_match_rule_with_overflow_policy(
+            rule.clone(),
+            &client,
+            Some(2),
+            OverflowPolicy::DropOldest,
+        )
+        .await?;
+        let mut newest = MessageStream::for_match_rule_with_overflow_policy(
+            rule,
+            &client,
+            Some(2),
+            OverflowPolicy::DropNewest,
         )
+        .await?;
+
+        for i in 0..5u32 {
+            server
+                .emit_signal(None::<()>, "/", "org.zbus.Overflow", "Signal", &i)
+                .await?;
+        }
+        // Nobody reads the streams yet but replies still get through.
+        let proxy = fdo::StatsProxy::builder(&client)
+            .path("/org/freedesktop/zbus/Stats")?
+            .build()
+            .await?;
+        proxy.stats().await?;
+
+        let stats = client.stats().await;
+        let lossy: Vec<_> = stats
+            .queues()
+            .iter()
+            .filter(|queue| queue.overflow_policy() != OverflowPolicy::Block)
+            .collect();
+        assert_eq!(lossy.len(), 2);
+        for queue in lossy {
+            assert_eq!(queue.len(), 2);
+            assert_eq!(queue.max_queued(), 2);
+            assert_eq!(queue.dropped_messages(), 3);
+        }
+
+        for (stream, expected) in [(&mut oldest, [3u32, 4]), (&mut newest, [0, 1])] {
+            let err = stream.next().await.unwrap().unwrap_err();
+            assert_eq!(err, Error::MissedMessages(3));
+            for i in expected {
+                let msg = stream.try_next().await?.unwrap();
+                assert_eq!(msg.body().deserialize::<u32>()?, i);
+            }
+        }
+
+        // The queues have room again.
+        server
+            .emit_signal(None::<()>, "/", "org.zbus.Overflow", "Signal", &5u32)
+            .await?;
+        for stream in [&mut oldest, &mut newest] {
+            let msg = stream.try_next().await?.unwrap();
+            assert_eq!(msg.body().deserialize::<u32>()?, 5);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn interceptors() {
+        crate::utils::block_on(test_interceptors()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_interceptors() -> Result<()> {
+        #[cfg(not(feature = "tokio"))]
+        use std::os::unix::net::UnixStream;
+        #[cfg(feature = "tokio")]
+        use tokio::net::UnixStream;
+
+        // Answers `Local` calls itself, drops `Hidden` signals and renames `Rename` ones.
+        struct Client;
+
+        #[async_trait::async_trait]
+        impl Interceptor for Client {
+            async fn outgoing(&self, msg: Message) -> Interception {
+                let header = msg.header();
+                match header.member().map(|m| m.as_str()) {
+                    Some("Local") => Interception::Reply(
+                        Message::method_reply(&msg)
+                            .unwrap()
+                            .build(&("local"))
+                            .unwrap(),
+                    ),
+                    Some("Hidden") => Interception::Drop,
+                    Some("Rename") => Interception::Continue(
+                        Message::signal("/", "org.zbus.Interceptors", "Renamed")
+                            .unwrap()
+                            .build(&())
+                            .unwrap(),
+                    ),
+                    _ => Interception::Continue(msg),
+                }
+            }
+        }
+
+        // Answers `Ping` calls and drops `Noise` signals.
+        struct Server;
+
+        #[async_trait::async_trait]
+        impl Interceptor for Server {
+            async fn incoming(&self, msg: Message) -> Interception {
+                let header = msg.header();
+                match header.member().map(|m| m.as_str()) {
+                    Some("Ping") => Interception::Reply(
+                        Message::method_reply(&msg)
+                            .unwrap()
+                            .build(&("pong"))
+                            .unwrap(),
+                    ),
+                    Some("Noise") => Interception::Drop,
+                    _ => Interception::Continue(msg),
+                }
+            }
+        }
+
+        let guid = Guid::generate();
+        let (p0, p1) = UnixStream::pair().unwrap();
+        let (client, server) = futures_util::try_join!(
+            Builder::unix_stream(p1).p2p().interceptor(Client).build(),
+            Builder::unix_stream(p0)
+                .server(&guid)
+                .p2p()
+                .interceptor(Server)
+                .build(),
+        )?;
+        let mut stream = MessageStream::from(&server);
+
+        let mut since = Sequence::default();
+        for (method, expected) in [("Ping", "pong"), ("Local", "local")] {
+            let reply = client
+                .call_method(None::<()>, "/", Some("org.zbus.Interceptors"), method, &())
+                .await?;
+            assert_eq!(reply.body().deserialize::<&str>()?, expected);
+            // The local reply comes after the ones received on the socket.
+            assert!(reply.recv_position() > since);
+            since = reply.recv_position();
+        }
+        for signal in ["Hidden", "Rename", "Noise", "Last"] {
+            client
+                .emit_signal(None::<()>, "/", "org.zbus.Interceptors", signal, &())
+                .await?;
+        }
+
+        // The server only gets to see what went through both interceptors.
+        for expected in ["Renamed", "Last"] {
+            let msg = stream.try_next().await?.unwrap();
+            assert_eq!(msg.header().member().unwrap(), expected);
+        }
+        // `Local` and `Hidden` never made it to the socket.
+        assert_eq!(client.stats().await.sent().messages(), 4);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn method_timeout() {
+        crate::utils::block_on(test_method_timeout()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_method_timeout() -> Result<()> {
+        #[cfg(not(feature = "tokio"))]
+        use std::os::unix::net::UnixStream;
+        #[cfg(feature = "tokio")]
+        use tokio::net::UnixStream;
+
+        let guid = Guid::generate();
+        let (p0, p1) = UnixStream::pair().unwrap();
+        let (client, server) = futures_util::try_join!(
+            Builder::unix_stream(p1)
+                .p2p()
+                .method_timeout(std::time::Duration::from_millis(50))
+                .build(),
+            Builder::unix_stream(p0).server(&guid).p2p().build(),
+        )?;
+        assert_eq!(
+            client.method_timeout(),
+            Some(std::time::Duration::from_millis(50))
+        );
+        let mut server_stream = MessageStream::from(&server);
+
+        // The server never replies to these.
+        let err = client
+            .call_method(None::<()>, "/", Some("org.zbus.p2p"), "Test", &())
+            .await
+            .unwrap_err();
+        assert_eq!(err, Error::Timeout);
+        let proxy: crate::Proxy<'_> = crate::proxy::Builder::new(&client)
+            .path("/")?
+            .destination(":1.0")?
+            .interface("org.zbus.p2p")?
+            .cache_properties(CacheProperties::No)
+            .method_timeout(std::time::Duration::from_secs(10))
+            .build()
+            .await?;
+        assert_eq!(
+            proxy.method_timeout(),
+            Some(std::time::Duration::from_secs(10))
+        );
+        let err = proxy
+            .call_with_timeout::<_, _, ()>("Test", std::time::Duration::from_millis(50), &())
+            .await
+            .unwrap_err();
+        assert_eq!(err, Error::Timeout);
+
+        // The proxy's timeout overrides the connection's.
+        let server_future = async {
+            let mut calls = 0;
+            loop {
+                let msg = server_stream.try_next().await?.unwrap();
+                if msg.message_type() != Type::MethodCall {
+                    continue;
+                }
+                calls += 1;
+                if calls >= 3 {
+                    // Give the connection's default timeout a chance to fire.
+                    crate::utils::sleep(std::time::Duration::from_millis(100)).await;
+                    server.reply(&msg, &("yay")).await?;
+                }
+                if calls == 4 {
+                    return Ok::<_, Error>(());
+                }
+            }
+        };
+        let client_future = async {
+            let reply = proxy.call::<_, _, String>("Test", &()).await?;
+            assert_eq!(reply, "yay");
+
+            // So does the timeout of a single call.
+            let reply = client
+                .call_method_with_timeout(
+                    None::<()>,
+                    "/",
+                    Some("org.zbus.p2p"),
+                    "Test",
+                    std::time::Duration::from_secs(10),
+                    &(),
+                )
+                .await?;
+            reply.body().deserialize::<String>()
+        };
+        let (reply, _) = futures_util::try_join!(client_future, server_future)?;
+        assert_eq!(reply, "yay");
+
+        Ok(())
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn own_name() {
+        crate::utils::block_on(test_own_name()).unwrap();
+    }
+
+    async fn test_own_name() -> Result<()> {
+        let name = "org.zbus.OwnNameTest";
+        let conn1 = Connection::session().await?;
+        let conn2 = Connection::session().await?;
+        let dbus = DBusProxy::new(&conn2).await?;
+
+        let mut ownership1 = conn1.own_name(name, BitFlags::empty()).await?;
+        assert_eq!(ownership1.next().await, Some(NameOwnershipEvent::Acquired));
+        assert!(ownership1.is_owner());
+        assert_eq!(ownership1.name().as_str(), name);
+
+        // A second request ends up in the queue..
+        let mut ownership2 = conn2.own_name(name, BitFlags::empty()).await?;
+        assert_eq!(ownership2.next().await, Some(NameOwnershipEvent::Queued));
+        assert!(!ownership2.is_owner());
+
+        // ..until the first owner drops its handle.
+        drop(ownership1);
+        assert_eq!(ownership2.next().await, Some(NameOwnershipEvent::Acquired));
+        assert_eq!(
+            dbus.get_name_owner(name.try_into()?).await?,
+            *conn2.unique_name().unwrap()
+        );
+
+        assert!(ownership2.release().await?);
+        assert!(!dbus.name_has_owner(name.try_into()?).await?);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn reconnect() {
+        crate::utils::block_on(test_reconnect()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_reconnect() -> Result<()> {
+        use crate::{broker::Broker, MessageStream};
+
+        struct Echo;
+
+        #[crate::dbus_interface(name = "org.zbus.ReconnectTest")]
+        impl Echo {
+            fn echo(&self, s: &str) -> String {
+                s.to_string()
+            }
+        }
+
+        let name = "org.zbus.ReconnectTest";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bus");
+        let address = format!("unix:path={}", path.display());
+        let broker = Broker::builder(address.as_str())?.build().await?;
+
+        let conn = Builder::address(address.as_str())?
+            .reconnect(ReconnectPolicy::new().initial_delay(Duration::from_millis(10)))
+            .name(name)?
+            .serve_at("/org/zbus/ReconnectTest", Echo)?
+            .build()
+            .await?;
+        assert_eq!(conn.state(), ConnectionState::Connected);
+        let mut states = conn.receive_state_changes();
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface(name)?
+            .member("Ping")?
+            .build();
+        let mut signals = MessageStream::for_match_rule(rule, &conn, None).await?;
+        let guid = conn.server_guid().to_owned();
+
+        // Take the bus down and wait for its socket to be gone before bringing it back.
+        drop(broker);
+        assert_eq!(states.next().await, Some(ConnectionState::Reconnecting));
+        while path.exists() {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let _broker = Broker::builder(address.as_str())?.build().await?;
+        assert_eq!(states.next().await, Some(ConnectionState::Connected));
+        assert_eq!(conn.state(), ConnectionState::Connected);
+        // A new bus, a new GUID.
+        assert_ne!(conn.server_guid(), guid);
+
+        // The name is owned again and the object is still served.
+        let client = Builder::address(address.as_str())?.build().await?;
+        let dbus = DBusProxy::new(&client).await?;
+        assert_eq!(
+            dbus.get_name_owner(name.try_into()?).await?,
+            *conn.unique_name().unwrap()
+        );
+        let reply = client
+            .call_method(
+                Some(name),
+                "/org/zbus/ReconnectTest",
+                Some(name),
+                "Echo",
+                &"hello",
+            )
+            .await?;
+        assert_eq!(reply.body().deserialize::<String>()?, "hello");
+
+        // The signal subscription is back too.
+        client
+            .emit_signal(None::<()>, "/org/zbus/ReconnectTest", name, "Ping", &())
+            .await?;
+        let signal = signals.try_next().await?.unwrap();
+        assert_eq!(signal.header().member().unwrap(), "Ping");
+
+        // And once more, to make sure the details of the latest socket are the ones reported.
+        let guid = conn.server_guid().to_owned();
+        drop((client, _broker));
+        assert_eq!(states.next().await, Some(ConnectionState::Reconnecting));
+        while path.exists() {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let _broker = Broker::builder(address.as_str())?.build().await?;
+        assert_eq!(states.next().await, Some(ConnectionState::Connected));
+        assert_ne!(conn.server_guid(), guid);
+        let client = Builder::address(address.as_str())?.build().await?;
+        let dbus = DBusProxy::new(&client).await?;
+        let owner = dbus.get_name_owner(name.try_into()?).await?;
+        assert_eq!(owner, *conn.unique_name().unwrap());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn reconnect_while_sending() {
+        crate::utils::block_on(test_reconnect_while_sending()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_reconnect_while_sending() -> Result<()> {
+        use crate::broker::Broker;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bus");
+        let address = format!("unix:path={}", path.display());
+        let broker = Broker::builder(address.as_str())?.build().await?;
+
+        struct DelayHello;
+
+        #[async_trait::async_trait]
+        impl Interceptor for DelayHello {
+            async fn outgoing(&self, msg: Message) -> Interception {
+                if msg.header().member().map_or(false, |m| m == "Hello") {
+                    sleep(Duration::from_millis(100)).await;
+                }
+
+                Interception::Continue(msg)
+            }
+        }
+
+        // A single attempt, once the bus is back, must do.
+        let policy = ReconnectPolicy::new()
+            .initial_delay(Duration::from_millis(200))
+            .max_attempts(1);
+        let conn = Builder::address(address.as_str())?
+            .reconnect(policy)
+            .interceptor(DelayHello)
+            .build()
+            .await?;
+        let mut states = conn.receive_state_changes();
+
+        // Keep sending from other threads while the connection is re-established, and `Hello` is
+        // delayed. The bus disconnects peers that send anything before `Hello` so none of it can go
+        // through the new socket too early.
+        let stop = Arc::new(AtomicBool::new(false));
+        let senders: Vec<_> = (0..4)
+            .map(|_| {
+                let conn = conn.clone();
+                let stop = stop.clone();
+                std::thread::spawn(move || {
+                    crate::utils::block_on(async move {
+                        while !stop.load(Ordering::Relaxed) {
+                            let _ = conn
+                                .emit_signal(
+                                    None::<()>,
+                                    "/org/zbus/Test",
+                                    "org.zbus.Test",
+                                    "Ping",
+                                    &(),
+                                )
+                                .await;
+                        }
+                    })
+                })
+            })
+            .collect();
+
+        drop(broker);
+        assert_eq!(states.next().await, Some(ConnectionState::Reconnecting));
+        while path.exists() {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let _broker = Broker::builder(address.as_str())?.build().await?;
+        assert_eq!(states.next().await, Some(ConnectionState::Connected));
+
+        // The connection stays up, with the sending going on.
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(conn.state(), ConnectionState::Connected);
+        let dbus = DBusProxy::new(&conn).await?;
+        let unique_name = conn.unique_name().unwrap();
+        assert!(dbus.name_has_owner(unique_name.as_ref().into()).await?);
+
+        stop.store(true, Ordering::Relaxed);
+        for sender in senders {
+            sender.join().unwrap();
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn reconnect_name_taken() {
+        crate::utils::block_on(test_reconnect_name_taken()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_reconnect_name_taken() -> Result<()> {
+        use crate::broker::Broker;
+
+        let name = "org.zbus.ReconnectNameTaken";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bus");
+        let address = format!("unix:path={}", path.display());
+        let broker = Broker::builder(address.as_str())?.build().await?;
+
+        // Leave enough time for another peer to take the name before we're back.
+        let conn = Builder::address(address.as_str())?
+            .reconnect(ReconnectPolicy::new().initial_delay(Duration::from_millis(500)))
+            .build()
+            .await?;
+        let mut states = conn.receive_state_changes();
+        let mut ownership = conn.own_name(name, BitFlags::empty()).await?;
+        assert_eq!(ownership.next().await, Some(NameOwnershipEvent::Acquired));
+
+        drop(broker);
+        assert_eq!(states.next().await, Some(ConnectionState::Reconnecting));
+        while path.exists() {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let _broker = Broker::builder(address.as_str())?.build().await?;
+        let other = Builder::address(address.as_str())?.build().await?;
+        other.request_name(name).await?;
+        assert_eq!(states.next().await, Some(ConnectionState::Connected));
+
+        // We're told about the loss and queued for the name again.
+        assert_eq!(ownership.next().await, Some(NameOwnershipEvent::Lost));
+        assert!(!ownership.is_owner());
+        other.release_name(name).await?;
+        assert_eq!(ownership.next().await, Some(NameOwnershipEvent::Acquired));
+        let dbus = DBusProxy::new(&other).await?;
+        assert_eq!(
+            dbus.get_name_owner(name.try_into()?).await?,
+            *conn.unique_name().unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[timeout(15000)]
+    fn unixexec() {
+        crate::utils::block_on(test_unixexec()).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn test_unixexec() -> Result<()> {
+        use crate::address::{Address, UnixExecAddress};
+
+        // `systemd-stdio-bridge` relays its standard input and output to the bus.
+        let bus = Address::session()?;
+        let address = UnixExecAddress::new("systemd-stdio-bridge", [format!("--bus-path={bus}")]);
+        let conn = Builder::address(Address::UnixExec(address))?
+            .name("org.zbus.UnixExecTest")?
+            .build()
+            .await?;
+
+        let other = Connection::session().await?;
+        let dbus = DBusProxy::new(&other).await?;
+        assert_eq!(
+            dbus.get_name_owner("org.zbus.UnixExecTest".try_into()?)
+                .await?,
+            *conn.unique_name().unwrap()
+        );
+
+        // The bridge goes away with the connection, and the name with it.
+        let mut owner_changes = dbus
+            .receive_name_owner_changed_with_args(&[(0, "org.zbus.UnixExecTest"), (2, "")])
+            .await?;
+        drop(conn);
+        owner_changes.next().await.unwrap();
+
+        // Processes are killed and reaped, without waiting for them on drop.
+        let address = UnixExecAddress::new("sleep", ["10"]);
+        let stream = crate::connection::socket::UnixExecStream::spawn(&address)?;
+        let proc_path = format!("/proc/{}", stream.child_id());
+        drop(stream);
+        while std::path::Path::new(&proc_path).exists() {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        Ok(())
     }
 
     // Compile-test only since we don't have a VM setup to run this with/in.
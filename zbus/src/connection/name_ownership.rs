@@ -0,0 +1,210 @@
+use enumflags2::BitFlags;
+use futures_core::stream;
+use futures_util::{stream::FusedStream, StreamExt};
+use static_assertions::assert_impl_all;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::{debug, trace_span, Instrument};
+use zbus_names::WellKnownName;
+
+use crate::{
+    fdo::{DBusProxy, ReleaseNameReply, RequestNameFlags, RequestNameReply},
+    message::Type,
+    CacheProperties, Connection, MatchRule, MessageStream, Result,
+};
+
+/// A change in the ownership of a name requested through [`Connection::own_name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameOwnershipEvent {
+    /// The connection is now the primary owner of the name.
+    Acquired,
+    /// The connection is no longer the primary owner of the name.
+    ///
+    /// Unless the name was requested with [`RequestNameFlags::DoNotQueue`], the request goes back
+    /// to the queue and the name can be acquired again later.
+    Lost,
+    /// The request is queued, waiting for the current owner to release the name.
+    Queued,
+}
+
+/// A handle to a well-known name requested through [`Connection::own_name`].
+///
+/// This is a [`stream::Stream`] of [`NameOwnershipEvent`]s, based on the `NameAcquired` and
+/// `NameLost` signals from the bus. The first event reflects the outcome of the request:
+/// [`NameOwnershipEvent::Acquired`] if the connection became the primary owner of the name and
+/// [`NameOwnershipEvent::Queued`] otherwise.
+///
+/// The name is released when the handle is dropped. Use [`NameOwnership::release`] to release it
+/// explicitly and find out the result.
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+#[must_use = "the name is released when the handle is dropped"]
+pub struct NameOwnership {
+    conn: Connection,
+    name: WellKnownName<'static>,
+    // The last event yielded (or to be yielded first).
+    state: NameOwnershipEvent,
+    initial_yielded: bool,
+    released: bool,
+    #[derivative(Debug = "ignore")]
+    stream: Option<MessageStream>,
+}
+
+assert_impl_all!(NameOwnership: Send, Sync, Unpin);
+
+impl NameOwnership {
+    pub(super) async fn new(
+        conn: &Connection,
+        name: WellKnownName<'static>,
+        flags: BitFlags<RequestNameFlags>,
+    ) -> Result<Self> {
+        // Subscribe first so we don't miss any changes right after the request.
+        //
+        // The bus only sends these signals to the owner, so there's no need to match on our unique
+        // name, which changes if the connection is re-established.
+        let stream = if conn.is_bus() {
+            let rule = MatchRule::builder()
+                .msg_type(Type::Signal)
+                .sender("org.freedesktop.DBus")?
+                .path("/org/freedesktop/DBus")?
+                .interface("org.freedesktop.DBus")?
+                .add_arg(name.as_str())?
+                .build();
+
+            Some(MessageStream::for_match_rule(rule, conn, None).await?)
+        } else {
+            None
+        };
+
+        let state = match conn.request_name_with_flags(name.clone(), flags).await? {
+            RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner => {
+                NameOwnershipEvent::Acquired
+            }
+            RequestNameReply::InQueue => NameOwnershipEvent::Queued,
+            // `request_name_with_flags` turns this into an error.
+            RequestNameReply::Exists => unreachable!("name taken but no error returned"),
+        };
+
+        Ok(Self {
+            conn: conn.clone(),
+            name,
+            state,
+            initial_yielded: false,
+            released: false,
+            stream,
+        })
+    }
+
+    /// The requested name.
+    pub fn name(&self) -> &WellKnownName<'static> {
+        &self.name
+    }
+
+    /// Whether the connection is the primary owner of the name, as of the last event yielded.
+    pub fn is_owner(&self) -> bool {
+        self.state == NameOwnershipEvent::Acquired
+    }
+
+    /// Release the name (or cancel the queued request).
+    ///
+    /// Returns `Ok(true)` if the connection owned the name or was in the queue for it.
+    pub async fn release(mut self) -> Result<bool> {
+        self.released = true;
+
+        release_name(&self.conn, self.name.clone()).await
+    }
+}
+
+async fn release_name(conn: &Connection, name: WellKnownName<'_>) -> Result<bool> {
+    if conn.release_name(name.clone()).await? {
+        return Ok(true);
+    }
+    if !conn.is_bus() {
+        return Ok(false);
+    }
+
+    // The connection forgets about names it lost to other peers but our request may still be in
+    // the queue.
+    DBusProxy::builder(conn)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?
+        .release_name(name)
+        .await
+        .map(|reply| reply == ReleaseNameReply::Released)
+        .map_err(Into::into)
+}
+
+impl stream::Stream for NameOwnership {
+    type Item = NameOwnershipEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if !this.initial_yielded {
+            this.initial_yielded = true;
+
+            return Poll::Ready(Some(this.state));
+        }
+        let stream = match &mut this.stream {
+            Some(stream) => stream,
+            // Nothing is going to change for p2p connections.
+            None => return Poll::Ready(None),
+        };
+
+        loop {
+            let msg = match futures_util::ready!(stream.poll_next_unpin(cx)) {
+                Some(Ok(msg)) => msg,
+                Some(Err(e)) => {
+                    debug!("Error from the name ownership signal stream: {e}");
+
+                    continue;
+                }
+                None => return Poll::Ready(None),
+            };
+            let event = match msg.header().member().map(|m| m.as_str()) {
+                Some("NameAcquired") => NameOwnershipEvent::Acquired,
+                Some("NameLost") => NameOwnershipEvent::Lost,
+                _ => continue,
+            };
+            // The signals for the initial request can arrive before its reply.
+            if event == this.state {
+                continue;
+            }
+            this.state = event;
+
+            return Poll::Ready(Some(event));
+        }
+    }
+}
+
+impl FusedStream for NameOwnership {
+    fn is_terminated(&self) -> bool {
+        self.initial_yielded
+            && self
+                .stream
+                .as_ref()
+                .map(|s| s.is_terminated())
+                .unwrap_or(true)
+    }
+}
+
+impl Drop for NameOwnership {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+
+        let conn = self.conn.clone();
+        let name = self.name.clone();
+        let task_name = format!("Release name `{name}`");
+        let release = async move {
+            if let Err(e) = release_name(&conn, name).await {
+                debug!("Failed to release name: {e}");
+            }
+        }
+        .instrument(trace_span!("{}", task_name));
+        self.conn.executor().spawn(release, &task_name).detach();
+    }
+}
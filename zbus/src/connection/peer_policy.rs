@@ -0,0 +1,44 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use crate::fdo::ConnectionCredentials;
+
+/// A client that successfully authenticated to a server connection.
+///
+/// This is what the policy set through [`Builder::peer_policy`] gets to decide whether the client
+/// is accepted.
+///
+/// [`Builder::peer_policy`]: super::Builder::peer_policy
+#[derive(Debug)]
+pub struct AuthenticatedPeer {
+    pub(crate) credentials: ConnectionCredentials,
+    pub(crate) mechanism: String,
+    pub(crate) identity: Option<String>,
+}
+
+impl AuthenticatedPeer {
+    /// The credentials of the peer, as reported by the socket.
+    ///
+    /// The fields are populated on the best effort basis, see [`Connection::peer_credentials`].
+    ///
+    /// [`Connection::peer_credentials`]: crate::Connection::peer_credentials
+    pub fn credentials(&self) -> &ConnectionCredentials {
+        &self.credentials
+    }
+
+    /// The name of the mechanism the client authenticated with, e.g `EXTERNAL`.
+    pub fn mechanism(&self) -> &str {
+        &self.mechanism
+    }
+
+    /// The identity the client claimed, if any.
+    ///
+    /// For `EXTERNAL` and `DBUS_COOKIE_SHA1`, this is the user ID (or the SID on Windows) the
+    /// client authenticated as. It's `None` for `ANONYMOUS` and custom mechanisms, as well as when
+    /// the client let the server derive its identity from the credentials.
+    pub fn identity(&self) -> Option<&str> {
+        self.identity.as_deref()
+    }
+}
+
+pub(crate) type PeerPolicy =
+    Arc<dyn Fn(AuthenticatedPeer) -> Pin<Box<dyn Future<Output = bool> + Send>> + Send + Sync>;
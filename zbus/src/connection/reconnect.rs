@@ -0,0 +1,488 @@
+use async_broadcast::Receiver;
+use futures_core::{stream, Future};
+use futures_util::{
+    future::{select, Either},
+    stream::FusedStream,
+    StreamExt,
+};
+use static_assertions::assert_impl_all;
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tracing::{debug, info, warn};
+use zbus_names::{OwnedUniqueName, WellKnownName};
+
+use crate::{
+    address::AddressList,
+    fdo::RequestNameReply,
+    message::{Sequence, Type},
+    utils::sleep,
+    AuthMechanism, Connection, Error, Guid, MatchRule, Message, OwnedMatchRule, Result,
+};
+
+use super::{
+    builder::connect_address, handshake::Authenticated, intercept, sasl::SaslMechanism,
+    socket::WriteHalf, socket_reader::close_senders, Interception, NameStatus, SocketReader,
+    WeakConnection,
+};
+
+/// The policy for reconnecting a connection after losing the socket.
+///
+/// Reconnection attempts are made with an exponential backoff: the delay before the first attempt
+/// is [`ReconnectPolicy::initial_delay`] and it's doubled after each failed attempt, up to
+/// [`ReconnectPolicy::max_delay`].
+///
+/// See [`Builder::reconnect`] for details.
+///
+/// [`Builder::reconnect`]: super::Builder::reconnect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_attempts: Option<usize>,
+}
+
+assert_impl_all!(ReconnectPolicy: Send, Sync, Unpin);
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Create a policy retrying indefinitely, starting after 100ms and waiting up to 30s between
+    /// attempts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the delay before the first reconnection attempt.
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+
+        self
+    }
+
+    /// Set the maximum delay between two reconnection attempts.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+
+        self
+    }
+
+    /// Give up after `attempts` failed reconnection attempts.
+    ///
+    /// The connection is then closed for good.
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = Some(attempts);
+
+        self
+    }
+
+    fn delay(&self, attempt: usize) -> Duration {
+        let factor = 1u32.checked_shl(attempt as u32).unwrap_or(u32::MAX);
+
+        self.initial_delay
+            .checked_mul(factor)
+            .map(|delay| delay.min(self.max_delay))
+            .unwrap_or(self.max_delay)
+    }
+}
+
+/// The state of a [`Connection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The connection is established.
+    Connected,
+    /// The socket was lost and the connection is being re-established.
+    ///
+    /// Only connections built with [`Builder::reconnect`] get into this state.
+    ///
+    /// [`Builder::reconnect`]: super::Builder::reconnect
+    Reconnecting,
+    /// The connection is closed for good.
+    Closed,
+}
+
+/// A [`stream::Stream`] of [`ConnectionState`] changes.
+///
+/// Use [`Connection::receive_state_changes`] to create an instance of this type.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct ConnectionStateStream {
+    receiver: Receiver<ConnectionState>,
+}
+
+assert_impl_all!(ConnectionStateStream: Send, Sync, Unpin);
+
+impl ConnectionStateStream {
+    pub(super) fn new(receiver: Receiver<ConnectionState>) -> Self {
+        Self { receiver }
+    }
+}
+
+impl stream::Stream for ConnectionStateStream {
+    type Item = ConnectionState;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().receiver.poll_next_unpin(cx)
+    }
+}
+
+impl FusedStream for ConnectionStateStream {
+    fn is_terminated(&self) -> bool {
+        self.receiver.is_terminated()
+    }
+}
+
+/// What's needed to re-establish a connection.
+#[derive(Debug)]
+pub(crate) struct Reconnect {
+    pub(super) policy: ReconnectPolicy,
+    pub(super) address: AddressList,
+    pub(super) auth_mechanisms: Option<VecDeque<AuthMechanism>>,
+    pub(super) sasl_mechanisms: Vec<Arc<dyn SaslMechanism>>,
+}
+
+/// The state of the connection on a new socket, before the socket is put to use.
+#[derive(Debug)]
+struct Restored {
+    unique_name: Option<OwnedUniqueName>,
+    // The reply to requesting each of the registered names again.
+    names: Vec<(WellKnownName<'static>, Result<RequestNameReply>)>,
+}
+
+// Resolves to the reading error.
+type ReadingFuture = Pin<Box<dyn Future<Output = Error> + Send>>;
+
+/// Keep reading from the socket, re-establishing the connection every time it's lost.
+pub(super) async fn supervise(conn: WeakConnection, reader: SocketReader) {
+    let mut reading: ReadingFuture = Box::pin(reader.receive_until_error());
+    loop {
+        let error = reading.await;
+        let conn_ = match conn.upgrade() {
+            Some(conn) => conn,
+            None => return,
+        };
+        if conn_.is_closed() {
+            conn_.close_streams(error).await;
+
+            return;
+        }
+        info!("Connection lost ({error}), reconnecting..");
+        conn_.set_state(ConnectionState::Reconnecting);
+        conn_.fail_pending_calls(error.clone()).await;
+        drop(conn_);
+
+        reading = match reconnect(&conn).await {
+            Some(reading) => reading,
+            None => {
+                if let Some(conn) = conn.upgrade() {
+                    conn.close_streams(error).await;
+                }
+
+                return;
+            }
+        };
+    }
+}
+
+// Returns the future reading from the new socket, or `None` if we gave up.
+async fn reconnect(conn: &WeakConnection) -> Option<ReadingFuture> {
+    let policy = conn.upgrade()?.inner.reconnect.as_ref()?.policy;
+    let mut attempt = 0;
+    loop {
+        if policy.max_attempts.map_or(false, |max| attempt >= max) {
+            warn!("Giving up on reconnecting after {attempt} attempts");
+
+            return None;
+        }
+        sleep(policy.delay(attempt)).await;
+        attempt += 1;
+
+        let conn = conn.upgrade()?;
+        if conn.is_closed() {
+            return None;
+        }
+        let (reading, server_guid, mut write) = match conn.reconnect_socket().await {
+            Ok(socket) => socket,
+            Err(e) => {
+                debug!("Reconnection attempt {attempt} failed: {e}");
+
+                continue;
+            }
+        };
+        // The socket needs to be read from while the state is being restored, to receive replies.
+        let restored = match select(reading, Box::pin(conn.restore_state(&mut write))).await {
+            Either::Left((e, _)) => {
+                debug!("Connection lost while restoring its state: {e}");
+
+                continue;
+            }
+            Either::Right((restored, reading)) => restored.map(|restored| (restored, reading)),
+        };
+        match restored {
+            Err(e) => {
+                debug!("Failed to restore the connection state: {e}");
+                if let Err(e) = write.close().await {
+                    debug!("Failed to close the socket: {e}");
+                }
+            }
+            Ok((restored, reading)) => {
+                info!("Reconnected after {attempt} attempt(s)");
+                conn.publish_socket(write, server_guid, restored).await;
+                conn.set_state(ConnectionState::Connected);
+
+                return Some(reading);
+            }
+        }
+    }
+}
+
+impl Connection {
+    // Connect and authenticate a new socket, and start reading from it.
+    //
+    // The new socket isn't used for sending messages until its state is restored: a bus would
+    // disconnect us if anything but `Hello` comes first.
+    async fn reconnect_socket(&self) -> Result<(ReadingFuture, Guid, Box<dyn WriteHalf>)> {
+        let inner = &self.inner;
+        // SAFETY: Only called for connections set up to reconnect.
+        let reconnect = inner.reconnect.as_ref().unwrap();
+        let stream = connect_address(reconnect.address.clone()).await?;
+        let mut auth = Authenticated::client(
+            stream,
+            reconnect.auth_mechanisms.clone(),
+            &reconnect.sasl_mechanisms,
+        )
+        .await?;
+        // SAFETY: `Authenticated` is always built with these fields set to `Some`.
+        let socket_read = auth.socket_read.take().unwrap();
+        let already_received_bytes = auth.already_received_bytes.take().unwrap();
+
+        let reader = SocketReader::new(
+            socket_read,
+            inner.msg_senders.clone(),
+            already_received_bytes,
+            inner.recv_seq.clone(),
+            inner.activity_event.clone(),
+            inner.stats.clone(),
+        )
+        .with_interceptors(self);
+
+        Ok((
+            Box::pin(reader.receive_until_error()),
+            auth.server_guid,
+            auth.socket_write,
+        ))
+    }
+
+    // Re-establish the state the connection had on the bus, through the new socket.
+    async fn restore_state(&self, write: &mut Box<dyn WriteHalf>) -> Result<Restored> {
+        if !self.is_bus() {
+            return Ok(Restored {
+                unique_name: None,
+                names: vec![],
+            });
+        }
+
+        let unique_name = self
+            .call_bus_method(write, "Hello", &())
+            .await?
+            .body()
+            .deserialize()?;
+
+        let rules: Vec<OwnedMatchRule> = self
+            .inner
+            .subscriptions
+            .lock()
+            .await
+            .keys()
+            .filter(|rule| rule.msg_type().unwrap_or(Type::Signal) == Type::Signal)
+            .cloned()
+            .collect();
+        for rule in rules {
+            self.call_bus_method(write, "AddMatch", &rule.into_inner())
+                .await?;
+        }
+
+        let requests: Vec<_> = self
+            .inner
+            .registered_names
+            .lock()
+            .await
+            .iter()
+            .map(|(name, (flags, _))| (name.clone(), *flags))
+            .collect();
+        let mut names = Vec::with_capacity(requests.len());
+        for (name, flags) in requests {
+            let reply = match self
+                .call_bus_method(write, "RequestName", &(&name, flags))
+                .await
+            {
+                Ok(reply) => reply.body().deserialize(),
+                // The bus refused, trying again won't help.
+                Err(e @ Error::MethodError(..)) => Err(e),
+                Err(e) => return Err(e),
+            };
+            names.push((name, reply));
+        }
+
+        Ok(Restored {
+            unique_name: Some(unique_name),
+            names,
+        })
+    }
+
+    // Call a method of the bus through the given socket, rather than the current one.
+    async fn call_bus_method<B>(
+        &self,
+        write: &mut Box<dyn WriteHalf>,
+        method_name: &'static str,
+        body: &B,
+    ) -> Result<Message>
+    where
+        B: serde::ser::Serialize + zvariant::DynamicType,
+    {
+        let msg = Message::method("/org/freedesktop/DBus", method_name)?
+            .destination("org.freedesktop.DBus")?
+            .interface("org.freedesktop.DBus")?
+            .build(body)?;
+        let msg = match intercept(&self.inner.interceptors, msg, true).await {
+            Interception::Continue(msg) => msg,
+            Interception::Drop => {
+                return Err(Error::Failure(format!(
+                    "`{method_name}` call dropped by an interceptor"
+                )))
+            }
+            Interception::Reply(reply) => {
+                return match reply.message_type() {
+                    Type::Error => Err(reply.into()),
+                    _ => Ok(reply),
+                }
+            }
+        };
+        let reply = self.expect_reply(&msg);
+        self.write_msg_to(write, &msg).await?;
+
+        reply.await
+    }
+
+    // Make the restored socket the current one.
+    async fn publish_socket(
+        &self,
+        write: Box<dyn WriteHalf>,
+        server_guid: Guid,
+        restored: Restored,
+    ) {
+        *self.inner.socket_write.lock().await = write;
+        *self.inner.server_guid.write().expect("poisoned lock") = server_guid;
+        *self.inner.unique_name.write().expect("poisoned lock") = restored.unique_name;
+
+        // This involves method calls, so it can't hold up the reading from the new socket.
+        if restored.names.is_empty() {
+            return;
+        }
+        let conn = self.clone();
+        let restore_names = async move {
+            for (name, reply) in restored.names {
+                conn.restore_name(name, reply).await;
+            }
+        };
+        self.executor()
+            .spawn(restore_names, "restore names")
+            .detach();
+    }
+
+    // Reconcile a registered name with the outcome of requesting it on the new socket.
+    //
+    // The names the connection no longer owns are forgotten and the application is told about it
+    // through a `NameLost` signal, as if it came from the bus.
+    async fn restore_name(&self, name: WellKnownName<'static>, reply: Result<RequestNameReply>) {
+        let mut names = self.inner.registered_names.lock().await;
+        let (flags, owner) = match names.get(&name) {
+            Some((flags, status)) => (*flags, matches!(status, NameStatus::Owner(_))),
+            // Released in the meantime.
+            None => return,
+        };
+        let queued = match reply {
+            // If the name was queued for, the `NameAcquired` signal from the bus takes care of it.
+            Ok(RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner) => return,
+            Ok(RequestNameReply::InQueue) if !owner => return,
+            Ok(RequestNameReply::InQueue) => {
+                warn!("Name `{name}` is owned by another peer after reconnecting, queued for it");
+
+                true
+            }
+            Ok(RequestNameReply::Exists) => {
+                warn!("Name `{name}` is owned by another peer after reconnecting");
+
+                false
+            }
+            Err(e) => {
+                warn!("Failed to request name `{name}` after reconnecting: {e}");
+
+                false
+            }
+        };
+        names.remove(&name);
+        drop(names);
+
+        if let Err(e) = self.dispatch_name_lost(&name).await {
+            warn!("Failed to notify the loss of name `{name}`: {e}");
+        }
+        // Keep track of the queued request, to find out when the name is acquired again.
+        if queued {
+            if let Err(e) = self.request_name_with_flags(name.clone(), flags).await {
+                warn!("Failed to queue for name `{name}` after reconnecting: {e}");
+            }
+        }
+    }
+
+    // Dispatch a `NameLost` signal for `name`, as if it came from the bus.
+    async fn dispatch_name_lost(&self, name: &WellKnownName<'_>) -> Result<()> {
+        let mut builder =
+            Message::signal("/org/freedesktop/DBus", "org.freedesktop.DBus", "NameLost")?
+                .sender("org.freedesktop.DBus")?;
+        let unique_name = self.unique_name();
+        if let Some(unique_name) = &unique_name {
+            builder = builder.destination(unique_name)?;
+        }
+        let signal = builder.build(&name.as_str())?;
+        let position = Sequence::new(self.inner.recv_seq.next());
+        let signal = signal.with_recv_position(position)?;
+        self.inner
+            .msg_senders
+            .lock()
+            .await
+            .dispatch(&signal, &self.inner.stats)
+            .await;
+
+        Ok(())
+    }
+
+    // Fail all the streams, we won't be receiving anything anymore.
+    async fn close_streams(&self, error: Error) {
+        close_senders(&self.inner.msg_senders, error).await;
+        self.set_state(ConnectionState::Closed);
+    }
+
+    // The method calls in flight won't get any replies.
+    async fn fail_pending_calls(&self, error: Error) {
+        let rule = OwnedMatchRule::from(MatchRule::builder().msg_type(Type::MethodReturn).build());
+        let senders = self.inner.msg_senders.lock().await;
+        if let Some(sender) = senders.shared.get(&Some(rule)) {
+            if let Err(e) = sender.broadcast(Err(error)).await {
+                debug!("Failed to fail the pending method calls: {e}");
+            }
+        }
+    }
+}
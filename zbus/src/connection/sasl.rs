@@ -0,0 +1,274 @@
+use async_trait::async_trait;
+use std::fmt;
+
+use crate::{Error, Result};
+
+/// A [SASL] authentication mechanism.
+///
+/// zbus supports the [`AuthMechanism`]s defined by the D-Bus specification out of the box. Other
+/// mechanisms can be implemented through this trait and registered through
+/// [`connection::Builder::sasl_mechanism`] or [`connection::Listener`]'s
+/// [`Builder::sasl_mechanism`].
+///
+/// A mechanism doesn't hold any state specific to a handshake. Instead, it creates a
+/// [`SaslClient`] or [`SaslServer`] for each handshake.
+///
+/// # Example
+///
+/// A mechanism authenticating clients through a pre-shared token:
+///
+/// ```no_run
+///# use std::error::Error;
+///# zbus::block_on(async {
+/// use zbus::connection::{Builder, SaslClient, SaslMechanism, SaslServer, SaslServerStep};
+///
+/// let _conn = Builder::address("vsock:cid=2,port=5000")?
+///     .p2p()
+///     .sasl_mechanism(Token(b"secret".to_vec()))
+///     .build()
+///     .await?;
+///
+/// struct Token(Vec<u8>);
+///
+/// impl SaslMechanism for Token {
+///     fn name(&self) -> &str {
+///         "X_TOKEN"
+///     }
+///
+///     fn client(&self) -> zbus::Result<Box<dyn SaslClient>> {
+///         Ok(Box::new(TokenClient(self.0.clone())))
+///     }
+///
+///     fn server(&self) -> zbus::Result<Box<dyn SaslServer>> {
+///         Ok(Box::new(TokenServer(self.0.clone())))
+///     }
+/// }
+///
+/// struct TokenClient(Vec<u8>);
+///
+/// #[async_trait::async_trait]
+/// impl SaslClient for TokenClient {
+///     async fn initial_response(&mut self) -> zbus::Result<Option<Vec<u8>>> {
+///         Ok(Some(self.0.clone()))
+///     }
+/// }
+///
+/// struct TokenServer(Vec<u8>);
+///
+/// #[async_trait::async_trait]
+/// impl SaslServer for TokenServer {
+///     async fn step(&mut self, response: Option<&[u8]>) -> zbus::Result<SaslServerStep> {
+///         match response {
+///             // Ask for the token.
+///             None => Ok(SaslServerStep::Challenge(vec![])),
+///             Some(token) if token == self.0 => Ok(SaslServerStep::Accept),
+///             Some(_) => Ok(SaslServerStep::Reject),
+///         }
+///     }
+/// }
+///# Ok::<(), Box<dyn Error + Send + Sync>>(())
+///# }).unwrap();
+/// ```
+///
+/// [SASL]: https://dbus.freedesktop.org/doc/dbus-specification.html#auth-protocol
+/// [`AuthMechanism`]: crate::AuthMechanism
+/// [`connection::Builder::sasl_mechanism`]: crate::connection::Builder::sasl_mechanism
+/// [`connection::Listener`]: crate::connection::Listener
+/// [`Builder::sasl_mechanism`]: crate::connection::listener::Builder::sasl_mechanism
+pub trait SaslMechanism: Send + Sync + 'static {
+    /// The name of the mechanism, as sent in the `AUTH` command.
+    ///
+    /// It must only contain upper-case ASCII letters, digits, `-` and `_`.
+    fn name(&self) -> &str;
+
+    /// Start authenticating to a server.
+    ///
+    /// The default implementation returns [`Error::Unsupported`], for mechanisms only meant to
+    /// authenticate clients. The mechanism is then skipped.
+    fn client(&self) -> Result<Box<dyn SaslClient>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Start authenticating a client.
+    ///
+    /// The default implementation returns [`Error::Unsupported`], for mechanisms only meant to
+    /// authenticate to servers. Clients attempting to use the mechanism are then rejected.
+    fn server(&self) -> Result<Box<dyn SaslServer>> {
+        Err(Error::Unsupported)
+    }
+}
+
+/// The client side of a [`SaslMechanism`], for a single handshake.
+#[async_trait]
+pub trait SaslClient: Send {
+    /// The initial response, sent along the `AUTH` command.
+    ///
+    /// If `None`, the server is expected to send a challenge first.
+    async fn initial_response(&mut self) -> Result<Option<Vec<u8>>>;
+
+    /// The response to a challenge (a `DATA` command) from the server.
+    ///
+    /// The default implementation returns an error, for mechanisms without challenges. The
+    /// handshake is then aborted.
+    async fn step(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        let _ = challenge;
+
+        Err(Error::Handshake("Unexpected mechanism DATA".into()))
+    }
+}
+
+/// The server side of a [`SaslMechanism`], for a single handshake.
+#[async_trait]
+pub trait SaslServer: Send {
+    /// Process a response from the client.
+    ///
+    /// This is first called with the initial response of the client, which is `None` if the
+    /// client didn't send any, then with the response to each [`SaslServerStep::Challenge`].
+    ///
+    /// Errors are logged and the client is rejected.
+    async fn step(&mut self, response: Option<&[u8]>) -> Result<SaslServerStep>;
+}
+
+/// The outcome of a [`SaslServer::step`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SaslServerStep {
+    /// Send this challenge to the client, and wait for its response.
+    Challenge(Vec<u8>),
+    /// The client is authenticated.
+    Accept,
+    /// The client failed to authenticate with this mechanism. It may try another one.
+    Reject,
+}
+
+impl fmt::Debug for dyn SaslMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SaslMechanism").field(&self.name()).finish()
+    }
+}
+
+impl fmt::Debug for dyn SaslClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SaslClient")
+    }
+}
+
+impl fmt::Debug for dyn SaslServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SaslServer")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use ntest::timeout;
+    use test_log::test;
+
+    use super::*;
+    use crate::connection::{Builder, Listener};
+
+    // A challenge-response mechanism, based on a pre-shared token.
+    struct Token(&'static [u8]);
+
+    impl SaslMechanism for Token {
+        fn name(&self) -> &str {
+            "X_ZBUS_TEST_TOKEN"
+        }
+
+        fn client(&self) -> Result<Box<dyn SaslClient>> {
+            Ok(Box::new(TokenClient(self.0)))
+        }
+
+        fn server(&self) -> Result<Box<dyn SaslServer>> {
+            Ok(Box::new(TokenServer {
+                token: self.0,
+                challenge: None,
+            }))
+        }
+    }
+
+    struct TokenClient(&'static [u8]);
+
+    #[async_trait]
+    impl SaslClient for TokenClient {
+        async fn initial_response(&mut self) -> Result<Option<Vec<u8>>> {
+            Ok(None)
+        }
+
+        async fn step(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+            Ok([challenge, self.0].concat())
+        }
+    }
+
+    struct TokenServer {
+        token: &'static [u8],
+        challenge: Option<Vec<u8>>,
+    }
+
+    #[async_trait]
+    impl SaslServer for TokenServer {
+        async fn step(&mut self, response: Option<&[u8]>) -> Result<SaslServerStep> {
+            match (&self.challenge, response) {
+                (None, None) => {
+                    let challenge = crate::connection::handshake::random_ascii(8).into_bytes();
+                    self.challenge = Some(challenge.clone());
+
+                    Ok(SaslServerStep::Challenge(challenge))
+                }
+                (Some(challenge), Some(response))
+                    if response == [&challenge[..], self.token].concat() =>
+                {
+                    Ok(SaslServerStep::Accept)
+                }
+                _ => Ok(SaslServerStep::Reject),
+            }
+        }
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn custom_mechanism() {
+        crate::utils::block_on(test_custom_mechanism()).unwrap();
+    }
+
+    async fn test_custom_mechanism() -> Result<()> {
+        let mut listener = Listener::builder("tcp:host=127.0.0.1,port=0")?
+            .auth_mechanisms(&[])
+            .sasl_mechanism(Token(b"secret"))
+            .build()
+            .await?;
+        let address = listener.address().clone();
+
+        // The custom mechanism is tried before the built-in ones.
+        let (client, server) = futures_util::try_join!(
+            async {
+                Builder::address(address.clone())?
+                    .p2p()
+                    .sasl_mechanism(Token(b"secret"))
+                    .build()
+                    .await
+            },
+            async { listener.next().await.unwrap() },
+        )?;
+        assert_eq!(client.server_guid(), server.server_guid());
+
+        // All mechanisms are rejected.
+        let (client, server) = futures_util::join!(
+            async {
+                Builder::address(address.clone())?
+                    .p2p()
+                    .sasl_mechanism(Token(b"wrong"))
+                    .build()
+                    .await
+            },
+            async { listener.next().await.unwrap() },
+        );
+        match client.unwrap_err() {
+            Error::Handshake(e) => assert_eq!(e, "Exhausted available AUTH mechanisms"),
+            e => panic!("unexpected error: {e}"),
+        }
+        assert!(server.is_err());
+
+        Ok(())
+    }
+}
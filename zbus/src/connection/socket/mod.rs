@@ -3,6 +3,10 @@ pub use split::{BoxedSplit, Split};
 
 mod tcp;
 mod unix;
+#[cfg(unix)]
+mod unixexec;
+#[cfg(unix)]
+pub use unixexec::UnixExecStream;
 mod vsock;
 
 #[cfg(not(feature = "tokio"))]
@@ -325,13 +325,32 @@ fn get_unix_peer_creds_blocking(fd: RawFd) -> io::Result<ConnectionCredentials>
         // 'static lifetime due to the Task.
         let fd = unsafe { BorrowedFd::borrow_raw(fd) };
 
-        getsockopt(&fd, PeerCredentials)
-            .map(|creds| {
-                ConnectionCredentials::default()
-                    .set_process_id(creds.pid() as _)
-                    .set_unix_user_id(creds.uid())
-            })
-            .map_err(|e| e.into())
+        let ucred = getsockopt(&fd, PeerCredentials)?;
+        let mut creds = ConnectionCredentials::default()
+            .set_process_id(ucred.pid() as _)
+            .set_unix_user_id(ucred.uid());
+
+        // These are best effort: the kernel might be too old or not have any LSM enabled.
+        if let Ok(mut gids) = getsockopt_vec::<u32>(fd.as_raw_fd(), peer_sockopt::SO_PEERGROUPS) {
+            gids.push(ucred.gid());
+            gids.sort_unstable();
+            gids.dedup();
+            for gid in gids {
+                creds = creds.add_unix_group_id(gid);
+            }
+        }
+        if let Ok(mut label) = getsockopt_vec::<u8>(fd.as_raw_fd(), peer_sockopt::SO_PEERSEC) {
+            // The label is expected to end with a single zero byte.
+            while label.last() == Some(&0) {
+                label.pop();
+            }
+            if !label.is_empty() {
+                label.push(0);
+                creds = creds.set_linux_security_label(label);
+            }
+        }
+
+        Ok(creds)
     }
 
     #[cfg(any(
@@ -350,6 +369,59 @@ fn get_unix_peer_creds_blocking(fd: RawFd) -> io::Result<ConnectionCredentials>
     }
 }
 
+// The socket options for the peer groups and security label, not exposed by `libc` for all targets.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+mod peer_sockopt {
+    use nix::libc::c_int;
+
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+    pub const SO_PEERSEC: c_int = 30;
+    #[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+    pub const SO_PEERSEC: c_int = 0x1e;
+    #[cfg(not(any(
+        target_arch = "mips",
+        target_arch = "mips64",
+        target_arch = "sparc",
+        target_arch = "sparc64"
+    )))]
+    pub const SO_PEERSEC: c_int = 31;
+
+    #[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+    pub const SO_PEERGROUPS: c_int = 0x3d;
+    #[cfg(not(any(target_arch = "sparc", target_arch = "sparc64")))]
+    pub const SO_PEERGROUPS: c_int = 59;
+}
+
+// Get a variable-length `SOL_SOCKET` option.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn getsockopt_vec<T: Copy + Default>(fd: RawFd, opt: nix::libc::c_int) -> io::Result<Vec<T>> {
+    use nix::libc::{getsockopt, socklen_t, ERANGE, SOL_SOCKET};
+    use std::mem::size_of;
+
+    let mut buf = vec![T::default(); 64];
+    loop {
+        let capacity = buf.len() * size_of::<T>();
+        let mut len = capacity as socklen_t;
+        // SAFETY: `buf` is valid for writes of `len` bytes.
+        let ret = unsafe { getsockopt(fd, SOL_SOCKET, opt, buf.as_mut_ptr().cast(), &mut len) };
+        if ret == 0 {
+            buf.truncate(len as usize / size_of::<T>());
+
+            return Ok(buf);
+        }
+
+        // On `ERANGE`, `len` is set to the size needed.
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(ERANGE) || len as usize <= capacity {
+            return Err(e);
+        }
+        buf.resize(
+            (len as usize + size_of::<T>() - 1) / size_of::<T>(),
+            T::default(),
+        );
+    }
+}
+
 // Send 0 byte as a separate SCM_CREDS message.
 #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
 async fn send_zero_byte(fd: &impl AsRawFd) -> io::Result<usize> {
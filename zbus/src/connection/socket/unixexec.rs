@@ -0,0 +1,169 @@
+#[cfg(not(feature = "tokio"))]
+use async_io::Async;
+use std::{
+    io,
+    os::{fd::OwnedFd as StdOwnedFd, unix::process::CommandExt},
+    process::{Child, Command, Stdio},
+    sync::Arc,
+};
+use tracing::debug;
+
+use super::{ReadHalf, RecvmsgResult, Socket, Split, WriteHalf};
+use crate::{address::UnixExecAddress, fdo::ConnectionCredentials};
+
+/// A socket connected to the standard input and output of a child process.
+///
+/// This is the socket of the `unixexec:` transport: the process is spawned with one end of a unix
+/// socket pair as its standard input and output, and D-Bus messages are exchanged over the other
+/// end. This is how bridges like `systemd-stdio-bridge` are used to reach a bus on another
+/// machine or in a container.
+///
+/// The socket owns the process: it's killed (if still running) once both halves of the socket are
+/// dropped.
+#[derive(Debug)]
+pub struct UnixExecStream {
+    #[cfg(not(feature = "tokio"))]
+    stream: Async<std::os::unix::net::UnixStream>,
+    #[cfg(feature = "tokio")]
+    stream: tokio::net::UnixStream,
+    child: ChildProcess,
+}
+
+impl UnixExecStream {
+    /// Spawn the process described by `address`.
+    pub fn spawn(address: &UnixExecAddress) -> io::Result<Self> {
+        let (stream, child_stream) = std::os::unix::net::UnixStream::pair()?;
+        let child_stdout = StdOwnedFd::from(child_stream.try_clone()?);
+        let child_stdin = StdOwnedFd::from(child_stream);
+
+        let mut command = Command::new(address.path());
+        if let Some(argv0) = address.argv0() {
+            command.arg0(argv0);
+        }
+        let child = command
+            .args(address.args())
+            .stdin(Stdio::from(child_stdin))
+            .stdout(Stdio::from(child_stdout))
+            .spawn()?;
+        // Dropping `command` closes our copies of the child's end.
+        drop(command);
+
+        stream.set_nonblocking(true)?;
+        #[cfg(not(feature = "tokio"))]
+        let stream = Async::new(stream)?;
+        #[cfg(feature = "tokio")]
+        let stream = tokio::net::UnixStream::from_std(stream)?;
+
+        Ok(Self {
+            stream,
+            child: ChildProcess(Some(child)),
+        })
+    }
+
+    /// The process ID of the child process.
+    pub fn child_id(&self) -> u32 {
+        // SAFETY: The process is only taken on drop.
+        self.child.0.as_ref().unwrap().id()
+    }
+}
+
+impl Socket for UnixExecStream {
+    type ReadHalf = Box<dyn ReadHalf>;
+    type WriteHalf = Box<dyn WriteHalf>;
+
+    fn split(self) -> Split<Self::ReadHalf, Self::WriteHalf> {
+        let child = Arc::new(self.child);
+        let split = Split::new_boxed(self.stream);
+
+        Split {
+            read: Box::new(ChildHalf {
+                half: split.read,
+                _child: child.clone(),
+            }),
+            write: Box::new(ChildHalf {
+                half: split.write,
+                _child: child,
+            }),
+        }
+    }
+}
+
+// Kills and reaps the process on drop.
+#[derive(Debug)]
+struct ChildProcess(Option<Child>);
+
+impl Drop for ChildProcess {
+    fn drop(&mut self) {
+        let Some(mut child) = self.0.take() else {
+            return;
+        };
+        if let Ok(Some(_)) = child.try_wait() {
+            return;
+        }
+        if let Err(e) = child.kill() {
+            debug!("Failed to kill the `unixexec` process: {e}");
+
+            return;
+        }
+
+        // The process may take a while to exit and we could be dropped from an async context, so
+        // it's reaped from a thread of its own.
+        let reap = move || {
+            if let Err(e) = child.wait() {
+                debug!("Failed to reap the `unixexec` process: {e}");
+            }
+        };
+        if let Err(e) = std::thread::Builder::new()
+            .name("zbus::unixexec reaper".into())
+            .spawn(reap)
+        {
+            debug!("Failed to reap the `unixexec` process: {e}");
+        }
+    }
+}
+
+// A socket half keeping the process alive.
+#[derive(Debug)]
+struct ChildHalf<H> {
+    half: H,
+    _child: Arc<ChildProcess>,
+}
+
+#[async_trait::async_trait]
+impl ReadHalf for ChildHalf<Box<dyn ReadHalf>> {
+    async fn recvmsg(&mut self, buf: &mut [u8]) -> RecvmsgResult {
+        self.half.recvmsg(buf).await
+    }
+
+    fn can_pass_unix_fd(&self) -> bool {
+        self.half.can_pass_unix_fd()
+    }
+
+    async fn peer_credentials(&mut self) -> io::Result<ConnectionCredentials> {
+        self.half.peer_credentials().await
+    }
+}
+
+#[async_trait::async_trait]
+impl WriteHalf for ChildHalf<Box<dyn WriteHalf>> {
+    async fn sendmsg(&mut self, buffer: &[u8], fds: &[std::os::fd::RawFd]) -> io::Result<usize> {
+        self.half.sendmsg(buffer, fds).await
+    }
+
+    #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+    async fn send_zero_byte(&mut self) -> io::Result<Option<usize>> {
+        self.half.send_zero_byte().await
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        self.half.close().await
+    }
+
+    fn can_pass_unix_fd(&self) -> bool {
+        self.half.can_pass_unix_fd()
+    }
+
+    async fn peer_credentials(&mut self) -> io::Result<ConnectionCredentials> {
+        self.half.peer_credentials().await
+    }
+}
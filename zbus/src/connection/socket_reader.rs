@@ -1,95 +1,264 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
+use async_broadcast::TrySendError;
 use event_listener::Event;
 use tracing::{debug, instrument, trace};
 use zvariant::{serialized, EncodingContext};
 
 use crate::{
     async_lock::Mutex,
-    connection::MsgBroadcaster,
+    connection::{
+        interceptor::{intercept, Interception, Interceptor},
+        Connection, Counters, MsgBroadcaster, WeakConnection,
+    },
     message::header::{PrimaryHeader, MAX_MESSAGE_SIZE, MIN_MESSAGE_SIZE},
-    padding_for_8_bytes, Executor, Message, OwnedMatchRule, Task,
+    padding_for_8_bytes, Message, OverflowPolicy, OwnedMatchRule,
 };
 
 use super::socket::ReadHalf;
 
+/// The positions of the messages received on a connection.
+///
+/// Shared by the socket readers of the connection, including the ones of the sockets replacing a
+/// lost one, and by the replies from the interceptors, so all the messages are ordered.
+#[derive(Debug, Default)]
+pub(crate) struct RecvSequence(AtomicU64);
+
+impl RecvSequence {
+    /// The position of the next message received.
+    pub fn next(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+}
+
+/// The queues received messages are dispatched to.
+#[derive(Debug, Default)]
+pub(crate) struct MsgSenders {
+    /// The queues shared by all the streams for the same match rule (or the lack of one).
+    ///
+    /// The socket reader waits for room in these.
+    pub shared: HashMap<Option<OwnedMatchRule>, MsgBroadcaster>,
+    /// The queues dedicated to a stream with a lossy [`OverflowPolicy`].
+    pub lossy: Vec<LossySender>,
+}
+
+impl MsgSenders {
+    pub fn is_empty(&self) -> bool {
+        self.shared.is_empty() && self.lossy.is_empty()
+    }
+
+    /// Put `msg` on the queues whose match rule it matches.
+    pub async fn dispatch(&mut self, msg: &Message, stats: &Counters) {
+        let mut queued = false;
+        for (rule, sender) in &self.shared {
+            if let Some(rule) = rule.as_ref() {
+                match rule.matches(msg) {
+                    Ok(true) => (),
+                    Ok(false) => continue,
+                    Err(e) => {
+                        debug!("Error matching message against rule: {:?}", e);
+
+                        continue;
+                    }
+                }
+            }
+
+            match sender.broadcast(Ok(msg.clone())).await {
+                Ok(_) => queued = true,
+                Err(e) => {
+                    // An error would be due to either of these:
+                    //
+                    // 1. the channel is closed.
+                    // 2. No active receivers.
+                    //
+                    // In either case, just log it.
+                    trace!(
+                        "Error broadcasting message to stream for `{:?}`: {:?}",
+                        rule,
+                        e
+                    );
+                }
+            }
+        }
+        self.lossy
+            .retain_mut(|sender| sender.send(msg, stats, &mut queued));
+        if !queued {
+            stats.message_dropped();
+        }
+        trace!("Broadcasted to all streams: {:?}", msg);
+    }
+}
+
+/// The queue of a stream, dropping messages when it's full.
+#[derive(Debug)]
+pub(crate) struct LossySender {
+    pub rule: OwnedMatchRule,
+    pub sender: MsgBroadcaster,
+    pub policy: OverflowPolicy,
+    /// The number of messages dropped that the stream has yet to report.
+    pub missed: Arc<AtomicUsize>,
+    /// The total number of messages dropped.
+    pub dropped: u64,
+}
+
+impl LossySender {
+    pub fn new(rule: OwnedMatchRule, mut sender: MsgBroadcaster, policy: OverflowPolicy) -> Self {
+        sender.set_overflow(policy == OverflowPolicy::DropOldest);
+
+        Self {
+            rule,
+            sender,
+            policy,
+            missed: Arc::new(AtomicUsize::new(0)),
+            dropped: 0,
+        }
+    }
+
+    // Returns `false` if the stream is gone.
+    fn send(&mut self, msg: &Message, stats: &Counters, queued: &mut bool) -> bool {
+        match self.rule.matches(msg) {
+            Ok(true) => (),
+            Ok(false) => return true,
+            Err(e) => {
+                debug!("Error matching message against rule: {:?}", e);
+
+                return true;
+            }
+        }
+
+        match self.sender.try_broadcast(Ok(msg.clone())) {
+            Ok(None) => *queued = true,
+            // The oldest message was dropped to make room.
+            Ok(Some(_)) => {
+                *queued = true;
+                self.dropped(stats);
+            }
+            Err(TrySendError::Full(_)) => self.dropped(stats),
+            Err(_) => {
+                trace!("Stream for `{:?}` is gone", self.rule);
+
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn dropped(&mut self, stats: &Counters) {
+        self.dropped += 1;
+        self.missed.fetch_add(1, Ordering::SeqCst);
+        stats.message_dropped();
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct SocketReader {
     socket: Box<dyn ReadHalf>,
-    senders: Arc<Mutex<HashMap<Option<OwnedMatchRule>, MsgBroadcaster>>>,
+    senders: Arc<Mutex<MsgSenders>>,
     already_received_bytes: Option<Vec<u8>>,
-    prev_seq: u64,
+    recv_seq: Arc<RecvSequence>,
     activity_event: Arc<Event>,
+    stats: Arc<Counters>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    conn: Option<WeakConnection>,
 }
 
 impl SocketReader {
     pub fn new(
         socket: Box<dyn ReadHalf>,
-        senders: Arc<Mutex<HashMap<Option<OwnedMatchRule>, MsgBroadcaster>>>,
+        senders: Arc<Mutex<MsgSenders>>,
         already_received_bytes: Vec<u8>,
+        recv_seq: Arc<RecvSequence>,
         activity_event: Arc<Event>,
+        stats: Arc<Counters>,
     ) -> Self {
         Self {
             socket,
             senders,
             already_received_bytes: Some(already_received_bytes),
-            prev_seq: 0,
+            recv_seq,
             activity_event,
+            stats,
+            interceptors: vec![],
+            conn: None,
         }
     }
 
-    pub fn spawn(self, executor: &Executor<'_>) -> Task<()> {
-        executor.spawn(self.receive_msg(), "socket reader")
+    /// Pass the received messages through the interceptors of `conn` before dispatching them.
+    pub fn with_interceptors(mut self, conn: &Connection) -> Self {
+        self.interceptors = conn.inner.interceptors.clone();
+        self.conn = Some(WeakConnection::from(conn));
+
+        self
     }
 
     // Keep receiving messages and put them on the queue.
     #[instrument(name = "socket reader", skip(self))]
-    async fn receive_msg(mut self) {
+    pub async fn receive_msg(self) {
+        let senders = self.senders.clone();
+        let error = self.receive_until_error().await;
+
+        close_senders(&senders, error).await;
+    }
+
+    /// Keep receiving messages and put them on the queue, until reading from the socket fails.
+    ///
+    /// Returns the error.
+    pub async fn receive_until_error(mut self) -> crate::Error {
         loop {
             trace!("Waiting for message on the socket..");
-            let msg = self.read_socket().await;
-            match &msg {
-                Ok(msg) => trace!("Message received on the socket: {:?}", msg),
-                Err(e) => trace!("Error reading from the socket: {:?}", e),
-            };
+            let msg = match self.read_socket().await {
+                Ok(msg) => msg,
+                Err(e) => {
+                    trace!("Error reading from the socket: {:?}", e);
 
-            let mut senders = self.senders.lock().await;
-            for (rule, sender) in &*senders {
-                if let Ok(msg) = &msg {
-                    if let Some(rule) = rule.as_ref() {
-                        match rule.matches(msg) {
-                            Ok(true) => (),
-                            Ok(false) => continue,
-                            Err(e) => {
-                                debug!("Error matching message against rule: {:?}", e);
-
-                                continue;
-                            }
-                        }
-                    }
+                    return e;
                 }
+            };
+            trace!("Message received on the socket: {:?}", msg);
+            self.stats.message_received(&msg);
 
-                if let Err(e) = sender.broadcast(msg.clone()).await {
-                    // An error would be due to either of these:
-                    //
-                    // 1. the channel is closed.
-                    // 2. No active receivers.
-                    //
-                    // In either case, just log it.
-                    trace!(
-                        "Error broadcasting message to stream for `{:?}`: {:?}",
-                        rule,
-                        e
-                    );
-                }
-            }
-            trace!("Broadcasted to all streams: {:?}", msg);
+            let msg = match self.intercept(msg).await {
+                Some(msg) => msg,
+                None => continue,
+            };
+            self.senders.lock().await.dispatch(&msg, &self.stats).await;
+        }
+    }
+
+    // Returns the message to dispatch, if any.
+    async fn intercept(&self, msg: Message) -> Option<Message> {
+        if self.interceptors.is_empty() {
+            return Some(msg);
+        }
+
+        let position = msg.recv_position();
+        match intercept(&self.interceptors, msg, false).await {
+            // Rewritten messages keep their place in the sequence.
+            Interception::Continue(msg) => match msg.with_recv_position(position) {
+                Ok(msg) => Some(msg),
+                Err(e) => {
+                    debug!("Invalid message from an interceptor: {:?}", e);
 
-            if msg.is_err() {
-                senders.clear();
-                trace!("Socket reading task stopped");
+                    None
+                }
+            },
+            Interception::Drop => None,
+            Interception::Reply(reply) => {
+                let conn = self.conn.as_ref().and_then(|conn| conn.upgrade());
+                if let Some(conn) = conn {
+                    if let Err(e) = conn.send(&reply).await {
+                        debug!("Failed to send the reply of an interceptor: {:?}", e);
+                    }
+                }
 
-                return;
+                None
             }
         }
     }
@@ -178,8 +347,7 @@ impl SocketReader {
         }
 
         // If we reach here, the message is complete; return it
-        let seq = self.prev_seq + 1;
-        self.prev_seq = seq;
+        let seq = self.recv_seq.next();
         let ctxt = EncodingContext::<byteorder::NativeEndian>::new_dbus(0);
         #[cfg(unix)]
         let bytes = serialized::Data::new_fds(bytes, ctxt, fds);
@@ -188,3 +356,31 @@ impl SocketReader {
         Message::from_raw_parts(bytes, seq)
     }
 }
+
+/// Broadcast `error` to all the streams and stop feeding them.
+pub(crate) async fn close_senders(senders: &Mutex<MsgSenders>, error: crate::Error) {
+    let mut senders = senders.lock().await;
+    for (rule, sender) in &senders.shared {
+        if let Err(e) = sender.broadcast(Err(error.clone())).await {
+            trace!(
+                "Error broadcasting error to stream for `{:?}`: {:?}",
+                rule,
+                e
+            );
+        }
+    }
+    for lossy in &mut senders.lossy {
+        // Make sure the error gets through, even if the queue is full.
+        lossy.sender.set_overflow(true);
+        if let Err(e) = lossy.sender.try_broadcast(Err(error.clone())) {
+            trace!(
+                "Error broadcasting error to stream for `{:?}`: {:?}",
+                lossy.rule,
+                e
+            );
+        }
+    }
+    senders.shared.clear();
+    senders.lossy.clear();
+    trace!("Socket reading task stopped");
+}
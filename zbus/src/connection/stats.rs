@@ -0,0 +1,261 @@
+use static_assertions::assert_impl_all;
+use std::sync::{Arc, Mutex};
+
+use crate::{message::Type, Message, OverflowPolicy, OwnedMatchRule};
+
+/// A snapshot of the statistics of a [`Connection`].
+///
+/// Use [`Connection::stats`] to get one.
+///
+/// [`Connection`]: crate::Connection
+/// [`Connection::stats`]: crate::Connection::stats
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub(super) sent: MessageCounts,
+    pub(super) received: MessageCounts,
+    pub(super) dropped_messages: u64,
+    pub(super) pending_method_calls: usize,
+    pub(super) match_rules: usize,
+    pub(super) message_streams: usize,
+    pub(super) queues: Vec<QueueStats>,
+}
+
+assert_impl_all!(ConnectionStats: Send, Sync, Unpin);
+
+impl ConnectionStats {
+    /// The messages sent over the connection.
+    pub fn sent(&self) -> &MessageCounts {
+        &self.sent
+    }
+
+    /// The messages received over the connection.
+    pub fn received(&self) -> &MessageCounts {
+        &self.received
+    }
+
+    /// The number of received messages that were dropped.
+    ///
+    /// These are the messages nobody was listening for, e.g signals that matched no active
+    /// [`MessageStream`], as well as the ones dropped from the full queue of a stream with a lossy
+    /// [`OverflowPolicy`].
+    ///
+    /// [`MessageStream`]: crate::MessageStream
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped_messages
+    }
+
+    /// The number of method calls still waiting for their reply.
+    pub fn pending_method_calls(&self) -> usize {
+        self.pending_method_calls
+    }
+
+    /// The number of match rules the connection is subscribed to.
+    pub fn match_rules(&self) -> usize {
+        self.match_rules
+    }
+
+    /// The number of live [`MessageStream`]s.
+    ///
+    /// This includes the streams used internally, e.g by the [`ObjectServer`] and for pending
+    /// method calls.
+    ///
+    /// [`MessageStream`]: crate::MessageStream
+    /// [`ObjectServer`]: crate::ObjectServer
+    pub fn message_streams(&self) -> usize {
+        self.message_streams
+    }
+
+    /// The message queues of the connection.
+    ///
+    /// There is one queue for each match rule, and one unfiltered queue, fed to the streams
+    /// created from the connection itself. Streams with a lossy [`OverflowPolicy`] have a queue of
+    /// their own.
+    pub fn queues(&self) -> &[QueueStats] {
+        &self.queues
+    }
+}
+
+/// The number of messages of each type, and of bytes, sent or received over a connection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MessageCounts {
+    method_calls: u64,
+    method_returns: u64,
+    errors: u64,
+    signals: u64,
+    bytes: u64,
+}
+
+assert_impl_all!(MessageCounts: Send, Sync, Unpin);
+
+impl MessageCounts {
+    /// The total number of messages.
+    pub fn messages(&self) -> u64 {
+        self.method_calls + self.method_returns + self.errors + self.signals
+    }
+
+    /// The number of messages of the given type.
+    pub fn messages_of_type(&self, msg_type: Type) -> u64 {
+        match msg_type {
+            Type::MethodCall => self.method_calls,
+            Type::MethodReturn => self.method_returns,
+            Type::Error => self.errors,
+            Type::Signal => self.signals,
+        }
+    }
+
+    /// The total size of the messages, in bytes.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    fn add(&mut self, msg: &Message) {
+        let count = match msg.message_type() {
+            Type::MethodCall => &mut self.method_calls,
+            Type::MethodReturn => &mut self.method_returns,
+            Type::Error => &mut self.errors,
+            Type::Signal => &mut self.signals,
+        };
+        *count += 1;
+        self.bytes += msg.data().len() as u64;
+    }
+}
+
+/// The state of a message queue of a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueStats {
+    pub(super) match_rule: Option<OwnedMatchRule>,
+    pub(super) overflow_policy: OverflowPolicy,
+    pub(super) len: usize,
+    pub(super) max_queued: usize,
+    pub(super) streams: usize,
+    pub(super) dropped_messages: u64,
+}
+
+assert_impl_all!(QueueStats: Send, Sync, Unpin);
+
+impl QueueStats {
+    /// The match rule messages are filtered with, if any.
+    pub fn match_rule(&self) -> Option<&OwnedMatchRule> {
+        self.match_rule.as_ref()
+    }
+
+    /// What happens when the queue is full.
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+
+    /// The number of messages in the queue, not yet read by all its streams.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// If the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The capacity of the queue.
+    pub fn max_queued(&self) -> usize {
+        self.max_queued
+    }
+
+    /// The number of active streams reading from the queue.
+    pub fn streams(&self) -> usize {
+        self.streams
+    }
+
+    /// The number of messages dropped from the queue, as it was full.
+    ///
+    /// This is always zero for queues with the [`OverflowPolicy::Block`] policy.
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped_messages
+    }
+}
+
+/// The counters of a connection, updated as messages go through.
+#[derive(Debug, Default)]
+pub(crate) struct Counters(Mutex<CountersInner>);
+
+#[derive(Debug, Default)]
+struct CountersInner {
+    sent: MessageCounts,
+    received: MessageCounts,
+    dropped_messages: u64,
+    pending_method_calls: usize,
+    message_streams: usize,
+}
+
+impl CountersInner {
+    fn gauge(&mut self, gauge: Gauge) -> &mut usize {
+        match gauge {
+            Gauge::PendingMethodCalls => &mut self.pending_method_calls,
+            Gauge::MessageStreams => &mut self.message_streams,
+        }
+    }
+}
+
+impl Counters {
+    pub(crate) fn message_sent(&self, msg: &Message) {
+        self.lock().sent.add(msg);
+    }
+
+    pub(crate) fn message_received(&self, msg: &Message) {
+        self.lock().received.add(msg);
+    }
+
+    pub(crate) fn message_dropped(&self) {
+        self.lock().dropped_messages += 1;
+    }
+
+    pub(super) fn snapshot(&self) -> ConnectionStats {
+        let inner = self.lock();
+
+        ConnectionStats {
+            sent: inner.sent,
+            received: inner.received,
+            dropped_messages: inner.dropped_messages,
+            pending_method_calls: inner.pending_method_calls,
+            match_rules: 0,
+            message_streams: inner.message_streams,
+            queues: vec![],
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CountersInner> {
+        self.0.lock().expect("poisoned lock")
+    }
+}
+
+/// What a [`Tracked`] instance counts.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Gauge {
+    PendingMethodCalls,
+    MessageStreams,
+}
+
+/// Counts towards a [`Gauge`] for as long as it's alive.
+#[derive(Debug)]
+pub(crate) struct Tracked {
+    counters: Arc<Counters>,
+    gauge: Gauge,
+}
+
+impl Tracked {
+    pub(crate) fn new(counters: Arc<Counters>, gauge: Gauge) -> Self {
+        *counters.lock().gauge(gauge) += 1;
+
+        Self { counters, gauge }
+    }
+}
+
+impl Clone for Tracked {
+    fn clone(&self) -> Self {
+        Self::new(self.counters.clone(), self.gauge)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        *self.counters.lock().gauge(self.gauge) -= 1;
+    }
+}
@@ -59,6 +59,14 @@ pub enum Error {
     MissingParameter(&'static str),
     /// Serial number in the message header is 0 (which is invalid).
     InvalidSerial,
+    /// No reply was received for a method call within the timeout.
+    Timeout,
+    /// A [`MessageStream`] with a lossy [`OverflowPolicy`] missed this many messages, as its queue
+    /// was full.
+    ///
+    /// [`MessageStream`]: crate::MessageStream
+    /// [`OverflowPolicy`]: crate::OverflowPolicy
+    MissedMessages(u64),
 }
 
 assert_impl_all!(Error: Send, Sync, Unpin);
@@ -76,6 +84,8 @@ impl PartialEq for Error {
             (Self::MissingField, Self::MissingField) => true,
             (Self::InvalidGUID, Self::InvalidGUID) => true,
             (Self::InvalidSerial, Self::InvalidSerial) => true,
+            (Self::Timeout, Self::Timeout) => true,
+            (Self::MissedMessages(s), Self::MissedMessages(o)) => s == o,
             (Self::Unsupported, Self::Unsupported) => true,
             (Self::FDO(s), Self::FDO(o)) => s == o,
             (Self::InvalidField, Self::InvalidField) => true,
@@ -113,6 +123,8 @@ impl error::Error for Error {
             Error::Failure(_) => None,
             Error::MissingParameter(_) => None,
             Error::InvalidSerial => None,
+            Error::Timeout => None,
+            Error::MissedMessages(_) => None,
         }
     }
 }
@@ -147,6 +159,8 @@ impl fmt::Display for Error {
                 write!(f, "Parameter `{}` was not specified but it is required", p)
             }
             Error::InvalidSerial => write!(f, "Serial number in the message header is 0"),
+            Error::Timeout => write!(f, "Timed out waiting for a method reply"),
+            Error::MissedMessages(n) => write!(f, "Missed {n} messages due to a full queue"),
         }
     }
 }
@@ -176,6 +190,8 @@ impl Clone for Error {
             Error::Failure(e) => Error::Failure(e.clone()),
             Error::MissingParameter(p) => Error::MissingParameter(p),
             Error::InvalidSerial => Error::InvalidSerial,
+            Error::Timeout => Error::Timeout,
+            Error::MissedMessages(n) => Error::MissedMessages(*n),
         }
     }
 }
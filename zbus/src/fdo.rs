@@ -17,8 +17,10 @@ use zvariant::{
 };
 
 use crate::{
-    dbus_interface, dbus_proxy, message::Header, object_server::SignalContext, DBusError, Guid,
-    ObjectServer,
+    dbus_interface, dbus_proxy,
+    message::{Header, Type as MessageType},
+    object_server::SignalContext,
+    Connection, DBusError, Guid, ObjectServer,
 };
 
 #[rustfmt::skip]
@@ -57,7 +59,8 @@ impl Introspectable {
         let path = header.path().ok_or(crate::Error::MissingField)?;
         let root = server.root().read().await;
         let node = root
-            .get_child(path)
+            .resolve(path)
+            .await
             .ok_or_else(|| Error::UnknownObject(format!("Unknown object '{path}'")))?;
 
         Ok(node.introspect().await)
@@ -129,7 +132,8 @@ impl Properties {
         let path = header.path().ok_or(crate::Error::MissingField)?;
         let root = server.root().read().await;
         let iface = root
-            .get_child(path)
+            .resolve(path)
+            .await
             .and_then(|node| node.interface_lock(interface_name.as_ref()))
             .ok_or_else(|| {
                 Error::UnknownInterface(format!("Unknown interface '{interface_name}'"))
@@ -155,7 +159,8 @@ impl Properties {
         let path = header.path().ok_or(crate::Error::MissingField)?;
         let root = server.root().read().await;
         let iface = root
-            .get_child(path)
+            .resolve(path)
+            .await
             .and_then(|node| node.interface_lock(interface_name.as_ref()))
             .ok_or_else(|| {
                 Error::UnknownInterface(format!("Unknown interface '{interface_name}'"))
@@ -193,7 +198,8 @@ impl Properties {
         let path = header.path().ok_or(crate::Error::MissingField)?;
         let root = server.root().read().await;
         let iface = root
-            .get_child(path)
+            .resolve(path)
+            .await
             .and_then(|node| node.interface_lock(interface_name.as_ref()))
             .ok_or_else(|| {
                 Error::UnknownInterface(format!("Unknown interface '{interface_name}'"))
@@ -295,7 +301,8 @@ impl ObjectManager {
         let path = header.path().ok_or(crate::Error::MissingField)?;
         let root = server.root().read().await;
         let node = root
-            .get_child(path)
+            .resolve(path)
+            .await
             .ok_or_else(|| Error::UnknownObject(format!("Unknown object '{path}'")))?;
 
         Ok(node.get_managed_objects().await)
@@ -358,7 +365,7 @@ pub(crate) struct Peer;
 impl Peer {
     fn ping(&self) {}
 
-    fn get_machine_id(&self) -> Result<String> {
+    pub(crate) fn get_machine_id(&self) -> Result<String> {
         let mut id = match std::fs::read_to_string("/var/lib/dbus/machine-id") {
             Ok(id) => id,
             Err(e) => {
@@ -437,11 +444,25 @@ macro_rules! gen_stats_proxy {
         )]
         trait Stats {
             /// GetStats (undocumented)
+            ///
+            /// The return type doesn't match the `a{sv}` signature of the method. Use `stats`
+            /// instead.
             fn get_stats(&self) -> Result<Vec<HashMap<String, OwnedValue>>>;
 
             /// GetConnectionStats (undocumented)
+            ///
+            /// The return type doesn't match the `a{sv}` signature of the method. Use
+            /// `connection_stats` instead.
             fn get_connection_stats(&self, name: BusName<'_>) -> Result<Vec<HashMap<String, OwnedValue>>>;
 
+            /// The statistics of the bus, or of the peer serving `fdo::Stats`.
+            #[dbus_proxy(name = "GetStats")]
+            fn stats(&self) -> Result<HashMap<String, OwnedValue>>;
+
+            /// The statistics of the connection with the given name.
+            #[dbus_proxy(name = "GetConnectionStats")]
+            fn connection_stats(&self, name: BusName<'_>) -> Result<HashMap<String, OwnedValue>>;
+
             /// GetAllMatchRules (undocumented)
             fn get_all_match_rules(&self) -> 
                 Result<
@@ -459,6 +480,66 @@ macro_rules! gen_stats_proxy {
 gen_stats_proxy!(true, false);
 assert_impl_all!(StatsProxy<'_>: Send, Sync, Unpin);
 
+/// Server-side implementation for the `org.freedesktop.DBus.Debug.Stats` interface.
+///
+/// Exposes the [`Connection::stats`] of the connection it's served on, so that the existing
+/// tooling can read them. Unlike the other standard interfaces, it's not implemented
+/// automatically. Register it to the [`ObjectServer`] at the path of your choice:
+///
+/// ```no_run
+///# zbus::block_on(async {
+/// use zbus::{fdo, Connection};
+///
+/// let conn = Connection::session().await?;
+/// conn.object_server().at("/org/example/Stats", fdo::Stats).await?;
+///# Ok::<(), zbus::Error>(())
+///# }).unwrap();
+/// ```
+///
+/// Only `GetStats` is supported. The keys of the returned dictionary follow the naming used by the
+/// reference bus implementation for connection statistics, e.g `IncomingMessages` and
+/// `OutgoingBytes`.
+///
+/// [`Connection::stats`]: crate::Connection::stats
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stats;
+
+assert_impl_all!(Stats: Send, Sync, Unpin);
+
+#[dbus_interface(name = "org.freedesktop.DBus.Debug.Stats")]
+impl Stats {
+    async fn get_stats(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+    ) -> HashMap<String, OwnedValue> {
+        let stats = conn.stats().await;
+        let mut dict = HashMap::new();
+        for (direction, counts) in [("Incoming", stats.received()), ("Outgoing", stats.sent())] {
+            dict.insert(format!("{direction}Messages"), counts.messages().into());
+            dict.insert(format!("{direction}Bytes"), counts.bytes().into());
+            for (msg_type, name) in [
+                (MessageType::MethodCall, "MethodCalls"),
+                (MessageType::MethodReturn, "MethodReturns"),
+                (MessageType::Error, "Errors"),
+                (MessageType::Signal, "Signals"),
+            ] {
+                let count = counts.messages_of_type(msg_type);
+                dict.insert(format!("{direction}{name}"), count.into());
+            }
+        }
+        dict.insert("DroppedMessages".into(), stats.dropped_messages().into());
+        for (key, value) in [
+            ("MatchRules", stats.match_rules()),
+            ("MessageStreams", stats.message_streams()),
+            ("PendingMethodCalls", stats.pending_method_calls()),
+        ] {
+            dict.insert(key.into(), (value as u32).into());
+        }
+
+        dict
+    }
+}
+
 /// The flags used by the bus [`request_name`] method.
 ///
 /// [`request_name`]: struct.DBusProxy.html#method.request_name
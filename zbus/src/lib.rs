@@ -95,6 +95,8 @@ pub use connection::{handshake::AuthMechanism, Connection};
 #[doc(hidden)]
 pub use connection::Builder as ConnectionBuilder;
 
+pub mod broker;
+
 mod message_stream;
 pub use message_stream::*;
 mod abstractions;
@@ -160,6 +162,20 @@ pub use object_server::ResponseDispatchNotifier;
 #[doc(hidden)]
 pub use object_server::SignalContext;
 
+pub mod object_manager_client;
+pub use object_manager_client::ObjectManagerClient;
+
+pub mod name_watcher;
+pub use name_watcher::NameWatcher;
+
+pub mod monitor;
+pub use monitor::Monitor;
+
+pub mod pcap;
+
+#[cfg(unix)]
+pub mod testing;
+
 mod utils;
 pub use utils::*;
 
@@ -888,6 +904,704 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn emits_changed_signal() {
+        block_on(test_emits_changed_signal()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_emits_changed_signal() -> Result<()> {
+        use futures_util::StreamExt;
+        #[cfg(not(feature = "tokio"))]
+        use std::os::unix::net::UnixStream;
+        #[cfg(feature = "tokio")]
+        use tokio::net::UnixStream;
+        use zbus::{
+            fdo::{PropertiesChanged, PropertiesProxy},
+            names::InterfaceName,
+        };
+        use zvariant::Value;
+
+        #[derive(Default)]
+        struct EmitsChangedSignal {
+            constant: u32,
+            invalidates: u32,
+            silent: u32,
+        }
+
+        #[crate::dbus_interface(name = "org.freedesktop.zbus.EmitsChangedSignal")]
+        impl EmitsChangedSignal {
+            #[dbus_interface(property(emits_changed_signal = "const"))]
+            fn constant(&self) -> u32 {
+                self.constant
+            }
+
+            #[dbus_interface(property)]
+            fn set_constant(&mut self, value: u32) {
+                self.constant = value;
+            }
+
+            // The attribute is on the setter, which comes first.
+            #[dbus_interface(property(emits_changed_signal = "invalidates"))]
+            fn set_invalidates(&mut self, value: u32) {
+                self.invalidates = value;
+            }
+
+            #[dbus_interface(property)]
+            fn invalidates(&self) -> u32 {
+                self.invalidates
+            }
+
+            #[dbus_interface(property(emits_changed_signal = "false"))]
+            fn silent(&self) -> u32 {
+                self.silent
+            }
+
+            #[dbus_interface(property)]
+            fn set_silent(&mut self, value: u32) {
+                self.silent = value;
+            }
+        }
+
+        let path = "/org/freedesktop/zbus/EmitsChangedSignal";
+        let iface_name =
+            InterfaceName::from_static_str_unchecked("org.freedesktop.zbus.EmitsChangedSignal");
+        let guid = crate::Guid::generate();
+        let (p0, p1) = UnixStream::pair().unwrap();
+        let (service, client) = futures_util::try_join!(
+            crate::connection::Builder::unix_stream(p0)
+                .server(&guid)
+                .p2p()
+                .serve_at(path, EmitsChangedSignal::default())?
+                .build(),
+            crate::connection::Builder::unix_stream(p1).p2p().build(),
+        )?;
+        let mut stream = crate::MessageStream::from(&client);
+        let props = PropertiesProxy::builder(&client)
+            .destination("org.freedesktop.zbus.EmitsChangedSignal")?
+            .path(path)?
+            .build()
+            .await?;
+
+        // Only the last one of these should result in a signal and since the replies come after
+        // the signals, we'd have received the others first.
+        props
+            .set(iface_name.clone(), "Constant", &Value::from(1u32))
+            .await?;
+        props
+            .set(iface_name.clone(), "Silent", &Value::from(1u32))
+            .await?;
+        props
+            .set(iface_name.clone(), "Invalidates", &Value::from(1u32))
+            .await?;
+        let iface_ref = service
+            .object_server()
+            .interface::<_, EmitsChangedSignal>(path)
+            .await?;
+        iface_ref
+            .get()
+            .await
+            .invalidates_changed(iface_ref.signal_context())
+            .await?;
+        iface_ref
+            .get()
+            .await
+            .silent_changed(iface_ref.signal_context())
+            .await?;
+
+        let mut signals = stream.by_ref().filter_map(|msg| {
+            futures_util::future::ready(
+                msg.ok()
+                    .filter(|m| m.message_type() == zbus::message::Type::Signal)
+                    .and_then(PropertiesChanged::from_message),
+            )
+        });
+        for _ in 0..2 {
+            let signal = signals.next().await.unwrap();
+            let args = signal.args()?;
+            assert!(args.changed_properties().is_empty());
+            assert_eq!(args.invalidated_properties(), &["Invalidates"]);
+        }
+        let signal = signals.next().await.unwrap();
+        let args = signal.args()?;
+        assert_eq!(args.changed_properties().len(), 1);
+        assert_eq!(args.changed_properties()["Silent"], Value::from(1u32));
+
+        let xml = crate::fdo::IntrospectableProxy::builder(&client)
+            .destination("org.freedesktop.zbus.EmitsChangedSignal")?
+            .path(path)?
+            .build()
+            .await?
+            .introspect()
+            .await?;
+        let node = zbus_xml::Node::from_reader(xml.as_bytes()).unwrap();
+        let iface = node
+            .interfaces()
+            .iter()
+            .find(|i| i.name() == iface_name)
+            .unwrap();
+        for (property, expected) in [
+            ("Constant", "const"),
+            ("Invalidates", "invalidates"),
+            ("Silent", "false"),
+        ] {
+            let property = iface
+                .properties()
+                .iter()
+                .find(|p| p.name() == property)
+                .unwrap();
+            let annotation = property
+                .annotations()
+                .iter()
+                .find(|a| a.name() == "org.freedesktop.DBus.Property.EmitsChangedSignal")
+                .map(|a| a.value());
+            assert_eq!(annotation, Some(expected));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn subtree_handler() {
+        block_on(test_subtree_handler()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_subtree_handler() -> Result<()> {
+        #[cfg(not(feature = "tokio"))]
+        use std::os::unix::net::UnixStream;
+        #[cfg(feature = "tokio")]
+        use tokio::net::UnixStream;
+        use zbus::{
+            dbus_interface,
+            fdo::{IntrospectableProxy, ObjectManager, ObjectManagerProxy, PropertiesProxy},
+            names::InterfaceName,
+            object_server::{SubtreeHandler, SubtreeObject},
+        };
+        use zvariant::{ObjectPath, Value};
+
+        type Rows = Arc<Mutex<HashMap<u32, String>>>;
+
+        struct Row {
+            id: u32,
+            rows: Rows,
+        }
+
+        #[dbus_interface(name = "org.freedesktop.zbus.Row")]
+        impl Row {
+            #[dbus_interface(property)]
+            fn value(&self) -> String {
+                self.rows.lock().unwrap()[&self.id].clone()
+            }
+
+            #[dbus_interface(property)]
+            fn set_value(&mut self, value: String) {
+                self.rows.lock().unwrap().insert(self.id, value);
+            }
+
+            fn delete(&self) {
+                self.rows.lock().unwrap().remove(&self.id);
+            }
+        }
+
+        struct Table(Rows);
+
+        #[async_trait::async_trait]
+        impl SubtreeHandler for Table {
+            async fn object(&self, path: &ObjectPath<'_>) -> Option<SubtreeObject> {
+                let id = path
+                    .strip_prefix("/org/freedesktop/zbus/rows/")?
+                    .parse()
+                    .ok()?;
+                if !self.0.lock().unwrap().contains_key(&id) {
+                    return None;
+                }
+
+                Some(SubtreeObject::new().with_interface(Row {
+                    id,
+                    rows: self.0.clone(),
+                }))
+            }
+
+            async fn children(&self, path: &ObjectPath<'_>) -> Vec<String> {
+                if path.as_str() != "/org/freedesktop/zbus/rows" {
+                    return vec![];
+                }
+
+                self.0
+                    .lock()
+                    .unwrap()
+                    .keys()
+                    .map(|id| id.to_string())
+                    .collect()
+            }
+        }
+
+        let rows = Rows::default();
+        rows.lock()
+            .unwrap()
+            .extend([(1, "one".to_string()), (2, "two".to_string())]);
+        let guid = crate::Guid::generate();
+        let (p0, p1) = UnixStream::pair().unwrap();
+        let (service, client) = futures_util::try_join!(
+            crate::connection::Builder::unix_stream(p0)
+                .server(&guid)
+                .p2p()
+                .serve_at("/org/freedesktop/zbus", ObjectManager)?
+                .build(),
+            crate::connection::Builder::unix_stream(p1).p2p().build(),
+        )?;
+        assert!(
+            service
+                .object_server()
+                .at_subtree("/org/freedesktop/zbus/rows", Table(rows.clone()))
+                .await?
+        );
+        assert!(
+            !service
+                .object_server()
+                .at_subtree("/org/freedesktop/zbus/rows", Table(rows.clone()))
+                .await?
+        );
+        let iface_name = InterfaceName::from_static_str_unchecked("org.freedesktop.zbus.Row");
+
+        // Properties and method calls on the resolved objects.
+        let props = PropertiesProxy::builder(&client)
+            .destination("org.freedesktop.zbus.Subtree")?
+            .path("/org/freedesktop/zbus/rows/1")?
+            .build()
+            .await?;
+        let value = props.get(iface_name.clone(), "Value").await?;
+        assert_eq!(value, OwnedValue::from(zvariant::Str::from("one")));
+        props
+            .set(iface_name.clone(), "Value", &Value::from("uno"))
+            .await?;
+        assert_eq!(rows.lock().unwrap()[&1], "uno");
+        client
+            .call_method(
+                Some("org.freedesktop.zbus.Subtree"),
+                "/org/freedesktop/zbus/rows/2",
+                Some("org.freedesktop.zbus.Row"),
+                "Delete",
+                &(),
+            )
+            .await?;
+        assert!(!rows.lock().unwrap().contains_key(&2));
+        let err = client
+            .call_method(
+                Some("org.freedesktop.zbus.Subtree"),
+                "/org/freedesktop/zbus/rows/2",
+                Some("org.freedesktop.zbus.Row"),
+                "Delete",
+                &(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            crate::fdo::Error::from(err),
+            crate::fdo::Error::UnknownObject(_)
+        ));
+
+        // Enumeration.
+        rows.lock().unwrap().insert(3, "three".to_string());
+        let xml = IntrospectableProxy::builder(&client)
+            .destination("org.freedesktop.zbus.Subtree")?
+            .path("/org/freedesktop/zbus/rows")?
+            .build()
+            .await?
+            .introspect()
+            .await?;
+        let node = zbus_xml::Node::from_reader(xml.as_bytes()).unwrap();
+        let mut children: Vec<_> = node.nodes().iter().filter_map(|n| n.name()).collect();
+        children.sort();
+        assert_eq!(children, ["1", "3"]);
+        let objects = ObjectManagerProxy::builder(&client)
+            .destination("org.freedesktop.zbus.Subtree")?
+            .path("/org/freedesktop/zbus")?
+            .build()
+            .await?
+            .get_managed_objects()
+            .await?;
+        let row = &objects[&ObjectPath::try_from("/org/freedesktop/zbus/rows/3")?.into()];
+        assert_eq!(
+            row[iface_name.as_str()]["Value"],
+            OwnedValue::from(zvariant::Str::from("three"))
+        );
+        assert!(
+            !objects.contains_key(&ObjectPath::try_from("/org/freedesktop/zbus/rows/2")?.into())
+        );
+
+        assert!(
+            service
+                .object_server()
+                .remove_subtree("/org/freedesktop/zbus/rows")
+                .await?
+        );
+        assert!(props.get(iface_name, "Value").await.is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn authorizer() {
+        block_on(test_authorizer()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_authorizer() -> Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use zbus::{
+            dbus_interface, fdo,
+            object_server::{Authorization, AuthorizationRequest, Authorizer},
+        };
+
+        struct Vault;
+
+        #[dbus_interface(name = "org.freedesktop.zbus.Vault")]
+        impl Vault {
+            #[dbus_interface(unprivileged)]
+            fn open(&self) -> u32 {
+                1
+            }
+
+            fn secret(&self) -> u32 {
+                2
+            }
+
+            fn checked_later(&self) -> u32 {
+                3
+            }
+
+            fn forbidden(&self) -> u32 {
+                4
+            }
+        }
+
+        #[derive(Clone)]
+        struct Policy {
+            uid: u32,
+            checks: Arc<AtomicUsize>,
+        }
+
+        impl Authorizer for Policy {
+            fn authorize<'a>(&'a self, request: &'a AuthorizationRequest<'a>) -> Authorization<'a> {
+                self.checks.fetch_add(1, Ordering::SeqCst);
+                if request.credentials().unix_user_id() != Some(self.uid) {
+                    return Authorization::Deny;
+                }
+
+                match request.member().as_str() {
+                    "Forbidden" => Authorization::Deny,
+                    "CheckedLater" => Authorization::Async(Box::pin(async move {
+                        request.interface() == "org.freedesktop.zbus.Vault"
+                    })),
+                    _ => Authorization::Allow,
+                }
+            }
+        }
+
+        let policy = Policy {
+            uid: nix::unistd::Uid::current().as_raw(),
+            checks: Arc::new(AtomicUsize::new(0)),
+        };
+        let service = crate::connection::Builder::session()?
+            .serve_at("/org/freedesktop/zbus/Vault", Vault)?
+            .authorizer(policy.clone())
+            .build()
+            .await?;
+        let client = crate::connection::Builder::session()?.build().await?;
+        let call = |method| {
+            client.call_method(
+                service.unique_name(),
+                "/org/freedesktop/zbus/Vault",
+                Some("org.freedesktop.zbus.Vault"),
+                method,
+                &(),
+            )
+        };
+
+        let reply = call("Open").await?;
+        assert_eq!(reply.body().deserialize::<u32>()?, 1);
+        assert_eq!(policy.checks.load(Ordering::SeqCst), 0);
+        let reply = call("Secret").await?;
+        assert_eq!(reply.body().deserialize::<u32>()?, 2);
+        let reply = call("CheckedLater").await?;
+        assert_eq!(reply.body().deserialize::<u32>()?, 3);
+        let err = call("Forbidden").await.unwrap_err();
+        assert!(matches!(fdo::Error::from(err), fdo::Error::AccessDenied(_)));
+        assert_eq!(policy.checks.load(Ordering::SeqCst), 3);
+
+        // The standard interfaces are checked too.
+        service
+            .object_server()
+            .set_authorizer(Policy {
+                uid: policy.uid + 1,
+                checks: policy.checks.clone(),
+            })
+            .await;
+        let err = fdo::IntrospectableProxy::builder(&client)
+            .destination(service.unique_name().unwrap())?
+            .path("/org/freedesktop/zbus/Vault")?
+            .build()
+            .await?
+            .introspect()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, fdo::Error::AccessDenied(_)));
+        let err = call("Secret").await.unwrap_err();
+        assert!(matches!(fdo::Error::from(err), fdo::Error::AccessDenied(_)));
+        let checks = policy.checks.load(Ordering::SeqCst);
+        // Calls that don't resolve aren't authorized, and get the usual errors.
+        let err = call("Missing").await.unwrap_err();
+        assert!(matches!(
+            fdo::Error::from(err),
+            fdo::Error::UnknownMethod(_)
+        ));
+        let err = client
+            .call_method(
+                service.unique_name(),
+                "/org/freedesktop/zbus/Vault",
+                Some("org.freedesktop.zbus.Missing"),
+                "Secret",
+                &(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            fdo::Error::from(err),
+            fdo::Error::UnknownInterface(_)
+        ));
+        assert_eq!(policy.checks.load(Ordering::SeqCst), checks);
+
+        service.object_server().remove_authorizer().await;
+        let reply = call("Forbidden").await?;
+        assert_eq!(reply.body().deserialize::<u32>()?, 4);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn slow_authorizer() {
+        block_on(test_slow_authorizer()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_slow_authorizer() -> Result<()> {
+        use event_listener::Event;
+        use futures_util::future::join;
+        use zbus::{
+            dbus_interface,
+            object_server::{Authorization, AuthorizationRequest, Authorizer},
+        };
+
+        struct Counter(u32);
+
+        #[dbus_interface(name = "org.freedesktop.zbus.Counter")]
+        impl Counter {
+            fn count(&self) -> u32 {
+                self.0
+            }
+
+            fn increment(&mut self) -> u32 {
+                self.0 += 1;
+                self.0
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct Policy {
+            started: Arc<Event>,
+            release: Arc<Event>,
+        }
+
+        impl Authorizer for Policy {
+            fn authorize<'a>(&'a self, request: &'a AuthorizationRequest<'a>) -> Authorization<'a> {
+                if request.member() != "Count" {
+                    return Authorization::Allow;
+                }
+
+                let release = self.release.listen();
+                self.started.notify(1);
+                Authorization::Async(Box::pin(async move {
+                    release.await;
+                    true
+                }))
+            }
+        }
+
+        let policy = Policy::default();
+        let service = crate::connection::Builder::session()?
+            .serve_at("/org/freedesktop/zbus/Counter", Counter(0))?
+            .authorizer(policy.clone())
+            .build()
+            .await?;
+        let client = crate::connection::Builder::session()?.build().await?;
+        let call = |method| {
+            client.call_method(
+                service.unique_name(),
+                "/org/freedesktop/zbus/Counter",
+                Some("org.freedesktop.zbus.Counter"),
+                method,
+                &(),
+            )
+        };
+
+        // A `&mut self` method can be called while another call awaits its authorization.
+        let started = policy.started.listen();
+        let (count, increment) = join(call("Count"), async {
+            started.await;
+            let reply = call("Increment").await;
+            policy.release.notify(1);
+
+            reply
+        })
+        .await;
+        assert_eq!(increment?.body().deserialize::<u32>()?, 1);
+        assert_eq!(count?.body().deserialize::<u32>()?, 1);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn deferred_reply() {
+        block_on(test_deferred_reply()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_deferred_reply() -> Result<()> {
+        #[cfg(not(feature = "tokio"))]
+        use std::os::unix::net::UnixStream;
+        #[cfg(feature = "tokio")]
+        use tokio::net::UnixStream;
+        use zbus::{dbus_interface, fdo, object_server::MethodReply};
+
+        struct Deferred {
+            pending: Vec<MethodReply<u32>>,
+            notify: async_broadcast::Sender<()>,
+        }
+
+        #[dbus_interface(name = "org.freedesktop.zbus.Deferred")]
+        impl Deferred {
+            async fn wait(&mut self, #[zbus(reply_handle)] reply: MethodReply<u32>) {
+                self.pending.push(reply);
+                self.notify.broadcast(()).await.unwrap();
+            }
+
+            async fn release(&mut self, value: u32) -> fdo::Result<()> {
+                for reply in self.pending.drain(..) {
+                    reply.reply(&value).await?;
+                }
+
+                Ok(())
+            }
+
+            fn drop_pending(&mut self, deny: bool) -> fdo::Result<()> {
+                if deny {
+                    for reply in &mut self.pending {
+                        reply.set_drop_error(fdo::Error::AccessDenied("Denied".to_string()))?;
+                    }
+                }
+                self.pending.clear();
+
+                Ok(())
+            }
+        }
+
+        async fn call<B>(client: &Connection, method: &str, body: &B) -> Result<Message>
+        where
+            B: serde::Serialize + zvariant::DynamicType,
+        {
+            client
+                .call_method(
+                    Some("org.freedesktop.zbus.Deferred"),
+                    "/org/freedesktop/zbus/Deferred",
+                    Some("org.freedesktop.zbus.Deferred"),
+                    method,
+                    body,
+                )
+                .await
+        }
+
+        let (notify, mut notified) = async_broadcast::broadcast(4);
+        let guid = crate::Guid::generate();
+        let (p0, p1) = UnixStream::pair().unwrap();
+        let (_service, client) = futures_util::try_join!(
+            crate::connection::Builder::unix_stream(p0)
+                .server(&guid)
+                .p2p()
+                .serve_at(
+                    "/org/freedesktop/zbus/Deferred",
+                    Deferred {
+                        pending: vec![],
+                        notify,
+                    },
+                )?
+                .build(),
+            crate::connection::Builder::unix_stream(p1).p2p().build(),
+        )?;
+
+        // Replies from another method call.
+        let (reply1, reply2, _) = futures_util::try_join!(
+            call(&client, "Wait", &()),
+            call(&client, "Wait", &()),
+            async {
+                notified.recv().await.unwrap();
+                notified.recv().await.unwrap();
+                call(&client, "Release", &42u32).await
+            }
+        )?;
+        assert_eq!(reply1.body().deserialize::<u32>()?, 42);
+        assert_eq!(reply2.body().deserialize::<u32>()?, 42);
+
+        // Dropped handles.
+        for (deny, expected) in [(false, "NoReply"), (true, "AccessDenied")] {
+            let (err, _) = futures_util::join!(call(&client, "Wait", &()), async {
+                notified.recv().await.unwrap();
+                call(&client, "DropPending", &deny).await.unwrap();
+            });
+            let err = fdo::Error::from(err.unwrap_err());
+            match (expected, err) {
+                ("NoReply", fdo::Error::NoReply(_))
+                | ("AccessDenied", fdo::Error::AccessDenied(_)) => (),
+                (_, err) => panic!("unexpected error: {err}"),
+            }
+        }
+
+        // The handle's type describes the out arguments.
+        let xml = crate::fdo::IntrospectableProxy::builder(&client)
+            .destination("org.freedesktop.zbus.Deferred")?
+            .path("/org/freedesktop/zbus/Deferred")?
+            .build()
+            .await?
+            .introspect()
+            .await?;
+        let node = zbus_xml::Node::from_reader(xml.as_bytes()).unwrap();
+        let iface = node
+            .interfaces()
+            .iter()
+            .find(|i| i.name() == "org.freedesktop.zbus.Deferred")
+            .unwrap();
+        let wait = iface.methods().iter().find(|m| m.name() == "Wait").unwrap();
+        let args: Vec<_> = wait
+            .args()
+            .iter()
+            .map(|a| (a.ty().to_string(), a.direction()))
+            .collect();
+        assert_eq!(args, [("u".to_string(), Some(zbus_xml::ArgDirection::Out))]);
+
+        Ok(())
+    }
+
     #[test]
     #[timeout(15000)]
     fn issue_260() {
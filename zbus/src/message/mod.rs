@@ -36,6 +36,10 @@ pub struct Sequence {
 impl Sequence {
     /// A sequence number that is higher than any other; used by errors that terminate a stream.
     pub(crate) const LAST: Self = Self { recv_seq: u64::MAX };
+
+    pub(crate) fn new(recv_seq: u64) -> Self {
+        Self { recv_seq }
+    }
 }
 
 /// A D-Bus Message.
@@ -283,6 +287,15 @@ impl Message {
     pub fn recv_position(&self) -> Sequence {
         self.inner.recv_seq
     }
+
+    /// The same message, received at `position`.
+    pub(crate) fn with_recv_position(&self, position: Sequence) -> Result<Self> {
+        if self.inner.recv_seq == position {
+            return Ok(self.clone());
+        }
+
+        Self::from_raw_parts(self.inner.bytes.clone(), position.recv_seq)
+    }
 }
 
 impl fmt::Debug for Message {
@@ -1,6 +1,9 @@
 use std::{
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{self, AtomicUsize},
+        Arc,
+    },
     task::{Context, Poll},
 };
 
@@ -12,9 +15,9 @@ use static_assertions::assert_impl_all;
 use tracing::warn;
 
 use crate::{
-    connection::ConnectionInner,
+    connection::{ConnectionInner, Gauge, Tracked},
     message::{Message, Sequence},
-    AsyncDrop, Connection, MatchRule, OwnedMatchRule, Result,
+    AsyncDrop, Connection, Error, MatchRule, OwnedMatchRule, Result,
 };
 
 /// A [`stream::Stream`] implementation that yields [`Message`] items.
@@ -144,6 +147,70 @@ impl MessageStream {
         ))
     }
 
+    /// Create a message stream for the given match rule, with the given [`OverflowPolicy`].
+    ///
+    /// This is the same as [`MessageStream::for_match_rule`], except for what happens when the
+    /// queue of the stream is full. With [`OverflowPolicy::Block`], the stream shares its queue
+    /// with the other streams for the same match rule, and the reception of messages is held up
+    /// until the stream makes room. With the other policies, the stream gets a queue of its own
+    /// and messages are dropped instead. The stream then yields an [`Error::MissedMessages`] item,
+    /// with the number of messages it missed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures_util::StreamExt;
+    /// use zbus::{Connection, Error, MatchRule, MessageStream, OverflowPolicy};
+    ///
+    /// # zbus::block_on(async {
+    /// let conn = Connection::session().await?;
+    /// let rule = MatchRule::builder()
+    ///     .msg_type(zbus::message::Type::Signal)
+    ///     .interface("org.freedesktop.DBus.Properties")?
+    ///     .build();
+    /// let mut stream = MessageStream::for_match_rule_with_overflow_policy(
+    ///     rule,
+    ///     &conn,
+    ///     Some(16),
+    ///     // We only care about the latest changes.
+    ///     OverflowPolicy::DropOldest,
+    /// )
+    /// .await?;
+    ///
+    /// while let Some(msg) = stream.next().await {
+    ///     match msg {
+    ///         Ok(msg) => println!("Got message: {msg}"),
+    ///         Err(Error::MissedMessages(n)) => println!("Missed {n} messages"),
+    ///         Err(e) => return Err(e),
+    ///     }
+    /// }
+    /// # Ok::<(), zbus::Error>(())
+    /// # }).unwrap();
+    /// ```
+    pub async fn for_match_rule_with_overflow_policy<R>(
+        rule: R,
+        conn: &Connection,
+        max_queued: Option<usize>,
+        policy: OverflowPolicy,
+    ) -> Result<Self>
+    where
+        R: TryInto<OwnedMatchRule>,
+        R::Error: Into<crate::Error>,
+    {
+        if policy == OverflowPolicy::Block {
+            return Self::for_match_rule(rule, conn, max_queued).await;
+        }
+
+        let rule = rule.try_into().map_err(Into::into)?;
+        let (msg_receiver, missed) = conn
+            .add_lossy_match(rule.clone(), max_queued, policy)
+            .await?;
+        let mut stream = Self::for_subscription_channel(msg_receiver, Some(rule), conn);
+        stream.inner.missed = Some(missed);
+
+        Ok(stream)
+    }
+
     /// The associated match rule, if any.
     pub fn match_rule(&self) -> Option<MatchRule<'_>> {
         self.inner.match_rule.as_deref().cloned()
@@ -172,11 +239,7 @@ impl MessageStream {
         let conn_inner = conn.inner.clone();
 
         Self {
-            inner: Inner {
-                conn_inner,
-                msg_receiver,
-                match_rule: rule,
-            },
+            inner: Inner::new(conn_inner, msg_receiver, rule),
         }
     }
 }
@@ -187,6 +250,13 @@ impl stream::Stream for MessageStream {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
 
+        if let Some(missed) = &this.inner.missed {
+            let missed = missed.swap(0, atomic::Ordering::SeqCst);
+            if missed > 0 {
+                return Poll::Ready(Some(Err(Error::MissedMessages(missed as u64))));
+            }
+        }
+
         Pin::new(&mut this.inner.msg_receiver).poll_next(cx)
     }
 }
@@ -242,11 +312,7 @@ impl From<Connection> for MessageStream {
         let msg_receiver = conn_inner.msg_receiver.activate_cloned();
 
         Self {
-            inner: Inner {
-                conn_inner,
-                msg_receiver,
-                match_rule: None,
-            },
+            inner: Inner::new(conn_inner, msg_receiver, None),
         }
     }
 }
@@ -276,6 +342,28 @@ struct Inner {
     conn_inner: Arc<ConnectionInner>,
     msg_receiver: ActiveReceiver<Result<Message>>,
     match_rule: Option<OwnedMatchRule>,
+    // The number of messages dropped from the queue, yet to be reported. Only set for streams with
+    // a lossy overflow policy.
+    missed: Option<Arc<AtomicUsize>>,
+    _tracked: Tracked,
+}
+
+impl Inner {
+    fn new(
+        conn_inner: Arc<ConnectionInner>,
+        msg_receiver: ActiveReceiver<Result<Message>>,
+        match_rule: Option<OwnedMatchRule>,
+    ) -> Self {
+        let _tracked = Tracked::new(conn_inner.stats.clone(), Gauge::MessageStreams);
+
+        Self {
+            conn_inner,
+            msg_receiver,
+            match_rule,
+            missed: None,
+            _tracked,
+        }
+    }
 }
 
 impl Drop for Inner {
@@ -304,3 +392,25 @@ impl AsyncDrop for MessageStream {
         }
     }
 }
+
+/// What to do with new messages when the queue of a [`MessageStream`] is full.
+///
+/// See [`MessageStream::for_match_rule_with_overflow_policy`] for details.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverflowPolicy {
+    /// Wait for the stream to make room.
+    ///
+    /// No message is lost but in the meantime, no message is received on the connection, including
+    /// method replies. This is the default.
+    #[default]
+    Block,
+    /// Drop the oldest message in the queue to make room for the new one.
+    DropOldest,
+    /// Drop the new message.
+    ///
+    /// Since the stream reports the loss as soon as it's polled, the missed messages are newer than
+    /// the ones it yields after the report.
+    DropNewest,
+}
+
+assert_impl_all!(OverflowPolicy: Send, Sync, Unpin);
@@ -0,0 +1,525 @@
+//! Monitoring the traffic of a bus.
+//!
+//! See [`Monitor`] for details.
+
+use futures_core::stream;
+use futures_util::StreamExt;
+use static_assertions::assert_impl_all;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::debug;
+use zbus_names::OwnedUniqueName;
+
+use crate::{
+    connection,
+    message::{Flags, Sequence, Type},
+    zvariant::Structure,
+    Connection, Error, MatchRule, Message, MessageStream, Result,
+};
+
+/// The maximum number of method calls tracked while waiting for their reply.
+///
+/// Calls whose reply never shows up, e.g because the monitor missed it, would otherwise accumulate
+/// forever. Once the limit is reached, the oldest call is forgotten.
+const MAX_PENDING_CALLS: usize = 4096;
+
+const DBUS_NAME: &str = "org.freedesktop.DBus";
+const DBUS_PATH: &str = "/org/freedesktop/DBus";
+
+/// Monitor the traffic of a bus.
+///
+/// This is the library equivalent of `dbus-monitor` and `busctl monitor`. The monitor uses a
+/// dedicated connection, turned into a monitor through the `BecomeMonitor` method of the
+/// [`org.freedesktop.DBus.Monitoring`] interface. Only the messages matching any of the given
+/// match rules are monitored, or all of them if no rule is given.
+///
+/// Older bus implementations don't support `BecomeMonitor`. With those, the monitor falls back to
+/// adding the match rules with `eavesdrop=true`, which lets the connection see the messages that
+/// aren't addressed to it, as long as the bus policy allows it. If even that isn't supported, the
+/// rules are added as is, and only broadcast signals can be monitored.
+/// [`Monitor::is_eavesdropping`] tells if the fallback was used.
+///
+/// The monitor is a [`stream::Stream`] of [`MonitoredMessage`]s. Each method call expecting a reply
+/// is kept track of, so that the latency of its reply or error can be reported.
+///
+/// # Example
+///
+/// Printing all the method calls to the `org.freedesktop.DBus` interface, and their replies:
+///
+/// ```no_run
+///# zbus::block_on(async {
+/// use futures_util::stream::TryStreamExt;
+/// use zbus::{message::Type, MatchRule, Monitor};
+///
+/// let calls = MatchRule::builder()
+///     .msg_type(Type::MethodCall)
+///     .interface("org.freedesktop.DBus")?
+///     .build();
+/// let replies = MatchRule::builder()
+///     .msg_type(Type::MethodReturn)
+///     .sender("org.freedesktop.DBus")?
+///     .build();
+/// let mut monitor = Monitor::session(&[calls, replies]).await?;
+///
+/// while let Some(msg) = monitor.try_next().await? {
+///     println!("{msg}");
+/// }
+///# Ok::<(), zbus::Error>(())
+///# }).unwrap();
+/// ```
+///
+/// [`org.freedesktop.DBus.Monitoring`]: https://dbus.freedesktop.org/doc/dbus-specification.html#bus-messages-become-monitor
+#[derive(Debug)]
+pub struct Monitor {
+    conn: Connection,
+    stream: MessageStream,
+    unique_name: Option<OwnedUniqueName>,
+    // Messages received before the monitor was set up, aren't monitored.
+    since: Sequence,
+    eavesdropping: bool,
+    pending_calls: PendingCalls,
+}
+
+assert_impl_all!(Monitor: Send, Sync, Unpin);
+
+impl Monitor {
+    /// Monitor the session bus.
+    pub async fn session(rules: &[MatchRule<'_>]) -> Result<Self> {
+        Self::new(connection::Builder::session()?, rules).await
+    }
+
+    /// Monitor the system bus.
+    pub async fn system(rules: &[MatchRule<'_>]) -> Result<Self> {
+        Self::new(connection::Builder::system()?, rules).await
+    }
+
+    /// Monitor the bus the connection built by `builder` is to.
+    ///
+    /// The connection is turned into a monitor, so it can't be used for anything else.
+    pub async fn new(builder: connection::Builder<'_>, rules: &[MatchRule<'_>]) -> Result<Self> {
+        let conn = builder.build().await?;
+        // Create the stream first, so no message is missed between the reply and its creation.
+        let stream = MessageStream::from(&conn);
+        let unique_name = conn.unique_name().map(|name| name.to_owned());
+
+        let become_monitor = conn
+            .call_method(
+                Some(DBUS_NAME),
+                DBUS_PATH,
+                Some("org.freedesktop.DBus.Monitoring"),
+                "BecomeMonitor",
+                &(rules, 0u32),
+            )
+            .await;
+        let (since, eavesdropping) = match become_monitor {
+            Ok(reply) => (reply.recv_position(), false),
+            Err(Error::MethodError(name, _, _))
+                if name == "org.freedesktop.DBus.Error.UnknownInterface"
+                    || name == "org.freedesktop.DBus.Error.UnknownMethod" =>
+            {
+                debug!("`BecomeMonitor` not supported by the bus, falling back to eavesdropping");
+
+                (eavesdrop(&conn, rules).await?, true)
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            conn,
+            stream,
+            unique_name,
+            since,
+            eavesdropping,
+            pending_calls: PendingCalls::default(),
+        })
+    }
+
+    /// The connection used for monitoring.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// If the bus doesn't support `BecomeMonitor` and eavesdropping match rules are used instead.
+    pub fn is_eavesdropping(&self) -> bool {
+        self.eavesdropping
+    }
+
+    fn is_own(&self, msg: &Message) -> bool {
+        // The bus tells us about losing our unique name when we become a monitor. Like the other
+        // signals addressed to us, it's not part of the monitored traffic.
+        let header = msg.header();
+
+        msg.message_type() == Type::Signal
+            && self
+                .unique_name
+                .as_ref()
+                .zip(header.destination())
+                .map_or(false, |(name, dest)| *dest == **name)
+    }
+
+    fn track(&mut self, msg: &Message) -> Option<Duration> {
+        let header = msg.header();
+
+        match msg.message_type() {
+            Type::MethodCall => {
+                if header.primary().flags().contains(Flags::NoReplyExpected) {
+                    return None;
+                }
+
+                let sender = header.sender().map(|s| s.to_string()).unwrap_or_default();
+                let serial = header.primary().serial_num().get();
+                self.pending_calls.insert((sender, serial));
+
+                None
+            }
+            Type::MethodReturn | Type::Error => {
+                let destination = header.destination()?.to_string();
+                let serial = header.reply_serial()?.get();
+
+                self.pending_calls
+                    .remove(&(destination, serial))
+                    .map(|called| called.elapsed())
+            }
+            Type::Signal => None,
+        }
+    }
+}
+
+impl stream::Stream for Monitor {
+    type Item = Result<MonitoredMessage>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let msg = match futures_util::ready!(this.stream.poll_next_unpin(cx)) {
+                Some(Ok(msg)) => msg,
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            };
+            if msg.recv_position() <= this.since || this.is_own(&msg) {
+                continue;
+            }
+
+            let latency = this.track(&msg);
+
+            return Poll::Ready(Some(Ok(MonitoredMessage {
+                message: msg,
+                timestamp: SystemTime::now(),
+                latency,
+            })));
+        }
+    }
+}
+
+/// The method calls waiting for their reply, in the order they were seen.
+#[derive(Debug, Default)]
+struct PendingCalls {
+    calls: HashMap<(String, u32), Instant>,
+    // The calls, oldest first. Entries whose call got its reply are only removed once they reach
+    // the front, so the queue can be longer than `calls`.
+    order: VecDeque<((String, u32), Instant)>,
+}
+
+impl PendingCalls {
+    fn insert(&mut self, call: (String, u32)) {
+        while self.calls.len() >= MAX_PENDING_CALLS || self.order.len() >= 2 * MAX_PENDING_CALLS {
+            let Some((oldest, called)) = self.order.pop_front() else {
+                break;
+            };
+            // The same sender and serial could be reused by a later call, only forget this one.
+            if self.calls.get(&oldest) == Some(&called) {
+                self.calls.remove(&oldest);
+            }
+        }
+
+        let called = Instant::now();
+        self.calls.insert(call.clone(), called);
+        self.order.push_back((call, called));
+    }
+
+    fn remove(&mut self, call: &(String, u32)) -> Option<Instant> {
+        self.calls.remove(call)
+    }
+}
+
+/// A message seen by a [`Monitor`].
+///
+/// Its [`Display`](fmt::Display) implementation prints the message in a format close to the one of
+/// `dbus-monitor`: the header fields on a first line, and the body, if any, on a second one.
+#[derive(Debug, Clone)]
+pub struct MonitoredMessage {
+    message: Message,
+    timestamp: SystemTime,
+    latency: Option<Duration>,
+}
+
+assert_impl_all!(MonitoredMessage: Send, Sync, Unpin);
+
+impl MonitoredMessage {
+    /// The message.
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    /// Consume `self`, returning the message.
+    pub fn into_message(self) -> Message {
+        self.message
+    }
+
+    /// When the message was seen by the monitor.
+    ///
+    /// This is when the message was read from the [`Monitor`] stream, not when it was received from
+    /// the bus. If the stream isn't polled continuously, the messages queue up in the meantime and
+    /// their timestamps include the time they spent queued.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// The time elapsed since the method call this message is the reply to.
+    ///
+    /// This is only available for method returns and errors, if the monitor saw their method
+    /// call.
+    ///
+    /// Like the [timestamps](Self::timestamp), it's measured when the messages are read from the
+    /// [`Monitor`] stream.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+}
+
+impl fmt::Display for MonitoredMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = self.message.header();
+        let msg_type = match self.message.message_type() {
+            Type::MethodCall => "method call",
+            Type::MethodReturn => "method return",
+            Type::Error => "error",
+            Type::Signal => "signal",
+        };
+        let time = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        write!(
+            f,
+            "{msg_type} time={}.{:06}",
+            time.as_secs(),
+            time.subsec_micros()
+        )?;
+
+        match header.sender() {
+            Some(sender) => write!(f, " sender={sender}")?,
+            None => f.write_str(" sender=(null sender)")?,
+        }
+        match header.destination() {
+            Some(destination) => write!(f, " -> destination={destination}")?,
+            None => f.write_str(" -> destination=(null destination)")?,
+        }
+        write!(f, " serial={}", header.primary().serial_num())?;
+        if let Some(reply_serial) = header.reply_serial() {
+            write!(f, " reply_serial={reply_serial}")?;
+        }
+        if let Some(path) = header.path() {
+            write!(f, " path={path}")?;
+        }
+        if let Some(interface) = header.interface() {
+            write!(f, " interface={interface}")?;
+        }
+        if let Some(member) = header.member() {
+            write!(f, " member={member}")?;
+        }
+        if let Some(error_name) = header.error_name() {
+            write!(f, " error_name={error_name}")?;
+        }
+        if let Some(latency) = self.latency {
+            write!(f, " latency={latency:?}")?;
+        }
+
+        let body = self.message.body();
+        if body.signature().map_or(true, |s| s.is_empty()) {
+            return Ok(());
+        }
+        match body.deserialize::<Structure<'_>>() {
+            Ok(body) => write!(f, "\n   {body}"),
+            Err(_) => f.write_str("\n   (undecodable body)"),
+        }
+    }
+}
+
+/// Add the eavesdropping variant of `rules`, returning the position of the last reply.
+async fn eavesdrop(conn: &Connection, rules: &[MatchRule<'_>]) -> Result<Sequence> {
+    // Like `dbus-monitor`, use a rule per message type when there are no rules, as a rule without
+    // any type only matches signals on some bus implementations.
+    let rules: Vec<String> = if rules.is_empty() {
+        ["signal", "method_call", "method_return", "error"]
+            .iter()
+            .map(|msg_type| format!("type='{msg_type}'"))
+            .collect()
+    } else {
+        rules.iter().map(ToString::to_string).collect()
+    };
+
+    let mut since = Sequence::default();
+    for rule in rules {
+        let eavesdrop_rule = if rule.is_empty() {
+            String::from("eavesdrop=true")
+        } else {
+            format!("{rule},eavesdrop=true")
+        };
+        // Match rules are sent as strings since `MatchRule` can't represent `eavesdrop`.
+        let reply = match add_match(conn, &eavesdrop_rule).await {
+            Ok(reply) => reply,
+            Err(Error::MethodError(name, _, _)) => {
+                debug!("Eavesdropping not supported by the bus ({name}), adding `{rule}` as is");
+
+                add_match(conn, &rule).await?
+            }
+            Err(e) => return Err(e),
+        };
+        since = reply.recv_position();
+    }
+
+    Ok(since)
+}
+
+async fn add_match(conn: &Connection, rule: &str) -> Result<Message> {
+    conn.call_method(
+        Some(DBUS_NAME),
+        DBUS_PATH,
+        Some(DBUS_NAME),
+        "AddMatch",
+        &rule,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::stream::TryStreamExt;
+    use ntest::timeout;
+    use test_log::test;
+
+    use super::*;
+    use crate::broker::Broker;
+
+    #[test]
+    #[timeout(15000)]
+    fn monitor() {
+        crate::utils::block_on(test_monitor()).unwrap();
+    }
+
+    async fn test_monitor() -> Result<()> {
+        let conn = Connection::session().await?;
+        let name = conn.unique_name().unwrap();
+        let rules = [
+            MatchRule::builder().sender(&name)?.build(),
+            MatchRule::builder().destination(&name)?.build(),
+        ];
+        let mut monitor = Monitor::session(&rules).await?;
+        assert!(!monitor.is_eavesdropping());
+
+        let reply = conn
+            .call_method(Some(DBUS_NAME), DBUS_PATH, Some(DBUS_NAME), "GetId", &())
+            .await?;
+
+        let call = monitor.try_next().await?.unwrap();
+        assert_eq!(call.message().message_type(), Type::MethodCall);
+        assert_eq!(call.message().header().member().unwrap(), "GetId");
+        assert!(call.latency().is_none());
+        let printed = call.to_string();
+        assert!(printed.starts_with("method call "), "{printed}");
+        assert!(printed.contains(&format!(" sender={name} ")), "{printed}");
+        assert!(printed.contains(" member=GetId"), "{printed}");
+
+        let ret = monitor.try_next().await?.unwrap();
+        assert_eq!(ret.message().message_type(), Type::MethodReturn);
+        assert_eq!(
+            ret.message().header().reply_serial(),
+            Some(call.message().primary_header().serial_num())
+        );
+        assert!(ret.latency().is_some());
+        let id: String = reply.body().deserialize()?;
+        let printed = ret.to_string();
+        assert!(printed.starts_with("method return "), "{printed}");
+        assert!(printed.contains(" latency="), "{printed}");
+        assert!(printed.contains(&id), "{printed}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn pending_calls() {
+        let mut pending = PendingCalls::default();
+        for serial in 0..MAX_PENDING_CALLS as u32 {
+            pending.insert((String::from(":1.1"), serial));
+        }
+        assert!(pending.remove(&(String::from(":1.1"), 1)).is_some());
+        // A call whose reply was seen, doesn't count towards the limit.
+        pending.insert((String::from(":1.2"), 0));
+        assert!(pending.remove(&(String::from(":1.1"), 0)).is_some());
+        pending.insert((String::from(":1.2"), 1));
+        // The oldest call still waiting is forgotten, once the limit is reached.
+        pending.insert((String::from(":1.3"), 0));
+        assert!(pending.remove(&(String::from(":1.1"), 2)).is_none());
+        assert!(pending.remove(&(String::from(":1.1"), 3)).is_some());
+        assert!(pending.remove(&(String::from(":1.2"), 0)).is_some());
+        assert!(pending.remove(&(String::from(":1.3"), 0)).is_some());
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn eavesdrop_fallback() {
+        crate::utils::block_on(test_eavesdrop_fallback()).unwrap();
+    }
+
+    async fn test_eavesdrop_fallback() -> Result<()> {
+        // The broker doesn't support `BecomeMonitor`, nor eavesdropping.
+        let dir = tempfile::tempdir().unwrap();
+        let broker = Broker::builder(format!("unix:dir={}", dir.path().display()).as_str())?
+            .build()
+            .await?;
+        let mut monitor =
+            Monitor::new(connection::Builder::address(broker.address().clone())?, &[]).await?;
+        assert!(monitor.is_eavesdropping());
+
+        let conn = connection::Builder::address(broker.address().clone())?
+            .build()
+            .await?;
+        conn.emit_signal(
+            None::<zbus_names::BusName<'_>>,
+            "/org/zbus/MonitorTest",
+            "org.zbus.MonitorTest",
+            "Ping",
+            &(42u32,),
+        )
+        .await?;
+
+        let signal = loop {
+            let msg = monitor.try_next().await?.unwrap();
+            if msg
+                .message()
+                .header()
+                .member()
+                .map_or(false, |m| m == "Ping")
+            {
+                break msg;
+            }
+        };
+        assert_eq!(
+            signal.message().header().sender().unwrap(),
+            &*conn.unique_name().unwrap()
+        );
+        let printed = signal.to_string();
+        assert!(printed.starts_with("signal "), "{printed}");
+        assert!(printed.contains(" path=/org/zbus/MonitorTest"), "{printed}");
+        assert!(printed.contains("uint32 42"), "{printed}");
+
+        Ok(())
+    }
+}
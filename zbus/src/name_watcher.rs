@@ -0,0 +1,248 @@
+//! Watching the ownership of bus names.
+//!
+//! See [`NameWatcher`] for details.
+
+use futures_core::stream;
+use futures_util::{stream::FusedStream, StreamExt};
+use static_assertions::assert_impl_all;
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::debug;
+use zbus_names::{BusName, OwnedBusName, OwnedUniqueName, UniqueName};
+
+use crate::{
+    fdo::{DBusProxy, NameOwnerChangedStream},
+    message::Sequence,
+    CacheProperties, Connection, Error, Message, Result,
+};
+
+/// A change in the ownership of the name watched by a [`NameWatcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The name is owned by the given unique name.
+    Appeared(OwnedUniqueName),
+    /// The name has no owner.
+    Vanished,
+}
+
+/// Watch the ownership of a bus name.
+///
+/// This is the zbus equivalent of [`g_bus_watch_name`]. The watcher is a [`stream::Stream`] of
+/// [`Event`]s. The first event always reflects the state of the name at the time of creation:
+/// [`Event::Appeared`] with the current owner if the name is owned, [`Event::Vanished`] otherwise.
+/// After that, an [`Event::Appeared`] is yielded every time the name gets claimed and an
+/// [`Event::Vanished`] every time it goes away. If the name is handed over from one owner to
+/// another directly, both events are yielded.
+///
+/// The watcher subscribes to `NameOwnerChanged` signals for the name before asking the bus for the
+/// current owner, and ignores the signals received before the reply. Hence no ownership change can
+/// be missed or reported twice.
+///
+/// # Example
+///
+/// ```no_run
+///# use std::error::Error;
+///# zbus::block_on(async {
+/// use futures_util::StreamExt;
+/// use zbus::{name_watcher::Event, Connection, NameWatcher};
+///
+/// let conn = Connection::session().await?;
+/// let mut watcher = NameWatcher::new(&conn, "org.freedesktop.Notifications").await?;
+/// while let Some(event) = watcher.next().await {
+///     match event {
+///         Event::Appeared(owner) => println!("Owned by {owner}"),
+///         Event::Vanished => println!("Not owned"),
+///     }
+/// }
+///# Ok::<(), Box<dyn Error + Send + Sync>>(())
+///# }).unwrap();
+/// ```
+///
+/// [`g_bus_watch_name`]: https://docs.gtk.org/gio/func.bus_watch_name.html
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct NameWatcher {
+    name: OwnedBusName,
+    owner: Option<OwnedUniqueName>,
+    // The position of the `GetNameOwner` reply. Older signals are already reflected in it.
+    since: Sequence,
+    pending: VecDeque<Event>,
+    #[derivative(Debug = "ignore")]
+    stream: NameOwnerChangedStream<'static>,
+}
+
+assert_impl_all!(NameWatcher: Send, Sync, Unpin);
+
+impl NameWatcher {
+    /// Start watching `name` on the bus `conn` is connected to.
+    pub async fn new<'n, N>(conn: &Connection, name: N) -> Result<Self>
+    where
+        N: TryInto<BusName<'n>>,
+        N::Error: Into<Error>,
+    {
+        let name: OwnedBusName = name.try_into().map_err(Into::into)?.into();
+        let stream = DBusProxy::builder(conn)
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?
+            .receive_name_owner_changed_with_args(&[(0, name.as_str())])
+            .await?;
+
+        let reply = conn
+            .call_method(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                Some("org.freedesktop.DBus"),
+                "GetNameOwner",
+                &name,
+            )
+            .await;
+        let (owner, since) = match reply {
+            Ok(reply) => (
+                Some(reply.body().deserialize::<OwnedUniqueName>()?),
+                reply.recv_position(),
+            ),
+            Err(Error::MethodError(error, _, reply))
+                if error.as_str() == "org.freedesktop.DBus.Error.NameHasNoOwner" =>
+            {
+                (None, reply.recv_position())
+            }
+            Err(e) => return Err(e),
+        };
+        let initial = match &owner {
+            Some(owner) => Event::Appeared(owner.clone()),
+            None => Event::Vanished,
+        };
+
+        Ok(Self {
+            name,
+            owner,
+            since,
+            pending: VecDeque::from([initial]),
+            stream,
+        })
+    }
+
+    /// The name being watched.
+    pub fn name(&self) -> &BusName<'_> {
+        self.name.inner()
+    }
+
+    /// The current owner of the name, as of the last event yielded.
+    pub fn owner(&self) -> Option<&UniqueName<'_>> {
+        self.owner.as_ref().map(|o| o.inner())
+    }
+}
+
+impl stream::Stream for NameWatcher {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                match &event {
+                    Event::Appeared(owner) => this.owner = Some(owner.clone()),
+                    Event::Vanished => this.owner = None,
+                }
+
+                return Poll::Ready(Some(event));
+            }
+
+            let signal = match futures_util::ready!(this.stream.poll_next_unpin(cx)) {
+                Some(signal) => signal,
+                None => return Poll::Ready(None),
+            };
+            if Message::from(signal.clone()).recv_position() <= this.since {
+                continue;
+            }
+            let args = match signal.args() {
+                Ok(args) => args,
+                Err(e) => {
+                    debug!("Ignoring malformed `NameOwnerChanged` signal: {e}");
+
+                    continue;
+                }
+            };
+            if args.old_owner().is_some() {
+                this.pending.push_back(Event::Vanished);
+            }
+            if let Some(new_owner) = args.new_owner().as_ref() {
+                this.pending
+                    .push_back(Event::Appeared(new_owner.to_owned().into()));
+            }
+        }
+    }
+}
+
+impl FusedStream for NameWatcher {
+    fn is_terminated(&self) -> bool {
+        self.pending.is_empty() && self.stream.is_terminated()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use ntest::timeout;
+    use test_log::test;
+
+    use super::{Event, NameWatcher};
+    use crate::{connection, Result};
+
+    #[test]
+    #[timeout(15000)]
+    fn name_watcher() {
+        crate::utils::block_on(test_name_watcher()).unwrap();
+    }
+
+    async fn test_name_watcher() -> Result<()> {
+        let name = "org.zbus.NameWatcherTest";
+        let conn = connection::Builder::session()?.build().await?;
+        let mut watcher = NameWatcher::new(&conn, name).await?;
+        assert_eq!(watcher.next().await.unwrap(), Event::Vanished);
+        assert!(watcher.owner().is_none());
+
+        let owner1 = connection::Builder::session()?.name(name)?.build().await?;
+        let name1 = owner1.unique_name().unwrap().to_owned();
+        assert_eq!(
+            watcher.next().await.unwrap(),
+            Event::Appeared(name1.clone())
+        );
+        assert_eq!(watcher.owner(), Some(&*name1));
+
+        // A watcher created while the name is owned starts with the current owner.
+        let mut watcher2 = NameWatcher::new(&conn, name).await?;
+        assert_eq!(
+            watcher2.next().await.unwrap(),
+            Event::Appeared(name1.clone())
+        );
+
+        // A direct hand-over is reported as the name vanishing and reappearing.
+        let owner2 = connection::Builder::session()?.build().await?;
+        let name2 = owner2.unique_name().unwrap().to_owned();
+        owner2
+            .request_name_with_flags(name, Default::default())
+            .await?;
+        owner1.release_name(name).await?;
+        assert_eq!(watcher.next().await.unwrap(), Event::Vanished);
+        assert_eq!(
+            watcher.next().await.unwrap(),
+            Event::Appeared(name2.clone())
+        );
+
+        drop(owner2);
+        assert_eq!(watcher.next().await.unwrap(), Event::Vanished);
+        assert!(watcher.owner().is_none());
+        assert_eq!(watcher2.next().await.unwrap(), Event::Vanished);
+        assert_eq!(watcher2.next().await.unwrap(), Event::Appeared(name2));
+        assert_eq!(watcher2.next().await.unwrap(), Event::Vanished);
+
+        Ok(())
+    }
+}
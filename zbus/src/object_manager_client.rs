@@ -0,0 +1,786 @@
+//! A client-side cache of the objects exposed through an [`ObjectManager`].
+//!
+//! See [`ObjectManagerClient`] for details.
+//!
+//! [`ObjectManager`]: crate::fdo::ObjectManager
+
+use async_broadcast::{broadcast, InactiveReceiver, Receiver, Sender};
+use futures_core::stream;
+use futures_util::{
+    future::{select, Either},
+    stream::FusedStream,
+    StreamExt,
+};
+use static_assertions::assert_impl_all;
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tracing::{debug, instrument, trace, warn};
+use zbus_names::{BusName, OwnedBusName, OwnedInterfaceName, OwnedUniqueName};
+use zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+
+use crate::{
+    fdo::{DBusProxy, ManagedObjects, NameOwnerChanged},
+    message::{Sequence, Type},
+    utils::sleep,
+    Connection, Error, MatchRule, Message, MessageStream, Result, Task,
+};
+
+const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const MAX_QUEUED_EVENTS: usize = 64;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// The interfaces of an object, along with their properties.
+pub type ObjectInterfaces = HashMap<OwnedInterfaceName, HashMap<String, OwnedValue>>;
+
+/// A change in the object tree of an [`ObjectManagerClient`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// An object was added or an existing object gained one or more interfaces.
+    InterfacesAdded {
+        /// The path of the object.
+        path: OwnedObjectPath,
+        /// The added interfaces, along with their properties.
+        interfaces: ObjectInterfaces,
+    },
+    /// An object was removed or lost one or more interfaces.
+    InterfacesRemoved {
+        /// The path of the object.
+        path: OwnedObjectPath,
+        /// The removed interfaces.
+        interfaces: Vec<OwnedInterfaceName>,
+    },
+    /// Properties of an interface on an object changed.
+    PropertiesChanged {
+        /// The path of the object.
+        path: OwnedObjectPath,
+        /// The interface the properties belong to.
+        interface: OwnedInterfaceName,
+        /// The properties that changed, along with their new values.
+        changed: HashMap<String, OwnedValue>,
+        /// The properties that were invalidated. Their values are no longer part of the tree.
+        invalidated: Vec<String>,
+    },
+}
+
+/// A live, client-side view of the objects exposed by a remote [`ObjectManager`].
+///
+/// On creation, the client subscribes to the `InterfacesAdded`, `InterfacesRemoved` and
+/// `PropertiesChanged` signals under the object manager's path, and only then calls
+/// `GetManagedObjects`. Signals that were received before the reply are ignored, since the reply
+/// already reflects them, so no change can be missed or applied twice.
+///
+/// From then on, the object tree is kept up to date in the background for as long as the client
+/// (or any of its clones) is alive. Use [`ObjectManagerClient::managed_objects`] to get a snapshot
+/// of the tree and [`ObjectManagerClient::receive_events`] to be notified of changes.
+///
+/// If the destination is a well-known name, the client also follows its ownership: all objects
+/// are removed when the name loses its owner and the tree is fetched again when it gets a new one.
+/// The objects of the previous owner are only replaced once the tree of the new owner has been
+/// fetched, and failed fetches are retried with an exponential backoff.
+///
+/// # Example
+///
+/// ```no_run
+///# use std::error::Error;
+///# zbus::block_on(async {
+/// use futures_util::StreamExt;
+/// use zbus::{object_manager_client::Event, Connection, ObjectManagerClient};
+///
+/// let conn = Connection::system().await?;
+/// let client = ObjectManagerClient::new(&conn, "org.bluez", "/").await?;
+/// for (path, interfaces) in client.managed_objects() {
+///     println!("{path}: {:?}", interfaces.keys().collect::<Vec<_>>());
+/// }
+///
+/// let mut events = client.receive_events();
+/// while let Some(event) = events.next().await {
+///     if let Event::InterfacesAdded { path, .. } = event {
+///         println!("{path} added");
+///     }
+/// }
+///# Ok::<(), Box<dyn Error + Send + Sync>>(())
+///# }).unwrap();
+/// ```
+///
+/// [`ObjectManager`]: crate::fdo::ObjectManager
+#[derive(Clone, Debug)]
+pub struct ObjectManagerClient {
+    inner: Arc<Inner>,
+}
+
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+struct Inner {
+    destination: OwnedBusName,
+    path: OwnedObjectPath,
+    objects: Arc<RwLock<ManagedObjects>>,
+    #[derivative(Debug = "ignore")]
+    events: InactiveReceiver<Event>,
+    #[derivative(Debug = "ignore")]
+    _task: Task<()>,
+}
+
+assert_impl_all!(ObjectManagerClient: Send, Sync, Unpin);
+
+impl ObjectManagerClient {
+    /// Create a client for the object manager at `path` on `destination`.
+    ///
+    /// This fetches the initial object tree before returning.
+    pub async fn new<'d, 'p, D, P>(conn: &Connection, destination: D, path: P) -> Result<Self>
+    where
+        D: TryInto<BusName<'d>>,
+        P: TryInto<ObjectPath<'p>>,
+        D::Error: Into<Error>,
+        P::Error: Into<Error>,
+    {
+        let destination: OwnedBusName = destination.try_into().map_err(Into::into)?.into();
+        let path: OwnedObjectPath = path.try_into().map_err(Into::into)?.into();
+
+        // Subscribe before fetching the tree so we don't miss any changes in between.
+        let mut rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .path_namespace(path.clone())?;
+        if conn.is_bus() {
+            rule = rule.sender(destination.clone())?;
+        }
+        let signals = MessageStream::for_match_rule(rule.build(), conn, None).await?;
+        let owner_changes = match &destination.inner() {
+            BusName::WellKnown(name) if conn.is_bus() => Some(
+                DBusProxy::new(conn)
+                    .await?
+                    .receive_name_owner_changed_with_args(&[(0, name.as_str())])
+                    .await?,
+            ),
+            _ => None,
+        };
+
+        let mut tracker = Tracker {
+            conn: conn.clone(),
+            destination: destination.clone(),
+            path: path.clone(),
+            owner: None,
+            since: None,
+            pending_owner: None,
+            failed_fetches: 0,
+            objects: Arc::new(RwLock::new(ManagedObjects::new())),
+            events: None,
+        };
+        tracker.fetch().await?;
+
+        let (mut sender, receiver) = broadcast(MAX_QUEUED_EVENTS);
+        sender.set_await_active(false);
+        tracker.events = Some(sender);
+        let objects = tracker.objects.clone();
+        let task = conn.executor().spawn(
+            tracker.run(signals, owner_changes),
+            &format!("object manager client for {destination}:{path}"),
+        );
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                destination,
+                path,
+                objects,
+                events: receiver.deactivate(),
+                _task: task,
+            }),
+        })
+    }
+
+    /// The destination of the object manager.
+    pub fn destination(&self) -> &BusName<'_> {
+        self.inner.destination.inner()
+    }
+
+    /// The path of the object manager.
+    pub fn path(&self) -> &ObjectPath<'_> {
+        &self.inner.path
+    }
+
+    /// A snapshot of the whole object tree.
+    pub fn managed_objects(&self) -> ManagedObjects {
+        self.inner.objects.read().expect("lock poisoned").clone()
+    }
+
+    /// A snapshot of the interfaces (and their properties) of the object at `path`.
+    ///
+    /// Returns `None` if no such object is currently known.
+    pub fn object(&self, path: &ObjectPath<'_>) -> Option<ObjectInterfaces> {
+        self.inner
+            .objects
+            .read()
+            .expect("lock poisoned")
+            .get(&OwnedObjectPath::from(path.to_owned()))
+            .cloned()
+    }
+
+    /// Get a stream of changes to the object tree.
+    ///
+    /// Only changes applied after this call are reported. Similar to [`MessageStream`], the
+    /// stream has a bounded queue and the tree stops being updated while it is full, so make sure
+    /// to keep reading from it or drop it.
+    pub fn receive_events(&self) -> EventStream {
+        EventStream {
+            receiver: self.inner.events.activate_cloned(),
+        }
+    }
+}
+
+/// A [`stream::Stream`] of changes to the object tree of an [`ObjectManagerClient`].
+///
+/// Use [`ObjectManagerClient::receive_events`] to create an instance of this type.
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct EventStream {
+    #[derivative(Debug = "ignore")]
+    receiver: Receiver<Event>,
+}
+
+assert_impl_all!(EventStream: Send, Sync, Unpin);
+
+impl stream::Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+impl FusedStream for EventStream {
+    fn is_terminated(&self) -> bool {
+        self.receiver.is_terminated()
+    }
+}
+
+enum Update {
+    Signal(Result<Message>),
+    OwnerChanged(NameOwnerChanged),
+}
+
+// The state of the background task keeping the tree up to date.
+struct Tracker {
+    conn: Connection,
+    destination: OwnedBusName,
+    path: OwnedObjectPath,
+    // The unique name of the current owner of the destination, if known.
+    owner: Option<OwnedUniqueName>,
+    // The position of the last `GetManagedObjects` reply. Older signals are already reflected in it.
+    since: Option<Sequence>,
+    // The new owner of the destination, until its tree is successfully fetched.
+    pending_owner: Option<OwnedUniqueName>,
+    failed_fetches: u32,
+    objects: Arc<RwLock<ManagedObjects>>,
+    events: Option<Sender<Event>>,
+}
+
+impl Tracker {
+    #[instrument(skip_all, fields(destination = %self.destination, path = %self.path))]
+    async fn run(
+        mut self,
+        signals: MessageStream,
+        owner_changes: Option<crate::fdo::NameOwnerChangedStream<'static>>,
+    ) {
+        let owner_changes = futures_util::stream::iter(owner_changes)
+            .flatten()
+            .map(Update::OwnerChanged);
+        let mut updates = futures_util::stream::select(signals.map(Update::Signal), owner_changes);
+
+        loop {
+            let update = if self.pending_owner.is_some() {
+                match select(updates.next(), sleep(self.retry_delay())).await {
+                    Either::Left((update, _)) => update,
+                    Either::Right(_) => {
+                        self.fetch_pending().await;
+
+                        continue;
+                    }
+                }
+            } else {
+                updates.next().await
+            };
+            let Some(update) = update else {
+                break;
+            };
+
+            match update {
+                Update::Signal(Ok(msg)) => {
+                    if let Err(e) = self.handle_signal(&msg).await {
+                        debug!("Ignoring malformed signal: {e}");
+                    }
+                }
+                Update::Signal(Err(e)) => {
+                    debug!("Error from the signal stream: {e}");
+                }
+                Update::OwnerChanged(signal) => match signal.args() {
+                    Ok(args) => {
+                        let new_owner = args.new_owner().as_ref().map(|o| o.to_owned().into());
+                        self.handle_owner_change(new_owner).await;
+                    }
+                    Err(e) => debug!("Ignoring malformed `NameOwnerChanged` signal: {e}"),
+                },
+            }
+        }
+        trace!("Signal stream ended, no longer tracking changes");
+    }
+
+    // Fetch the whole tree, replacing the current one.
+    //
+    // The current tree is left untouched if this fails.
+    async fn fetch(&mut self) -> Result<()> {
+        let reply = self
+            .conn
+            .call_method(
+                Some(self.destination.inner()),
+                &self.path,
+                Some(OBJECT_MANAGER_INTERFACE),
+                "GetManagedObjects",
+                &(),
+            )
+            .await?;
+        let objects: ManagedObjects = reply.body().deserialize()?;
+        let header = reply.header();
+        self.owner = header.sender().map(|s| s.to_owned().into());
+        self.since = Some(reply.recv_position());
+
+        let added: Vec<_> = objects
+            .iter()
+            .map(|(path, interfaces)| Event::InterfacesAdded {
+                path: path.clone(),
+                interfaces: interfaces.clone(),
+            })
+            .collect();
+        let removed =
+            std::mem::replace(&mut *self.objects.write().expect("lock poisoned"), objects);
+        self.remove_objects(removed).await;
+        for event in added {
+            self.emit(event).await;
+        }
+
+        Ok(())
+    }
+
+    // Try fetching the tree of the new owner, keeping the current one until that succeeds.
+    async fn fetch_pending(&mut self) {
+        match self.fetch().await {
+            Ok(()) => {
+                self.pending_owner = None;
+                self.failed_fetches = 0;
+            }
+            Err(e) => {
+                self.failed_fetches = self.failed_fetches.saturating_add(1);
+                warn!(
+                    "Failed to fetch managed objects from the new owner, retrying in {:?}: {e}",
+                    self.retry_delay(),
+                );
+            }
+        }
+    }
+
+    fn retry_delay(&self) -> Duration {
+        let factor = 1u32
+            .checked_shl(self.failed_fetches.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+
+        INITIAL_RETRY_DELAY
+            .checked_mul(factor)
+            .map_or(MAX_RETRY_DELAY, |delay| delay.min(MAX_RETRY_DELAY))
+    }
+
+    async fn handle_owner_change(&mut self, new_owner: Option<OwnedUniqueName>) {
+        if new_owner.is_some() && new_owner == self.owner {
+            self.pending_owner = None;
+
+            return;
+        }
+
+        match new_owner {
+            Some(new_owner) => {
+                self.pending_owner = Some(new_owner);
+                self.failed_fetches = 0;
+                self.fetch_pending().await;
+            }
+            None => {
+                // Whatever the previous owner exposed is gone now.
+                let objects = std::mem::take(&mut *self.objects.write().expect("lock poisoned"));
+                self.owner = None;
+                self.since = None;
+                self.pending_owner = None;
+                self.remove_objects(objects).await;
+            }
+        }
+    }
+
+    async fn remove_objects(&self, objects: ManagedObjects) {
+        for (path, interfaces) in objects {
+            self.emit(Event::InterfacesRemoved {
+                path,
+                interfaces: interfaces.into_keys().collect(),
+            })
+            .await;
+        }
+    }
+
+    async fn handle_signal(&mut self, msg: &Message) -> Result<()> {
+        // The tree of a new owner will reflect its signals, and the ones of the previous owner
+        // don't matter anymore.
+        if self.pending_owner.is_some() {
+            return Ok(());
+        }
+        // Until we have a tree (e.g. while the destination has no owner), there is nothing to
+        // update.
+        let since = match self.since {
+            Some(since) if msg.recv_position() > since => since,
+            _ => return Ok(()),
+        };
+        trace!("Signal received after tree fetched at {since:?}: {msg:?}");
+        let header = msg.header();
+        if let Some(owner) = &self.owner {
+            if header.sender() != Some(owner.inner()) {
+                return Ok(());
+            }
+        }
+        let (Some(interface), Some(member), Some(path)) =
+            (header.interface(), header.member(), header.path())
+        else {
+            return Ok(());
+        };
+
+        let event = match (interface.as_str(), member.as_str()) {
+            (OBJECT_MANAGER_INTERFACE, "InterfacesAdded") if *path == *self.path => {
+                let (path, interfaces): (OwnedObjectPath, ObjectInterfaces) =
+                    msg.body().deserialize()?;
+                self.objects
+                    .write()
+                    .expect("lock poisoned")
+                    .entry(path.clone())
+                    .or_default()
+                    .extend(interfaces.clone());
+
+                Event::InterfacesAdded { path, interfaces }
+            }
+            (OBJECT_MANAGER_INTERFACE, "InterfacesRemoved") if *path == *self.path => {
+                let (path, interfaces): (OwnedObjectPath, Vec<OwnedInterfaceName>) =
+                    msg.body().deserialize()?;
+                let mut objects = self.objects.write().expect("lock poisoned");
+                if let Some(object) = objects.get_mut(&path) {
+                    for interface in &interfaces {
+                        object.remove(interface);
+                    }
+                    if object.is_empty() {
+                        objects.remove(&path);
+                    }
+                }
+
+                Event::InterfacesRemoved { path, interfaces }
+            }
+            (PROPERTIES_INTERFACE, "PropertiesChanged") => {
+                let (interface, changed, invalidated): (
+                    OwnedInterfaceName,
+                    HashMap<String, OwnedValue>,
+                    Vec<String>,
+                ) = msg.body().deserialize()?;
+                let path = OwnedObjectPath::from(path.to_owned());
+                {
+                    let mut objects = self.objects.write().expect("lock poisoned");
+                    // Only interfaces on managed objects are of interest.
+                    let Some(properties) = objects
+                        .get_mut(&path)
+                        .and_then(|object| object.get_mut(&interface))
+                    else {
+                        return Ok(());
+                    };
+                    properties.extend(changed.clone());
+                    for name in &invalidated {
+                        properties.remove(name);
+                    }
+                }
+
+                Event::PropertiesChanged {
+                    path,
+                    interface,
+                    changed,
+                    invalidated,
+                }
+            }
+            _ => return Ok(()),
+        };
+        self.emit(event).await;
+
+        Ok(())
+    }
+
+    async fn emit(&self, event: Event) {
+        if let Some(events) = &self.events {
+            // An error only means nobody is listening.
+            let _ = events.broadcast(event).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use ntest::timeout;
+    use std::collections::HashMap;
+    #[cfg(not(feature = "tokio"))]
+    use std::os::unix::net::UnixStream;
+    use test_log::test;
+    #[cfg(feature = "tokio")]
+    use tokio::net::UnixStream;
+    use zvariant::{ObjectPath, OwnedValue};
+
+    use super::{Event, ObjectManagerClient};
+    use crate::{
+        connection, dbus_interface,
+        fdo::{self, ManagedObjects, ObjectManager, RequestNameFlags},
+        object_server::Interface,
+        Guid, Result,
+    };
+
+    struct Device {
+        level: u8,
+    }
+
+    #[dbus_interface(name = "org.zbus.ObjectManagerClientTest.Device")]
+    impl Device {
+        #[dbus_interface(property)]
+        fn level(&self) -> u8 {
+            self.level
+        }
+
+        #[dbus_interface(property)]
+        fn set_level(&mut self, level: u8) {
+            self.level = level;
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn object_manager_client() {
+        crate::utils::block_on(test_object_manager_client()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_object_manager_client() -> Result<()> {
+        let guid = Guid::generate();
+        let (p0, p1) = UnixStream::pair().unwrap();
+        let (service, client) = futures_util::try_join!(
+            connection::Builder::unix_stream(p0)
+                .server(&guid)
+                .p2p()
+                .serve_at("/org/zbus/test", ObjectManager)?
+                .serve_at("/org/zbus/test/dev0", Device { level: 0 })?
+                .build(),
+            connection::Builder::unix_stream(p1).p2p().build(),
+        )?;
+
+        let manager = ObjectManagerClient::new(&client, ":1.0", "/org/zbus/test").await?;
+        let dev0 = ObjectPath::try_from("/org/zbus/test/dev0")?;
+        let dev1 = ObjectPath::try_from("/org/zbus/test/dev1")?;
+        let objects = manager.managed_objects();
+        assert_eq!(objects.len(), 1);
+        let dev0_ifaces = manager.object(&dev0).unwrap();
+        let props = &dev0_ifaces[Device::name().as_str()];
+        assert_eq!(props["Level"], OwnedValue::from(0u8));
+
+        let mut events = manager.receive_events();
+
+        // Adding an object.
+        service
+            .object_server()
+            .at("/org/zbus/test/dev1", Device { level: 5 })
+            .await?;
+        match events.next().await.unwrap() {
+            Event::InterfacesAdded { path, interfaces } => {
+                assert_eq!(path, dev1.clone().into());
+                let props = &interfaces[Device::name().as_str()];
+                assert_eq!(props["Level"], OwnedValue::from(5u8));
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+        assert!(manager.object(&dev1).is_some());
+
+        // Changing a property.
+        let iface = service
+            .object_server()
+            .interface::<_, Device>(&dev0)
+            .await?;
+        iface.get_mut().await.level = 42;
+        iface
+            .get()
+            .await
+            .level_changed(iface.signal_context())
+            .await?;
+        match events.next().await.unwrap() {
+            Event::PropertiesChanged {
+                path,
+                interface,
+                changed,
+                invalidated,
+            } => {
+                assert_eq!(path, dev0.clone().into());
+                assert_eq!(interface, Device::name());
+                assert_eq!(
+                    changed,
+                    HashMap::from([("Level".to_string(), OwnedValue::from(42u8))])
+                );
+                assert!(invalidated.is_empty());
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+        let dev0_ifaces = manager.object(&dev0).unwrap();
+        let props = &dev0_ifaces[Device::name().as_str()];
+        assert_eq!(props["Level"], OwnedValue::from(42u8));
+
+        // Removing an object.
+        service
+            .object_server()
+            .remove::<Device, _>("/org/zbus/test/dev0")
+            .await?;
+        match events.next().await.unwrap() {
+            Event::InterfacesRemoved { path, interfaces } => {
+                assert_eq!(path, dev0.clone().into());
+                assert!(interfaces.contains(&Device::name().into()));
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+        assert!(manager.object(&dev0).is_none());
+        assert_eq!(manager.managed_objects().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn object_manager_client_owner_change() {
+        crate::utils::block_on(test_object_manager_client_owner_change()).unwrap();
+    }
+
+    async fn test_object_manager_client_owner_change() -> Result<()> {
+        let name = "org.zbus.ObjectManagerClientTest";
+        let service = || async {
+            let conn = connection::Builder::session()?
+                .serve_at("/org/zbus/test/dev0", Device { level: 0 })?
+                .build()
+                .await?;
+            conn.object_server()
+                .at("/org/zbus/test", ObjectManager)
+                .await?;
+            conn.request_name(name).await?;
+
+            Result::Ok(conn)
+        };
+        let service_conn = service().await?;
+        let client = connection::Builder::session()?.build().await?;
+        let manager = ObjectManagerClient::new(&client, name, "/org/zbus/test").await?;
+        let dev0 = ObjectPath::try_from("/org/zbus/test/dev0")?;
+        assert!(manager.object(&dev0).is_some());
+        let mut events = manager.receive_events();
+
+        // Objects go away with the owner of the name.
+        drop(service_conn);
+        match events.next().await.unwrap() {
+            Event::InterfacesRemoved { path, .. } => assert_eq!(path, dev0.clone().into()),
+            event => panic!("unexpected event: {event:?}"),
+        }
+        assert!(manager.managed_objects().is_empty());
+
+        // ..and come back with the new one.
+        let _service_conn = service().await?;
+        match events.next().await.unwrap() {
+            Event::InterfacesAdded { path, .. } => assert_eq!(path, dev0.clone().into()),
+            event => panic!("unexpected event: {event:?}"),
+        }
+        assert!(manager.object(&dev0).is_some());
+
+        Ok(())
+    }
+
+    // An object manager failing its first `GetManagedObjects` call.
+    struct FlakyObjectManager {
+        calls: usize,
+        objects: ManagedObjects,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.DBus.ObjectManager")]
+    impl FlakyObjectManager {
+        fn get_managed_objects(&mut self) -> fdo::Result<ManagedObjects> {
+            self.calls += 1;
+            if self.calls == 1 {
+                return Err(fdo::Error::Failed("Not ready yet".to_string()));
+            }
+
+            Ok(self.objects.clone())
+        }
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn object_manager_client_fetch_retry() {
+        crate::utils::block_on(test_object_manager_client_fetch_retry()).unwrap();
+    }
+
+    async fn test_object_manager_client_fetch_retry() -> Result<()> {
+        let name = "org.zbus.ObjectManagerClientRetryTest";
+        let dev0 = ObjectPath::try_from("/org/zbus/test/dev0")?;
+        let dev1 = ObjectPath::try_from("/org/zbus/test/dev1")?;
+        let old_service = connection::Builder::session()?
+            .serve_at(&dev0, Device { level: 0 })?
+            .build()
+            .await?;
+        old_service
+            .object_server()
+            .at("/org/zbus/test", ObjectManager)
+            .await?;
+        old_service
+            .request_name_with_flags(name, RequestNameFlags::AllowReplacement.into())
+            .await?;
+        let client = connection::Builder::session()?.build().await?;
+        let manager = ObjectManagerClient::new(&client, name, "/org/zbus/test").await?;
+        assert!(manager.object(&dev0).is_some());
+        let mut events = manager.receive_events();
+
+        // The name is taken over by a service that can't list its objects right away.
+        let level = HashMap::from([("Level".to_string(), OwnedValue::from(1u8))]);
+        let objects = ManagedObjects::from([(
+            dev1.clone().into(),
+            HashMap::from([(Device::name().into(), level)]),
+        )]);
+        let new_service = connection::Builder::session()?
+            .serve_at("/org/zbus/test", FlakyObjectManager { calls: 0, objects })?
+            .build()
+            .await?;
+        new_service
+            .request_name_with_flags(name, RequestNameFlags::ReplaceExisting.into())
+            .await?;
+
+        // The objects of the previous owner are only replaced once the fetch is retried.
+        match events.next().await.unwrap() {
+            Event::InterfacesRemoved { path, .. } => assert_eq!(path, dev0.clone().into()),
+            event => panic!("unexpected event: {event:?}"),
+        }
+        match events.next().await.unwrap() {
+            Event::InterfacesAdded { path, .. } => assert_eq!(path, dev1.clone().into()),
+            event => panic!("unexpected event: {event:?}"),
+        }
+        assert!(manager.object(&dev0).is_none());
+        assert!(manager.object(&dev1).is_some());
+        let iface = new_service
+            .object_server()
+            .interface::<_, FlakyObjectManager>("/org/zbus/test")
+            .await?;
+        assert_eq!(iface.get().await.calls, 2);
+
+        Ok(())
+    }
+}
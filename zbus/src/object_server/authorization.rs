@@ -0,0 +1,223 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+};
+use zbus_names::{InterfaceName, MemberName, OwnedUniqueName};
+
+use crate::{
+    async_lock::Mutex,
+    fdo::{self, ConnectionCredentials, DBusProxy},
+    message::{Header, Message},
+    CacheProperties, Connection,
+};
+
+/// The outcome of an [`Authorizer`] check.
+pub enum Authorization<'a> {
+    /// The method call is allowed.
+    Allow,
+
+    /// The method call is denied. An `org.freedesktop.DBus.Error.AccessDenied` error is returned to
+    /// the caller.
+    Deny,
+
+    /// The decision will be made by this future, allowing the call if it resolves to `true`.
+    Async(Pin<Box<dyn Future<Output = bool> + Send + 'a>>),
+}
+
+/// A method call to be authorized by an [`Authorizer`].
+#[derive(Debug)]
+pub struct AuthorizationRequest<'r> {
+    message: &'r Message,
+    header: &'r Header<'r>,
+    credentials: &'r ConnectionCredentials,
+    interface: &'r InterfaceName<'r>,
+    member: &'r MemberName<'r>,
+}
+
+impl<'r> AuthorizationRequest<'r> {
+    /// The method call message.
+    pub fn message(&self) -> &Message {
+        self.message
+    }
+
+    /// The header of the method call message.
+    pub fn header(&self) -> &Header<'_> {
+        self.header
+    }
+
+    /// The credentials of the caller.
+    ///
+    /// On a bus connection, these are the credentials of the sender of the message, as reported by
+    /// the bus. On a peer-to-peer connection, these are the credentials of the peer.
+    pub fn credentials(&self) -> &ConnectionCredentials {
+        self.credentials
+    }
+
+    /// The interface being called.
+    pub fn interface(&self) -> &InterfaceName<'_> {
+        self.interface
+    }
+
+    /// The method being called.
+    pub fn member(&self) -> &MemberName<'_> {
+        self.member
+    }
+}
+
+/// A policy deciding which method calls the [`ObjectServer`] dispatches.
+///
+/// Set an authorizer through [`ObjectServer::set_authorizer`] or
+/// [`connection::Builder::authorizer`]. Before dispatching a method call to an interface, the
+/// object server asks the authorizer whether the call is allowed, passing it the call and the
+/// credentials of the caller. Denied calls get an `org.freedesktop.DBus.Error.AccessDenied` error
+/// reply.
+///
+/// All method calls are checked, including the ones to the standard interfaces (e.g
+/// `org.freedesktop.DBus.Properties`), except for the methods marked as `unprivileged` in
+/// [`dbus_interface`]. Calls to objects, interfaces or methods that don't exist aren't checked,
+/// they get the corresponding `Unknown*` error as usual.
+///
+/// # Example
+///
+/// Only allowing calls from the same user:
+///
+/// ```no_run
+///# use std::error::Error;
+///# zbus::block_on(async {
+/// use zbus::{
+///     connection::Builder,
+///     object_server::{Authorization, AuthorizationRequest, Authorizer},
+/// };
+///
+/// struct SameUser(u32);
+///
+/// impl Authorizer for SameUser {
+///     fn authorize<'a>(&'a self, request: &'a AuthorizationRequest<'a>) -> Authorization<'a> {
+///         match request.credentials().unix_user_id() {
+///             Some(uid) if uid == self.0 => Authorization::Allow,
+///             _ => Authorization::Deny,
+///         }
+///     }
+/// }
+///
+/// let _conn = Builder::session()?
+///     .authorizer(SameUser(1000))
+///     .name("org.myservice.Example")?
+///     .build()
+///     .await?;
+///# Ok::<(), Box<dyn Error + Send + Sync>>(())
+///# }).unwrap();
+/// ```
+///
+/// [`ObjectServer`]: crate::ObjectServer
+/// [`ObjectServer::set_authorizer`]: crate::ObjectServer::set_authorizer
+/// [`connection::Builder::authorizer`]: crate::connection::Builder::authorizer
+/// [`dbus_interface`]: macro@crate::dbus_interface
+pub trait Authorizer: Send + Sync + 'static {
+    /// Decide whether the method call described by `request` is allowed.
+    fn authorize<'a>(&'a self, request: &'a AuthorizationRequest<'a>) -> Authorization<'a>;
+}
+
+// Unique names are never reused on a bus so we only need to bound the cache.
+const MAX_CACHED_CREDENTIALS: usize = 64;
+
+/// Credentials of the peers, as reported by the bus.
+#[derive(Debug, Default)]
+pub(crate) struct CredentialsCache {
+    credentials: HashMap<Option<OwnedUniqueName>, Arc<ConnectionCredentials>>,
+    order: VecDeque<Option<OwnedUniqueName>>,
+}
+
+impl CredentialsCache {
+    fn get(&self, sender: &Option<OwnedUniqueName>) -> Option<Arc<ConnectionCredentials>> {
+        self.credentials.get(sender).cloned()
+    }
+
+    fn insert(&mut self, sender: Option<OwnedUniqueName>, credentials: Arc<ConnectionCredentials>) {
+        if self
+            .credentials
+            .insert(sender.clone(), credentials)
+            .is_none()
+        {
+            self.order.push_back(sender);
+        }
+        while self.order.len() > MAX_CACHED_CREDENTIALS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.credentials.remove(&oldest);
+            }
+        }
+    }
+}
+
+pub(crate) async fn authorize(
+    authorizer: &dyn Authorizer,
+    cache: &Mutex<CredentialsCache>,
+    conn: &Connection,
+    msg: &Message,
+) -> fdo::Result<()> {
+    let hdr = msg.header();
+    let (interface, member) = match (hdr.interface(), hdr.member()) {
+        (Some(interface), Some(member)) => (interface, member),
+        _ => return Err(fdo::Error::Failed("Missing interface or member".into())),
+    };
+    let credentials = sender_credentials(cache, conn, &hdr).await.map_err(|e| {
+        fdo::Error::AccessDenied(format!("Failed to get the credentials of the caller: {e}"))
+    })?;
+    let request = AuthorizationRequest {
+        message: msg,
+        header: &hdr,
+        credentials: &credentials,
+        interface,
+        member,
+    };
+    let allowed = match authorizer.authorize(&request) {
+        Authorization::Allow => true,
+        Authorization::Deny => false,
+        Authorization::Async(check) => check.await,
+    };
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(fdo::Error::AccessDenied(format!(
+            "Access denied to `{interface}.{member}`"
+        )))
+    }
+}
+
+async fn sender_credentials(
+    cache: &Mutex<CredentialsCache>,
+    conn: &Connection,
+    hdr: &Header<'_>,
+) -> crate::Result<Arc<ConnectionCredentials>> {
+    // The peer is the sender on a p2p connection.
+    let sender = if conn.is_bus() {
+        match hdr.sender() {
+            Some(sender) => Some(OwnedUniqueName::from(sender.to_owned())),
+            None => return Err(crate::Error::MissingField),
+        }
+    } else {
+        None
+    };
+    if let Some(credentials) = cache.lock().await.get(&sender) {
+        return Ok(credentials);
+    }
+
+    let credentials = match &sender {
+        Some(sender) => {
+            DBusProxy::builder(conn)
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?
+                .get_connection_credentials(sender.as_ref().into())
+                .await?
+        }
+        None => conn.peer_credentials().await?,
+    };
+    let credentials = Arc::new(credentials);
+    cache.lock().await.insert(sender, credentials.clone());
+
+    Ok(credentials)
+}
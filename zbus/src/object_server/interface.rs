@@ -101,6 +101,26 @@ pub trait Interface: Any + Send + Sync {
         ctxt: &SignalContext<'_>,
     ) -> Option<fdo::Result<()>>;
 
+    /// Whether the interface has a method named `name`.
+    ///
+    /// The default implementation returns `true`, leaving it to `call` and `call_mut` to report
+    /// unknown methods.
+    fn has_method(&self, name: &MemberName<'_>) -> bool {
+        let _ = name;
+        true
+    }
+
+    /// Whether calls to the method `name` need to be checked by the [`Authorizer`] of the object
+    /// server, if one is set.
+    ///
+    /// The default implementation returns `true`.
+    ///
+    /// [`Authorizer`]: crate::object_server::Authorizer
+    fn requires_authorization(&self, name: &MemberName<'_>) -> bool {
+        let _ = name;
+        true
+    }
+
     /// Call a method.
     ///
     /// Return [`DispatchResult::NotFound`] if the method doesn't exist, or
@@ -0,0 +1,152 @@
+use serde::Serialize;
+use static_assertions::assert_impl_all;
+use std::marker::PhantomData;
+use tracing::{debug, trace, trace_span, Instrument};
+use zvariant::DynamicType;
+
+use crate::{
+    fdo,
+    message::{Flags, Message},
+    Connection, DBusError, Result,
+};
+
+/// A handle to reply to a method call at a later point.
+///
+/// Interface methods receive this handle through an argument marked with the
+/// `#[zbus(reply_handle)]` attribute (see [`dbus_interface`]). Such methods don't reply to the call
+/// by returning: the reply is sent through [`MethodReply::reply`] or
+/// [`MethodReply::reply_error`] instead. The handle can be stored in the interface, moved to
+/// another task or passed to another method call, so the interface is not kept locked while the
+/// reply is pending.
+///
+/// `T` is the type of the reply body. It's also used to describe the method's out arguments in the
+/// introspection data.
+///
+/// If the handle is dropped without replying, an error is sent to the caller. By default, it's
+/// [`fdo::Error::NoReply`]. Use [`MethodReply::set_drop_error`] to send a different error.
+///
+/// If the caller doesn't expect a reply, the reply methods do nothing.
+///
+/// [`dbus_interface`]: macro@crate::dbus_interface
+#[derive(derivative::Derivative)]
+#[derivative(Debug(bound = ""))]
+#[must_use = "the caller gets an error if the handle is dropped without replying"]
+pub struct MethodReply<T> {
+    conn: Connection,
+    call: Message,
+    #[derivative(Debug = "ignore")]
+    drop_reply: Option<Message>,
+    replied: bool,
+    #[derivative(Debug = "ignore")]
+    phantom: PhantomData<fn(T)>,
+}
+
+assert_impl_all!(MethodReply<()>: Send, Sync, Unpin);
+
+impl<T> MethodReply<T> {
+    /// Create a handle for replying to `call`, received on `conn`.
+    ///
+    /// This is used by the [`dbus_interface`] macro and should not need to be called directly.
+    ///
+    /// [`dbus_interface`]: macro@crate::dbus_interface
+    #[doc(hidden)]
+    pub fn new(conn: &Connection, call: &Message) -> Self {
+        Self {
+            conn: conn.clone(),
+            call: call.clone(),
+            drop_reply: None,
+            replied: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The method call to reply to.
+    pub fn call(&self) -> &Message {
+        &self.call
+    }
+
+    /// The connection the method call was received on.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Set the error to send to the caller if the handle is dropped without replying.
+    pub fn set_drop_error<E>(&mut self, error: E) -> Result<()>
+    where
+        E: DBusError,
+    {
+        self.drop_reply = Some(error.create_reply(&self.call.header())?);
+
+        Ok(())
+    }
+
+    /// Reply to the method call with `body`.
+    pub async fn reply(mut self, body: &T) -> Result<()>
+    where
+        T: Serialize + DynamicType,
+    {
+        self.replied = true;
+        if !self.reply_expected() {
+            return Ok(());
+        }
+
+        self.conn.reply(&self.call, body).await
+    }
+
+    /// Reply to the method call with an error.
+    pub async fn reply_error<E>(mut self, error: E) -> Result<()>
+    where
+        E: DBusError,
+    {
+        self.replied = true;
+        if !self.reply_expected() {
+            return Ok(());
+        }
+
+        self.conn.reply_dbus_error(&self.call.header(), error).await
+    }
+
+    fn reply_expected(&self) -> bool {
+        let expected = !self
+            .call
+            .primary_header()
+            .flags()
+            .contains(Flags::NoReplyExpected);
+        if !expected {
+            trace!("No reply expected for {:?} by the caller.", self.call);
+        }
+
+        expected
+    }
+}
+
+impl<T> Drop for MethodReply<T> {
+    fn drop(&mut self) {
+        if self.replied || !self.reply_expected() {
+            return;
+        }
+
+        let reply = match self.drop_reply.take() {
+            Some(reply) => Ok(reply),
+            None => fdo::Error::NoReply("The method call was dropped without a reply".to_string())
+                .create_reply(&self.call.header()),
+        };
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(e) => {
+                debug!("Failed to create the error reply for a dropped method call: {e}");
+
+                return;
+            }
+        };
+        let conn = self.conn.clone();
+        let task_name = "Reply to dropped method call";
+        let send = async move {
+            if let Err(e) = conn.send(&reply).await {
+                debug!("Failed to send the error reply for a dropped method call: {e}");
+            }
+        }
+        .instrument(trace_span!("{}", task_name));
+        self.conn.executor().spawn(send, task_name).detach();
+    }
+}
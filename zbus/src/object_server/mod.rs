@@ -12,11 +12,11 @@ use std::{
 use tracing::{debug, instrument, trace};
 
 use static_assertions::assert_impl_all;
-use zbus_names::InterfaceName;
+use zbus_names::{InterfaceName, OwnedInterfaceName};
 use zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Signature, Type, Value};
 
 use crate::{
-    async_lock::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    async_lock::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
     connection::WeakConnection,
     fdo,
     fdo::{Introspectable, ManagedObjects, ObjectManager, Peer, Properties},
@@ -27,9 +27,19 @@ use crate::{
 mod interface;
 pub use interface::{DispatchResult, Interface};
 
+mod method_reply;
+pub use method_reply::MethodReply;
+
+mod authorization;
+use authorization::CredentialsCache;
+pub use authorization::{Authorization, AuthorizationRequest, Authorizer};
+
 mod signal_context;
 pub use signal_context::SignalContext;
 
+mod subtree;
+pub use subtree::{SubtreeHandler, SubtreeObject};
+
 /// Opaque structure that derefs to an `Interface` type.
 pub struct InterfaceDeref<'d, I> {
     iface: RwLockReadGuard<'d, dyn Interface>,
@@ -187,6 +197,25 @@ pub(crate) struct Node {
     children: HashMap<String, Node>,
     #[derivative(Debug = "ignore")]
     interfaces: HashMap<InterfaceName<'static>, Arc<RwLock<dyn Interface>>>,
+    #[derivative(Debug = "ignore")]
+    subtree: Option<Arc<dyn SubtreeHandler>>,
+}
+
+// A node, either from the tree or resolved through a `SubtreeHandler`.
+pub(crate) enum NodeRef<'n> {
+    Static(&'n Node),
+    Resolved(Node),
+}
+
+impl Deref for NodeRef<'_> {
+    type Target = Node;
+
+    fn deref(&self) -> &Node {
+        match self {
+            NodeRef::Static(node) => node,
+            NodeRef::Resolved(node) => node,
+        }
+    }
 }
 
 impl Node {
@@ -221,6 +250,45 @@ impl Node {
         Some(node)
     }
 
+    // Get the child Node at path, resolving it through the closest `SubtreeHandler` above it if
+    // it's not in the tree.
+    pub(crate) async fn resolve(&self, path: &ObjectPath<'_>) -> Option<NodeRef<'_>> {
+        let mut node = self;
+        let mut handler = self.subtree.as_ref();
+
+        for i in path.split('/').skip(1) {
+            if i.is_empty() {
+                continue;
+            }
+            match node.children.get(i) {
+                Some(n) => {
+                    node = n;
+                    if n.subtree.is_some() {
+                        handler = n.subtree.as_ref();
+                    }
+                }
+                None => {
+                    let handler = handler?.clone();
+
+                    return Node::from_subtree(handler, path.to_owned().into())
+                        .await
+                        .map(NodeRef::Resolved);
+                }
+            }
+        }
+
+        Some(NodeRef::Static(node))
+    }
+
+    async fn from_subtree(handler: Arc<dyn SubtreeHandler>, path: OwnedObjectPath) -> Option<Self> {
+        let object = handler.object(&path).await?;
+        let mut node = Node::new(path);
+        node.interfaces.extend(object.interfaces);
+        node.subtree = Some(handler);
+
+        Some(node)
+    }
+
     // Get the child Node at path. Optionally create one if it doesn't exist.
     // It also returns the path of parent node that implements ObjectManager (if any). If multiple
     // parents implement it (they shouldn't), then the closest one is returned.
@@ -271,12 +339,13 @@ impl Node {
     }
 
     fn is_empty(&self) -> bool {
-        !self.interfaces.keys().any(|k| {
-            *k != Peer::name()
-                && *k != Introspectable::name()
-                && *k != Properties::name()
-                && *k != ObjectManager::name()
-        })
+        self.subtree.is_none()
+            && !self.interfaces.keys().any(|k| {
+                *k != Peer::name()
+                    && *k != Introspectable::name()
+                    && *k != Properties::name()
+                    && *k != ObjectManager::name()
+            })
     }
 
     fn remove_node(&mut self, node: &str) -> bool {
@@ -355,6 +424,22 @@ impl Node {
                     for iface in node.interfaces.values() {
                         iface.read().await.introspect_to_writer(writer, level + 2);
                     }
+
+                    if let Some(handler) = &node.subtree {
+                        for child in handler.children(&node.path).await {
+                            if node.children.contains_key(&child) {
+                                continue;
+                            }
+                            writeln!(
+                                writer,
+                                "{:indent$}<node name=\"{}\"/>",
+                                "",
+                                child,
+                                indent = level + 2
+                            )
+                            .unwrap();
+                        }
+                    }
                 }
                 Fragment::End { level } => {
                     writeln!(writer, "{:indent$}</node>", "", indent = level).unwrap();
@@ -377,24 +462,69 @@ impl Node {
         // Recursively get all properties of all interfaces of descendants.
         let mut node_list: Vec<_> = self.children.values().collect();
         while let Some(node) = node_list.pop() {
-            let mut interfaces = HashMap::new();
-            for iface_name in node.interfaces.keys().filter(|n| {
-                // Filter standard interfaces.
-                *n != &Peer::name()
-                    && *n != &Introspectable::name()
-                    && *n != &Properties::name()
-                    && *n != &ObjectManager::name()
-            }) {
-                let props = node.get_properties(iface_name.clone()).await;
-                interfaces.insert(iface_name.clone().into(), props);
-            }
+            let interfaces = node.get_all_properties().await;
             managed_objects.insert(node.path.clone(), interfaces);
             node_list.extend(node.children.values());
         }
 
+        // Now the objects served by subtree handlers, anywhere in the tree.
+        let mut node_list = vec![self];
+        while let Some(node) = node_list.pop() {
+            node_list.extend(node.children.values());
+            let handler = match &node.subtree {
+                Some(handler) => handler,
+                None => continue,
+            };
+
+            let mut paths = vec![node.path.clone()];
+            while let Some(path) = paths.pop() {
+                for child in handler.children(&path).await {
+                    let child_path = if path.as_str() == "/" {
+                        format!("/{child}")
+                    } else {
+                        format!("{path}/{child}")
+                    };
+                    let child_path = match OwnedObjectPath::try_from(child_path) {
+                        Ok(child_path) => child_path,
+                        Err(e) => {
+                            debug!("Invalid child `{child}` of `{path}`: {e}");
+
+                            continue;
+                        }
+                    };
+                    paths.push(child_path.clone());
+                    // Objects in the tree take precedence.
+                    if managed_objects.contains_key(&child_path) {
+                        continue;
+                    }
+                    if let Some(child) = Node::from_subtree(handler.clone(), child_path).await {
+                        let interfaces = child.get_all_properties().await;
+                        managed_objects.insert(child.path, interfaces);
+                    }
+                }
+            }
+        }
+
         managed_objects
     }
 
+    // Get all properties of all the non-standard interfaces.
+    async fn get_all_properties(&self) -> HashMap<OwnedInterfaceName, HashMap<String, OwnedValue>> {
+        let mut interfaces = HashMap::new();
+        for iface_name in self.interfaces.keys().filter(|n| {
+            // Filter standard interfaces.
+            *n != &Peer::name()
+                && *n != &Introspectable::name()
+                && *n != &Properties::name()
+                && *n != &ObjectManager::name()
+        }) {
+            let props = self.get_properties(iface_name.clone()).await;
+            interfaces.insert(iface_name.clone().into(), props);
+        }
+
+        interfaces
+    }
+
     async fn get_properties(
         &self,
         interface_name: InterfaceName<'_>,
@@ -465,10 +595,14 @@ impl Node {
 /// # })?;
 /// # Ok::<_, Box<dyn Error + Send + Sync>>(())
 /// ```
-#[derive(Debug)]
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
 pub struct ObjectServer {
     conn: WeakConnection,
     root: RwLock<Node>,
+    #[derivative(Debug = "ignore")]
+    authorizer: RwLock<Option<Arc<dyn Authorizer>>>,
+    credentials: Mutex<CredentialsCache>,
 }
 
 assert_impl_all!(ObjectServer: Send, Sync, Unpin);
@@ -479,6 +613,8 @@ impl ObjectServer {
         Self {
             conn: conn.into(),
             root: RwLock::new(Node::new("/".try_into().expect("zvariant bug"))),
+            authorizer: RwLock::new(None),
+            credentials: Mutex::new(CredentialsCache::default()),
         }
     }
 
@@ -597,6 +733,135 @@ impl ObjectServer {
         Ok(false)
     }
 
+    /// Register a [`SubtreeHandler`] serving the objects below a given path.
+    ///
+    /// Objects registered through [`ObjectServer::at`] take precedence over the ones resolved by
+    /// the handler. If multiple handlers are registered on the same branch, the one closest to the
+    /// requested object is used.
+    ///
+    /// If a handler is already registered at this path, returns false.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// use zbus::{
+    ///     dbus_interface,
+    ///     object_server::{SubtreeHandler, SubtreeObject},
+    ///     zvariant::ObjectPath,
+    ///     Connection,
+    /// };
+    ///
+    /// struct Row(u32);
+    ///
+    /// #[dbus_interface(name = "org.myiface.Row")]
+    /// impl Row {
+    ///     #[dbus_interface(property)]
+    ///     fn id(&self) -> u32 {
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// struct Rows;
+    ///
+    /// #[zbus::export::async_trait::async_trait]
+    /// impl SubtreeHandler for Rows {
+    ///     async fn object(&self, path: &ObjectPath<'_>) -> Option<SubtreeObject> {
+    ///         let id = path.strip_prefix("/org/zbus/rows/")?.parse().ok()?;
+    ///
+    ///         (id < 10_000).then(|| SubtreeObject::new().with_interface(Row(id)))
+    ///     }
+    ///
+    ///     async fn children(&self, path: &ObjectPath<'_>) -> Vec<String> {
+    ///         match path.as_str() {
+    ///             "/org/zbus/rows" => (0..10_000).map(|id| id.to_string()).collect(),
+    ///             _ => vec![],
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// # zbus::block_on(async {
+    /// let connection = Connection::session().await?;
+    /// connection
+    ///     .object_server()
+    ///     .at_subtree("/org/zbus/rows", Rows)
+    ///     .await?;
+    /// # Ok::<_, Box<dyn Error + Send + Sync>>(())
+    /// # })?;
+    /// # Ok::<_, Box<dyn Error + Send + Sync>>(())
+    /// ```
+    pub async fn at_subtree<'p, P, H>(&self, path: P, handler: H) -> Result<bool>
+    where
+        H: SubtreeHandler,
+        P: TryInto<ObjectPath<'p>>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let mut root = self.root.write().await;
+        let node = root.get_child_mut(&path, true).0.unwrap();
+        if node.subtree.is_some() {
+            return Ok(false);
+        }
+        node.subtree = Some(Arc::new(handler));
+
+        Ok(true)
+    }
+
+    /// Set the [`Authorizer`] checking the method calls before they're dispatched.
+    ///
+    /// This replaces the previously set authorizer, if any. To ensure no call gets through
+    /// unchecked, set the authorizer before the interfaces are registered or use
+    /// [`zbus::connection::Builder::authorizer`].
+    pub async fn set_authorizer<A>(&self, authorizer: A)
+    where
+        A: Authorizer,
+    {
+        self.set_authorizer_ready(Arc::new(authorizer)).await
+    }
+
+    /// Same as `set_authorizer` but expects an authorizer already in `Arc<dyn Authorizer>` form.
+    pub(crate) async fn set_authorizer_ready(&self, authorizer: Arc<dyn Authorizer>) {
+        *self.authorizer.write().await = Some(authorizer);
+    }
+
+    /// Remove the [`Authorizer`], if any.
+    ///
+    /// All method calls are dispatched without any checks after this.
+    pub async fn remove_authorizer(&self) {
+        *self.authorizer.write().await = None;
+    }
+
+    /// Unregister the [`SubtreeHandler`] at a given path.
+    ///
+    /// Returns whether a handler was registered at this path.
+    pub async fn remove_subtree<'p, P>(&self, path: P) -> Result<bool>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let mut root = self.root.write().await;
+        let node = match root.get_child_mut(&path, false).0 {
+            Some(node) => node,
+            None => return Ok(false),
+        };
+        let removed = node.subtree.take().is_some();
+        if node.is_empty() && node.children.is_empty() {
+            let mut path_parts = path.rsplit('/').filter(|i| !i.is_empty());
+            if let Some(last_part) = path_parts.next() {
+                let ppath = ObjectPath::from_string_unchecked(
+                    path_parts.fold(String::new(), |a, p| format!("/{p}{a}")),
+                );
+                root.get_child_mut(&ppath, false)
+                    .0
+                    .unwrap()
+                    .remove_node(last_part);
+            }
+        }
+
+        Ok(removed)
+    }
+
     /// Get the interface at the given path.
     ///
     /// # Errors
@@ -698,7 +963,8 @@ impl ObjectServer {
         let iface = {
             let root = self.root.read().await;
             let node = root
-                .get_child(path)
+                .resolve(path)
+                .await
                 .ok_or_else(|| fdo::Error::UnknownObject(format!("Unknown object '{path}'")))?;
 
             node.interface_lock(iface_name.as_ref()).ok_or_else(|| {
@@ -706,6 +972,26 @@ impl ObjectServer {
             })?
         };
 
+        // Look the method up and authorize the call without holding the interface lock, so a slow
+        // authorization doesn't block the other callers meanwhile. Only the calls to existing
+        // methods are authorized, so the others get the right error.
+        let requires_authorization = {
+            let iface = iface.read().await;
+            if !iface.has_method(member) {
+                return Err(fdo::Error::UnknownMethod(format!(
+                    "Unknown method '{member}'"
+                )));
+            }
+
+            iface.requires_authorization(member)
+        };
+        if requires_authorization {
+            let authorizer = self.authorizer.read().await.clone();
+            if let Some(authorizer) = authorizer {
+                authorization::authorize(&*authorizer, &self.credentials, connection, msg).await?;
+            }
+        }
+
         trace!("acquiring read lock on interface `{}`", iface_name);
         let read_lock = iface.read().await;
         trace!("acquired read lock on interface `{}`", iface_name);
@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use std::{collections::HashMap, sync::Arc};
+use zbus_names::InterfaceName;
+use zvariant::ObjectPath;
+
+use crate::{async_lock::RwLock, object_server::Interface};
+
+/// A handler serving all the objects under a path, resolving them on demand.
+///
+/// Register a handler using [`ObjectServer::at_subtree`]. Whenever a message is addressed to an
+/// object below the handler's path that isn't registered through [`ObjectServer::at`], the
+/// object server asks the handler for its interfaces through [`SubtreeHandler::object`]. This
+/// allows exposing a large number of objects (e.g rows of a database) without having to keep an
+/// [`Interface`] instance around for each of them.
+///
+/// The interfaces returned by the handler only live for the duration of the message being
+/// dispatched. Any state that must outlive a call (including changes made from `&mut self`
+/// methods) needs to be kept elsewhere, typically in a resource shared between the handler and the
+/// interfaces it creates.
+///
+/// Since the object server is locked while the handler is consulted, its methods must not
+/// access the object server.
+///
+/// [`ObjectServer::at_subtree`]: crate::ObjectServer::at_subtree
+/// [`ObjectServer::at`]: crate::ObjectServer::at
+#[async_trait]
+pub trait SubtreeHandler: Send + Sync + 'static {
+    /// Resolve the object at `path`.
+    ///
+    /// Return `None` if there is no object at `path`. The standard interfaces
+    /// (`org.freedesktop.DBus.Properties` etc) are provided for the object by the object server.
+    async fn object(&self, path: &ObjectPath<'_>) -> Option<SubtreeObject>;
+
+    /// The names (i.e the last element of the path) of the direct children of the object at
+    /// `path`.
+    ///
+    /// This is used for introspection and to enumerate the objects in response to
+    /// `org.freedesktop.DBus.ObjectManager.GetManagedObjects`, if an [`ObjectManager`] is
+    /// registered at or above the handler's path.
+    ///
+    /// [`ObjectManager`]: crate::fdo::ObjectManager
+    async fn children(&self, path: &ObjectPath<'_>) -> Vec<String>;
+}
+
+/// The interfaces of an object resolved by a [`SubtreeHandler`].
+#[derive(Default, derivative::Derivative)]
+#[derivative(Debug)]
+pub struct SubtreeObject {
+    #[derivative(Debug = "ignore")]
+    pub(super) interfaces: HashMap<InterfaceName<'static>, Arc<RwLock<dyn Interface>>>,
+}
+
+impl SubtreeObject {
+    /// Create an object with no interfaces.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an interface to the object.
+    ///
+    /// If the object already has an interface with the same name, it's replaced.
+    pub fn with_interface<I>(mut self, iface: I) -> Self
+    where
+        I: Interface,
+    {
+        self.interfaces
+            .insert(I::name(), Arc::new(RwLock::new(iface)));
+
+        self
+    }
+}
@@ -0,0 +1,583 @@
+//! Recording and replaying D-Bus traffic, in the pcap format.
+//!
+//! Captures use the `DLT_DBUS` link type, as written by `dbus-monitor --pcap` and understood by
+//! Wireshark. Each packet is a complete D-Bus message, timestamped with the time it was captured
+//! at.
+//!
+//! * [`Writer`] writes messages to a capture.
+//! * [`Recorder`] records all the messages going through a [`Connection`], when registered as its
+//!   [`Interceptor`].
+//! * [`Reader`] reads the messages back from a capture.
+//!
+//! # Example
+//!
+//! Replaying the method calls of a capture against a service:
+//!
+//! ```no_run
+//!# use std::error::Error;
+//!# zbus::block_on(async {
+//! use std::fs::File;
+//! use zbus::{message::Type, pcap::Reader, Connection};
+//!
+//! let conn = Connection::session().await?;
+//! for record in Reader::new(File::open("calls.pcap")?)? {
+//!     let msg = record?.into_message();
+//!     if msg.message_type() == Type::MethodCall {
+//!         conn.send(&msg).await?;
+//!     }
+//! }
+//!# Ok::<(), Box<dyn Error + Send + Sync>>(())
+//!# }).unwrap();
+//! ```
+//!
+//! [`Connection`]: crate::Connection
+//! [`Interceptor`]: crate::connection::Interceptor
+
+use async_broadcast::{broadcast, Sender};
+use async_trait::async_trait;
+use futures_core::Stream;
+use futures_util::StreamExt;
+use static_assertions::assert_impl_all;
+use std::{
+    io::{self, Read, Write},
+    sync::{mpsc, Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tracing::debug;
+use zvariant::{serialized, EncodingContext};
+
+use crate::{
+    connection::{Interception, Interceptor},
+    message::header::MAX_MESSAGE_SIZE,
+    Error, Message, Result,
+};
+
+// The link type of D-Bus captures.
+const LINKTYPE_DBUS: u32 = 231;
+
+// The magic numbers of captures with microsecond and nanosecond timestamps.
+const MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+
+const HEADER_SIZE: usize = 24;
+const RECORD_HEADER_SIZE: usize = 16;
+
+/// Writes messages to a pcap capture.
+///
+/// The capture header is written on creation.
+#[derive(Debug)]
+pub struct Writer<W> {
+    writer: W,
+}
+
+impl<W> Writer<W>
+where
+    W: Write,
+{
+    /// Start a new capture, written to `writer`.
+    pub fn new(mut writer: W) -> Result<Self> {
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(&MAGIC_MICROS.to_ne_bytes());
+        // Version 2.4.
+        header.extend_from_slice(&2u16.to_ne_bytes());
+        header.extend_from_slice(&4u16.to_ne_bytes());
+        // The timestamps are in UTC, with no accuracy information.
+        header.extend_from_slice(&0i32.to_ne_bytes());
+        header.extend_from_slice(&0u32.to_ne_bytes());
+        header.extend_from_slice(&(MAX_MESSAGE_SIZE as u32).to_ne_bytes());
+        header.extend_from_slice(&LINKTYPE_DBUS.to_ne_bytes());
+        writer.write_all(&header)?;
+
+        Ok(Self { writer })
+    }
+
+    /// Write `msg` to the capture, timestamped with the current time.
+    pub fn write(&mut self, msg: &Message) -> Result<()> {
+        self.write_at(msg, SystemTime::now())
+    }
+
+    /// Write `msg` to the capture, timestamped with `timestamp`.
+    pub fn write_at(&mut self, msg: &Message, timestamp: SystemTime) -> Result<()> {
+        let since_epoch = timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let data = msg.data();
+        let len = data.len() as u32;
+
+        let mut header = Vec::with_capacity(RECORD_HEADER_SIZE);
+        header.extend_from_slice(&(since_epoch.as_secs() as u32).to_ne_bytes());
+        header.extend_from_slice(&since_epoch.subsec_micros().to_ne_bytes());
+        header.extend_from_slice(&len.to_ne_bytes());
+        header.extend_from_slice(&len.to_ne_bytes());
+        self.writer.write_all(&header)?;
+        self.writer.write_all(data.bytes())?;
+
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush().map_err(Into::into)
+    }
+
+    /// The underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Records the messages going through a connection to a pcap capture.
+///
+/// Register the recorder as an [`Interceptor`] of the connection, through
+/// [`connection::Builder::interceptor`], to record all the messages the connection sends and
+/// receives. The recorder can also be used on its own, e.g to record the messages of a
+/// [`MessageStream`], through [`Recorder::record_stream`] or [`Recorder::record`].
+///
+/// Messages are timestamped when they're recorded and queued for a dedicated thread to write them,
+/// so recording never blocks on the writer. The recorder is cheap to clone, all clones writing to
+/// the same capture. Since interceptors can't report errors, failures to write messages are logged
+/// and the first one is returned by the next [`Recorder::flush`]. The thread exits once all clones
+/// are dropped, after writing all the queued messages.
+///
+/// # Example
+///
+/// ```no_run
+///# use std::error::Error;
+///# zbus::block_on(async {
+/// use std::fs::File;
+/// use zbus::{connection::Builder, pcap::Recorder};
+///
+/// let recorder = Recorder::new(File::create("session.pcap")?)?;
+/// let _conn = Builder::session()?
+///     .interceptor(recorder.clone())
+///     .build()
+///     .await?;
+///
+/// // Do something useful with the connection..
+///
+/// recorder.flush().await?;
+///# Ok::<(), Box<dyn Error + Send + Sync>>(())
+///# }).unwrap();
+/// ```
+///
+/// [`connection::Builder::interceptor`]: crate::connection::Builder::interceptor
+/// [`MessageStream`]: crate::MessageStream
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    // `mpsc::Sender` is only `Sync` since Rust 1.72.
+    commands: Arc<Mutex<mpsc::Sender<Command>>>,
+}
+
+assert_impl_all!(Recorder: Send, Sync, Unpin);
+
+#[derive(Debug)]
+enum Command {
+    Record(Message, SystemTime),
+    Flush(Sender<Result<()>>),
+}
+
+impl Recorder {
+    /// Start a new capture, written to `writer`.
+    ///
+    /// The capture header is written right away, the messages from a dedicated thread.
+    pub fn new<W>(writer: W) -> Result<Self>
+    where
+        W: Write + Send + 'static,
+    {
+        let writer = Writer::new(writer)?;
+        let (commands, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("zbus::pcap::Recorder".into())
+            .spawn(move || write_commands(writer, receiver))?;
+
+        Ok(Self {
+            commands: Arc::new(Mutex::new(commands)),
+        })
+    }
+
+    /// Record `msg`, timestamped with the current time.
+    ///
+    /// The message is only queued for writing, see [`Recorder::flush`] to wait for it to be
+    /// written.
+    pub fn record(&self, msg: &Message) -> Result<()> {
+        self.send(Command::Record(msg.clone(), SystemTime::now()))
+    }
+
+    /// Record all the messages of `stream`, as they're yielded.
+    ///
+    /// The messages are yielded unchanged by the returned stream. Errors are passed through and
+    /// failures to record a message are only logged.
+    pub fn record_stream<S>(&self, stream: S) -> impl Stream<Item = Result<Message>>
+    where
+        S: Stream<Item = Result<Message>>,
+    {
+        let recorder = self.clone();
+
+        stream.inspect(move |msg| {
+            if let Ok(msg) = msg {
+                if let Err(e) = recorder.record(msg) {
+                    debug!("Failed to record message: {e}");
+                }
+            }
+        })
+    }
+
+    /// Wait for all the messages recorded so far to be written, and flush the underlying writer.
+    ///
+    /// Returns the first error that happened while writing the messages, if any.
+    pub async fn flush(&self) -> Result<()> {
+        let (sender, mut receiver) = broadcast(1);
+        self.send(Command::Flush(sender))?;
+
+        receiver
+            .recv()
+            .await
+            .unwrap_or_else(|_| Err(recorder_gone()))
+    }
+
+    fn send(&self, command: Command) -> Result<()> {
+        self.commands
+            .lock()
+            .expect("poisoned lock")
+            .send(command)
+            .map_err(|_| recorder_gone())
+    }
+
+    fn intercept(&self, msg: Message) -> Interception {
+        if let Err(e) = self.record(&msg) {
+            debug!("Failed to record message: {e}");
+        }
+
+        Interception::Continue(msg)
+    }
+}
+
+#[async_trait]
+impl Interceptor for Recorder {
+    async fn outgoing(&self, msg: Message) -> Interception {
+        self.intercept(msg)
+    }
+
+    async fn incoming(&self, msg: Message) -> Interception {
+        self.intercept(msg)
+    }
+}
+
+// The loop of the thread of a `Recorder`, running until all senders are dropped.
+fn write_commands<W>(mut writer: Writer<W>, commands: mpsc::Receiver<Command>)
+where
+    W: Write,
+{
+    let mut error = None;
+    for command in commands {
+        match command {
+            Command::Record(msg, timestamp) => {
+                if let Err(e) = writer.write_at(&msg, timestamp) {
+                    debug!("Failed to record message: {e}");
+                    error.get_or_insert(e);
+                }
+            }
+            Command::Flush(reply) => {
+                let res = match error.take() {
+                    Some(e) => Err(e),
+                    None => writer.flush(),
+                };
+                // The capacity is enough for the only reply.
+                let _ = reply.try_broadcast(res);
+            }
+        }
+    }
+
+    if let Err(e) = writer.flush() {
+        debug!("Failed to flush the capture: {e}");
+    }
+}
+
+fn recorder_gone() -> Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "The recorder thread is gone").into()
+}
+
+/// Reads messages from a pcap capture.
+///
+/// The reader is an [`Iterator`] of the [`Record`]s of the capture. Only captures of the `DLT_DBUS`
+/// link type are supported.
+///
+/// Just like the messages zbus receives, only the messages in the native byte order can be read.
+/// Other messages are reported as [`Error::IncorrectEndian`].
+#[derive(Debug)]
+pub struct Reader<R> {
+    reader: R,
+    swapped: bool,
+    nanos: bool,
+    done: bool,
+}
+
+impl<R> Reader<R>
+where
+    R: Read,
+{
+    /// Read the capture from `reader`.
+    ///
+    /// The capture header is read and checked right away.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut header = [0; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+
+        let magic = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]);
+        let (swapped, nanos) = match magic {
+            MAGIC_MICROS => (false, false),
+            MAGIC_NANOS => (false, true),
+            m if m.swap_bytes() == MAGIC_MICROS => (true, false),
+            m if m.swap_bytes() == MAGIC_NANOS => (true, true),
+            _ => return Err(invalid_data("Not a pcap capture")),
+        };
+        let reader = Self {
+            reader,
+            swapped,
+            nanos,
+            done: false,
+        };
+        let link_type = reader.u32_at(&header, 20);
+        if link_type != LINKTYPE_DBUS {
+            return Err(invalid_data(format!(
+                "Unsupported link type {link_type}, expected DLT_DBUS ({LINKTYPE_DBUS})"
+            )));
+        }
+
+        Ok(reader)
+    }
+
+    // Read the next record, returning its timestamp and bytes.
+    fn read_record(&mut self) -> Result<Option<(SystemTime, Vec<u8>)>> {
+        let mut header = [0; RECORD_HEADER_SIZE];
+        let mut pos = 0;
+        while pos < header.len() {
+            match self.reader.read(&mut header[pos..])? {
+                // The capture ends cleanly between records.
+                0 if pos == 0 => return Ok(None),
+                0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                n => pos += n,
+            }
+        }
+
+        let secs = Duration::from_secs(self.u32_at(&header, 0).into());
+        let fraction = self.u32_at(&header, 4).into();
+        let fraction = if self.nanos {
+            Duration::from_nanos(fraction)
+        } else {
+            Duration::from_micros(fraction)
+        };
+        let len = self.u32_at(&header, 8) as usize;
+        let orig_len = self.u32_at(&header, 12) as usize;
+        if len > MAX_MESSAGE_SIZE {
+            return Err(Error::ExcessData);
+        }
+        if len < orig_len {
+            return Err(invalid_data(format!(
+                "Message truncated from {orig_len} to {len} bytes"
+            )));
+        }
+        let mut bytes = vec![0; len];
+        self.reader.read_exact(&mut bytes)?;
+
+        Ok(Some((SystemTime::UNIX_EPOCH + secs + fraction, bytes)))
+    }
+
+    fn u32_at(&self, bytes: &[u8], offset: usize) -> u32 {
+        let bytes = [
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ];
+        let n = u32::from_ne_bytes(bytes);
+
+        if self.swapped {
+            n.swap_bytes()
+        } else {
+            n
+        }
+    }
+}
+
+impl<R> Iterator for Reader<R>
+where
+    R: Read,
+{
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let (timestamp, bytes) = match self.read_record() {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                self.done = true;
+
+                return None;
+            }
+            // The reader is left in an unknown place.
+            Err(e) => {
+                self.done = true;
+
+                return Some(Err(e));
+            }
+        };
+        let ctxt = EncodingContext::<byteorder::NativeEndian>::new_dbus(0);
+        let message = Message::from_raw_parts(serialized::Data::new(bytes, ctxt), 0);
+
+        Some(message.map(|message| Record { timestamp, message }))
+    }
+}
+
+/// A message read from a capture by a [`Reader`].
+#[derive(Debug, Clone)]
+pub struct Record {
+    timestamp: SystemTime,
+    message: Message,
+}
+
+assert_impl_all!(Record: Send, Sync, Unpin);
+
+impl Record {
+    /// The time the message was captured at.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// The message.
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    /// Take the message.
+    pub fn into_message(self) -> Message {
+        self.message
+    }
+}
+
+fn invalid_data<E>(error: E) -> Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use ntest::timeout;
+    use test_log::test;
+
+    use super::*;
+    use crate::MessageStream;
+
+    #[test]
+    fn write_read() {
+        let signal = Message::signal("/org/zbus/Pcap", "org.zbus.Pcap", "Signal")
+            .unwrap()
+            .build(&("pcap", 42u32))
+            .unwrap();
+        let call = Message::method("/org/zbus/Pcap", "Call")
+            .unwrap()
+            .build(&())
+            .unwrap();
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456);
+
+        let mut writer = Writer::new(vec![]).unwrap();
+        writer.write_at(&signal, timestamp).unwrap();
+        writer.write_at(&call, timestamp).unwrap();
+        let capture = writer.into_inner();
+
+        let records = Reader::new(&capture[..])
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        for (record, msg) in records.iter().zip([&signal, &call]) {
+            assert_eq!(record.timestamp(), timestamp);
+            assert_eq!(record.message().data().bytes(), msg.data().bytes());
+        }
+        let body = records[0].message().body();
+        assert_eq!(body.deserialize::<(&str, u32)>().unwrap(), ("pcap", 42));
+
+        // A truncated capture ends with an error.
+        let mut reader = Reader::new(&capture[..capture.len() - 1]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+
+        // Only D-Bus captures are supported.
+        let mut other = capture.clone();
+        other[20..24].copy_from_slice(&1u32.to_ne_bytes());
+        assert!(Reader::new(&other[..]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn recorder() {
+        crate::utils::block_on(test_recorder()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_recorder() -> Result<()> {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let recorder = Recorder::new(file.reopen().unwrap())?;
+        let (client, server) =
+            crate::Connection::pair_with(|server| Ok(server.interceptor(recorder.clone()))).await?;
+        let mut client_stream = MessageStream::from(&client);
+        let mut server_stream = MessageStream::from(&server);
+
+        client
+            .emit_signal(None::<()>, "/", "org.zbus.Pcap", "Ping", &())
+            .await?;
+        server_stream.next().await.unwrap()?;
+        server
+            .emit_signal(None::<()>, "/", "org.zbus.Pcap", "Pong", &())
+            .await?;
+        client_stream.next().await.unwrap()?;
+        recorder.flush().await?;
+
+        // The server got the ping and sent the pong.
+        assert_eq!(members(&file)?, ["Ping", "Pong"]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(15000)]
+    fn record_stream() {
+        crate::utils::block_on(test_record_stream()).unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn test_record_stream() -> Result<()> {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let recorder = Recorder::new(file.reopen().unwrap())?;
+        let (client, server) = crate::Connection::pair().await?;
+        let mut stream = Box::pin(recorder.record_stream(MessageStream::from(&server)));
+
+        for member in ["Ping", "Pong"] {
+            client
+                .emit_signal(None::<()>, "/", "org.zbus.Pcap", member, &())
+                .await?;
+            let msg = stream.next().await.unwrap()?;
+            assert_eq!(msg.header().member().unwrap(), member);
+        }
+        recorder.flush().await?;
+        assert_eq!(members(&file)?, ["Ping", "Pong"]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn members(file: &tempfile::NamedTempFile) -> Result<Vec<String>> {
+        Reader::new(file.reopen().unwrap())?
+            .map(|record| record.map(|r| r.message().header().member().unwrap().to_string()))
+            .collect()
+    }
+}
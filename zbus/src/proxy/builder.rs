@@ -1,4 +1,4 @@
-use std::{collections::HashSet, marker::PhantomData, sync::Arc};
+use std::{collections::HashSet, marker::PhantomData, sync::Arc, time::Duration};
 
 use static_assertions::assert_impl_all;
 use zbus_names::{BusName, InterfaceName};
@@ -30,6 +30,7 @@ pub struct Builder<'a, T = ()> {
     proxy_type: PhantomData<T>,
     cache: CacheProperties,
     uncached_properties: Option<HashSet<Str<'a>>>,
+    method_timeout: Option<Duration>,
 }
 
 impl<'a, T> Clone for Builder<'a, T> {
@@ -41,6 +42,7 @@ impl<'a, T> Clone for Builder<'a, T> {
             interface: self.interface.clone(),
             cache: self.cache,
             uncached_properties: self.uncached_properties.clone(),
+            method_timeout: self.method_timeout,
             proxy_type: PhantomData,
         }
     }
@@ -95,6 +97,18 @@ impl<'a, T> Builder<'a, T> {
         self
     }
 
+    /// Set the timeout for method replies.
+    ///
+    /// This overrides the [default timeout][dt] of the connection for all method calls made
+    /// through the proxy.
+    ///
+    /// [dt]: crate::connection::Builder::method_timeout
+    #[must_use]
+    pub fn method_timeout(mut self, timeout: Duration) -> Self {
+        self.method_timeout = Some(timeout);
+        self
+    }
+
     pub(crate) fn build_internal(self) -> Result<Proxy<'a>> {
         let conn = self.conn;
         let destination = self
@@ -113,6 +127,7 @@ impl<'a, T> Builder<'a, T> {
                 interface,
                 cache,
                 uncached_properties,
+                self.method_timeout,
             )),
         })
     }
@@ -158,6 +173,7 @@ where
                 .map(|i| InterfaceName::from_static_str(i).expect("invalid interface name")),
             cache: CacheProperties::default(),
             uncached_properties: None,
+            method_timeout: None,
             proxy_type: PhantomData,
         }
     }
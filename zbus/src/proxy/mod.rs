@@ -14,6 +14,7 @@ use std::{
     pin::Pin,
     sync::{Arc, RwLock, RwLockReadGuard},
     task::{Context, Poll},
+    time::Duration,
 };
 use tracing::{debug, info_span, instrument, trace, Instrument};
 
@@ -95,6 +96,8 @@ pub(crate) struct ProxyInner<'a> {
     /// Set of properties which do not get cached, by name.
     /// This overrides proxy-level caching behavior.
     uncached_properties: HashSet<Str<'a>>,
+    /// Timeout for method replies, overriding the connection's default.
+    method_timeout: Option<Duration>,
 }
 
 impl Drop for ProxyInnerStatic {
@@ -477,6 +480,7 @@ impl<'a> ProxyInner<'a> {
         interface: InterfaceName<'a>,
         cache: CacheProperties,
         uncached_properties: HashSet<Str<'a>>,
+        method_timeout: Option<Duration>,
     ) -> Self {
         let property_cache = match cache {
             CacheProperties::Yes | CacheProperties::Lazily => Some(OnceCell::new()),
@@ -492,6 +496,7 @@ impl<'a> ProxyInner<'a> {
             interface,
             property_cache,
             uncached_properties,
+            method_timeout,
         }
     }
 
@@ -808,17 +813,9 @@ impl<'a> Proxy<'a> {
         M::Error: Into<Error>,
         B: serde::ser::Serialize + zvariant::DynamicType,
     {
-        self.inner
-            .inner_without_borrows
-            .conn
-            .call_method(
-                Some(&self.inner.destination),
-                self.inner.path.as_str(),
-                Some(&self.inner.interface),
-                method_name,
-                body,
-            )
+        self.call_method_raw(method_name, BitFlags::empty(), body, self.method_timeout())
             .await
+            .map(|reply| reply.expect("no reply"))
     }
 
     /// Call a method and return the reply body.
@@ -861,6 +858,65 @@ impl<'a> Proxy<'a> {
     {
         let flags = flags.iter().map(Flags::from).collect::<BitFlags<_>>();
         match self
+            .call_method_raw(method_name, flags, body, self.method_timeout())
+            .await?
+        {
+            Some(reply) => reply.body().deserialize().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Call a method and return the reply body, failing with [`Error::Timeout`] if no reply is
+    /// received within `timeout`.
+    ///
+    /// This overrides the [default timeout][dt] of the proxy for this call only.
+    ///
+    /// [dt]: struct.Proxy.html#method.method_timeout
+    pub async fn call_with_timeout<'m, M, B, R>(
+        &self,
+        method_name: M,
+        timeout: Duration,
+        body: &B,
+    ) -> Result<R>
+    where
+        M: TryInto<MemberName<'m>>,
+        M::Error: Into<Error>,
+        B: serde::ser::Serialize + zvariant::DynamicType,
+        R: serde::de::DeserializeOwned + zvariant::Type,
+    {
+        let reply = self
+            .call_method_raw(method_name, BitFlags::empty(), body, Some(timeout))
+            .await?
+            .expect("no reply");
+
+        reply.body().deserialize()
+    }
+
+    /// The timeout for method replies.
+    ///
+    /// This is the timeout set through [`Builder::method_timeout`] if any, and the connection's
+    /// [default timeout][dt] otherwise.
+    ///
+    /// [dt]: struct.Connection.html#method.method_timeout
+    pub fn method_timeout(&self) -> Option<Duration> {
+        self.inner
+            .method_timeout
+            .or_else(|| self.inner.inner_without_borrows.conn.method_timeout())
+    }
+
+    async fn call_method_raw<'m, M, B>(
+        &self,
+        method_name: M,
+        flags: BitFlags<Flags>,
+        body: &B,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Message>>
+    where
+        M: TryInto<MemberName<'m>>,
+        M::Error: Into<Error>,
+        B: serde::ser::Serialize + zvariant::DynamicType,
+    {
+        let reply = self
             .inner
             .inner_without_borrows
             .conn
@@ -872,9 +928,13 @@ impl<'a> Proxy<'a> {
                 flags,
                 body,
             )
-            .await?
-        {
-            Some(reply) => reply.await?.body().deserialize().map(Some),
+            .await?;
+        match reply {
+            Some(mut reply) => {
+                reply.set_timeout(timeout);
+
+                reply.await.map(Some)
+            }
             None => Ok(None),
         }
     }
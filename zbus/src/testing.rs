@@ -0,0 +1,827 @@
+//! Mocking D-Bus services, for testing clients.
+//!
+//! See [`MockService`] for details.
+
+use futures_util::StreamExt;
+use static_assertions::assert_impl_all;
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+    sync::{Arc, Mutex},
+};
+use tracing::debug;
+use zbus_names::{
+    ErrorName, InterfaceName, MemberName, OwnedErrorName, OwnedInterfaceName, OwnedMemberName,
+    WellKnownName,
+};
+use zvariant::{DynamicType, ObjectPath, OwnedObjectPath, OwnedValue, Structure, Value};
+
+use crate::{
+    broker::Broker,
+    connection,
+    message::{self, Type},
+    Connection, Error, MatchRule, Message, MessageStream, Result, Task,
+};
+
+/// A mock D-Bus service, for testing the code using a service through its proxies.
+///
+/// The service is hosted on its own in-process [`Broker`], where it owns the well-known name it's
+/// created with, and the [client connection] to create the proxies with is connected to the same
+/// broker. Instead of serving actual interfaces, it answers method calls as instructed by the
+/// [`Expectation`]s it's given, in the order they were added, and serves the properties set
+/// through [`MockService::set_property`] through the standard `org.freedesktop.DBus.Properties`
+/// interface. Any other method call is answered with an `org.freedesktop.DBus.Error.UnknownMethod`
+/// error.
+///
+/// Once done, the service checks that each expectation got the expected number of calls and that
+/// there were no unexpected calls. This happens on [`MockService::verify`] or, failing that, when
+/// the service is dropped, which then panics with a report of the failures.
+///
+/// # Example
+///
+/// ```
+///# zbus::block_on(async {
+/// use zbus::{
+///     dbus_proxy,
+///     testing::{Expectation, MockService},
+/// };
+///
+/// #[dbus_proxy(
+///     interface = "org.zbus.Greeter",
+///     default_service = "org.zbus.Greeter",
+///     default_path = "/org/zbus/Greeter"
+/// )]
+/// trait Greeter {
+///     fn greet(&self, name: &str) -> zbus::Result<String>;
+///
+///     #[dbus_proxy(property)]
+///     fn greeting(&self) -> zbus::Result<String>;
+/// }
+///
+/// let mock = MockService::new("org.zbus.Greeter").await?;
+/// mock.set_property("/org/zbus/Greeter", "org.zbus.Greeter", "Greeting", "Hello")
+///     .await?;
+/// mock.expect(
+///     Expectation::call("/org/zbus/Greeter", "org.zbus.Greeter", "Greet")?
+///         .with_args(&"Maria")?
+///         .returns(&"Hello Maria!")?,
+/// );
+///
+/// let greeter = GreeterProxy::new(mock.client()).await?;
+/// assert_eq!(greeter.greeting().await?, "Hello");
+/// assert_eq!(greeter.greet("Maria").await?, "Hello Maria!");
+/// mock.verify()?;
+///# Ok::<(), zbus::Error>(())
+///# }).unwrap();
+/// ```
+///
+/// [client connection]: MockService::client
+/// [`Broker`]: crate::broker::Broker
+#[derive(Debug)]
+pub struct MockService {
+    client: Connection,
+    service: Connection,
+    state: Arc<Mutex<State>>,
+    #[allow(unused)]
+    task: Task<()>,
+    #[allow(unused)]
+    broker: Broker,
+}
+
+assert_impl_all!(MockService: Send, Sync, Unpin);
+
+impl MockService {
+    /// Start a new mock service, owning `name`.
+    pub async fn new<'n, N>(name: N) -> Result<Self>
+    where
+        N: TryInto<WellKnownName<'n>>,
+        N::Error: Into<Error>,
+    {
+        let name = name.try_into().map_err(Into::into)?;
+        let dir = std::env::temp_dir();
+        let broker = Broker::builder(format!("unix:tmpdir={}", dir.display()).as_str())?
+            .build()
+            .await?;
+        let service = connection::Builder::address(broker.address().clone())?
+            .build()
+            .await?;
+        let rule = MatchRule::builder().msg_type(Type::MethodCall).build();
+        let calls = MessageStream::for_match_rule(rule, &service, None).await?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let task = service
+            .executor()
+            .spawn(serve(service.clone(), calls, state.clone()), "mock service");
+        service.request_name(name).await?;
+        let client = connection::Builder::address(broker.address().clone())?
+            .build()
+            .await?;
+
+        Ok(Self {
+            client,
+            service,
+            state,
+            task,
+            broker,
+        })
+    }
+
+    /// The connection to the service, to create the proxies with.
+    pub fn client(&self) -> &Connection {
+        &self.client
+    }
+
+    /// Add an expected method call.
+    ///
+    /// Calls are matched against the expectations in the order they were added, skipping the ones
+    /// that already got all their expected calls.
+    pub fn expect(&self, expectation: Expectation) {
+        self.state().expectations.push((expectation, 0));
+    }
+
+    /// Set the value of a property.
+    ///
+    /// The `PropertiesChanged` signal is emitted for the change.
+    pub async fn set_property<'p, 'i, 'v, P, I, V>(
+        &self,
+        path: P,
+        interface: I,
+        name: &str,
+        value: V,
+    ) -> Result<()>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        I: TryInto<InterfaceName<'i>>,
+        V: Into<Value<'v>>,
+        P::Error: Into<Error>,
+        I::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let interface = interface.try_into().map_err(Into::into)?;
+        let value = OwnedValue::from(value.into());
+        let changed = self
+            .state()
+            .set_property(path.into(), interface.into(), name, value)?;
+
+        self.service.send(&changed).await
+    }
+
+    /// The value of a property, as set through [`MockService::set_property`] or by the client.
+    pub fn property<'p, 'i, P, I>(
+        &self,
+        path: P,
+        interface: I,
+        name: &str,
+    ) -> Result<Option<OwnedValue>>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        I: TryInto<InterfaceName<'i>>,
+        P::Error: Into<Error>,
+        I::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let interface = interface.try_into().map_err(Into::into)?;
+
+        Ok(self
+            .state()
+            .properties
+            .get(&(path.into(), interface.into()))
+            .and_then(|properties| properties.get(name))
+            .cloned())
+    }
+
+    /// Emit a signal.
+    pub async fn emit_signal<'p, 'i, 'm, P, I, M, B>(
+        &self,
+        path: P,
+        interface: I,
+        signal_name: M,
+        body: &B,
+    ) -> Result<()>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        I: TryInto<InterfaceName<'i>>,
+        M: TryInto<MemberName<'m>>,
+        P::Error: Into<Error>,
+        I::Error: Into<Error>,
+        M::Error: Into<Error>,
+        B: serde::ser::Serialize + DynamicType,
+    {
+        self.service
+            .emit_signal(None::<()>, path, interface, signal_name, body)
+            .await
+    }
+
+    /// Check that all expectations got the expected number of calls, and that there were no
+    /// unexpected calls.
+    ///
+    /// On failure, [`Error::Failure`] is returned with a report of the failures.
+    pub fn verify(&self) -> Result<()> {
+        let state = self.state();
+        let mut report = String::new();
+        let unmet = state
+            .expectations
+            .iter()
+            .filter(|(expectation, calls)| expectation.times.map_or(false, |n| *calls != n));
+        for (i, (expectation, calls)) in unmet.enumerate() {
+            if i == 0 {
+                report.push_str("Unmet expectations:\n");
+            }
+            // SAFETY: Only expectations with a number of calls are unmet.
+            let times = expectation.times.unwrap();
+            let _ = writeln!(
+                report,
+                "- {expectation}: expected {times} call(s), got {calls}"
+            );
+        }
+        for (i, call) in state.unexpected.iter().enumerate() {
+            if i == 0 {
+                report.push_str("Unexpected calls:\n");
+            }
+            let _ = writeln!(report, "- {call}");
+        }
+
+        if report.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Failure(report))
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("poisoned lock")
+    }
+}
+
+impl Drop for MockService {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+
+        if let Err(e) = self.verify() {
+            panic!("{e}");
+        }
+    }
+}
+
+/// A method call expected by a [`MockService`].
+///
+/// By default, the call is expected once, with any arguments, and gets an empty reply.
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    path: OwnedObjectPath,
+    interface: OwnedInterfaceName,
+    method: OwnedMemberName,
+    args: Option<Args>,
+    reply: Reply,
+    signals: Vec<Signal>,
+    times: Option<usize>,
+}
+
+assert_impl_all!(Expectation: Send, Sync, Unpin);
+
+impl Expectation {
+    /// Expect a call to `method` of `interface`, on the object at `path`.
+    pub fn call<'p, 'i, 'm, P, I, M>(path: P, interface: I, method: M) -> Result<Self>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        I: TryInto<InterfaceName<'i>>,
+        M: TryInto<MemberName<'m>>,
+        P::Error: Into<Error>,
+        I::Error: Into<Error>,
+        M::Error: Into<Error>,
+    {
+        Ok(Self {
+            path: path.try_into().map_err(Into::into)?.into(),
+            interface: interface.try_into().map_err(Into::into)?.into(),
+            method: method.try_into().map_err(Into::into)?.into(),
+            args: None,
+            reply: Reply::Return(Args::new(&())?),
+            signals: vec![],
+            times: Some(1),
+        })
+    }
+
+    /// Only match calls with the given arguments.
+    ///
+    /// As for method call bodies, multiple arguments are given as a tuple. Arguments are compared
+    /// in their serialized form so file descriptors (`h`) are not supported and
+    /// [`Error::Unsupported`] is returned for them.
+    pub fn with_args<B>(mut self, args: &B) -> Result<Self>
+    where
+        B: serde::ser::Serialize + DynamicType,
+    {
+        self.args = Some(Args::new(args)?);
+
+        Ok(self)
+    }
+
+    /// Reply to the call with the given body.
+    ///
+    /// The body can't contain file descriptors, [`Error::Unsupported`] is returned for them.
+    pub fn returns<B>(mut self, body: &B) -> Result<Self>
+    where
+        B: serde::ser::Serialize + DynamicType,
+    {
+        self.reply = Reply::Return(Args::new(body)?);
+
+        Ok(self)
+    }
+
+    /// Reply to the call with an error.
+    pub fn fails<'e, E>(mut self, name: E, description: &str) -> Result<Self>
+    where
+        E: TryInto<ErrorName<'e>>,
+        E::Error: Into<Error>,
+    {
+        let name = name.try_into().map_err(Into::into)?;
+        self.reply = Reply::Error(name.into(), description.to_string());
+
+        Ok(self)
+    }
+
+    /// Emit a signal after replying to the call.
+    ///
+    /// Signals are emitted in the order they were added. The body can't contain file descriptors,
+    /// [`Error::Unsupported`] is returned for them.
+    pub fn emits<'p, 'i, 'm, P, I, M, B>(
+        mut self,
+        path: P,
+        interface: I,
+        signal_name: M,
+        body: &B,
+    ) -> Result<Self>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        I: TryInto<InterfaceName<'i>>,
+        M: TryInto<MemberName<'m>>,
+        P::Error: Into<Error>,
+        I::Error: Into<Error>,
+        M::Error: Into<Error>,
+        B: serde::ser::Serialize + DynamicType,
+    {
+        self.signals.push(Signal {
+            path: path.try_into().map_err(Into::into)?.into(),
+            interface: interface.try_into().map_err(Into::into)?.into(),
+            name: signal_name.try_into().map_err(Into::into)?.into(),
+            args: Args::new(body)?,
+        });
+
+        Ok(self)
+    }
+
+    /// Expect exactly `n` calls.
+    pub fn times(mut self, n: usize) -> Self {
+        self.times = Some(n);
+
+        self
+    }
+
+    /// Accept any number of calls, including none.
+    pub fn any_times(mut self) -> Self {
+        self.times = None;
+
+        self
+    }
+
+    fn matches(&self, call: &Message) -> bool {
+        let header = call.header();
+
+        header.path().map_or(false, |p| *p == *self.path)
+            && header.interface().map_or(false, |i| *i == self.interface)
+            && header.member().map_or(false, |m| *m == self.method)
+            && self.args.as_ref().map_or(true, |args| args.matches(call))
+    }
+
+    fn answer(&self, call: &Message) -> Result<Vec<Message>> {
+        let reply = match &self.reply {
+            Reply::Return(body) => body.build(Message::method_reply(call)?)?,
+            Reply::Error(name, description) => {
+                Message::method_error(call, name)?.build(description)?
+            }
+        };
+        let mut msgs = vec![reply];
+        for signal in &self.signals {
+            let builder = Message::signal(&signal.path, &signal.interface, &signal.name)?;
+            msgs.push(signal.args.build(builder)?);
+        }
+
+        Ok(msgs)
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} at {}", self.interface, self.method, self.path)?;
+        if let Some(args) = &self.args {
+            write!(f, " with {args}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Reply {
+    Return(Args),
+    Error(OwnedErrorName, String),
+}
+
+#[derive(Debug, Clone)]
+struct Signal {
+    path: OwnedObjectPath,
+    interface: OwnedInterfaceName,
+    name: OwnedMemberName,
+    args: Args,
+}
+
+// Arguments serialized upfront, as the body of a message of their own.
+//
+// File descriptors are rejected: they'd be compared by their index in the message rather than by
+// what they refer to, and couldn't be sent again from the deserialized body.
+#[derive(Debug, Clone)]
+struct Args(Message);
+
+impl Args {
+    fn new<B>(args: &B) -> Result<Self>
+    where
+        B: serde::ser::Serialize + DynamicType,
+    {
+        if args.dynamic_signature().as_str().contains('h') {
+            return Err(Error::Unsupported);
+        }
+
+        Message::method("/", "Args")?.build(args).map(Self)
+    }
+
+    fn matches(&self, msg: &Message) -> bool {
+        let (expected, actual) = (self.0.body(), msg.body());
+
+        // The encoding is canonical, so equal arguments are encoded the same.
+        expected.signature() == actual.signature()
+            && expected.data().bytes() == actual.data().bytes()
+    }
+
+    fn build(&self, builder: message::Builder<'_>) -> Result<Message> {
+        let body = self.0.body();
+        let Some(signature) = body.signature() else {
+            return builder.build(&());
+        };
+        // The builder strips the outer parentheses, which would otherwise turn a single struct
+        // argument into its fields.
+        let signature = format!("({signature})");
+
+        // SAFETY: The bytes were serialized with this signature by `Message::build` and, since
+        // file descriptors are rejected, don't refer to any.
+        unsafe {
+            builder.build_raw_body(
+                body.data().bytes(),
+                signature.as_str(),
+                #[cfg(unix)]
+                vec![],
+            )
+        }
+    }
+}
+
+impl fmt::Display for Args {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display_body(&self.0, f)
+    }
+}
+
+fn display_body(msg: &Message, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let body = msg.body();
+    if body.signature().is_none() {
+        return f.write_str("()");
+    }
+
+    match body.deserialize::<Structure<'_>>() {
+        Ok(args) => write!(f, "{args}"),
+        Err(_) => f.write_str("<invalid body>"),
+    }
+}
+
+// A method call, as shown in reports.
+struct Call<'m>(&'m Message);
+
+impl fmt::Display for Call<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = self.0.header();
+        if let Some(interface) = header.interface() {
+            write!(f, "{interface}.")?;
+        }
+        if let Some(member) = header.member() {
+            write!(f, "{member}")?;
+        }
+        if let Some(path) = header.path() {
+            write!(f, " at {path}")?;
+        }
+        f.write_str(" with ")?;
+
+        display_body(self.0, f)
+    }
+}
+
+type Properties = HashMap<String, OwnedValue>;
+
+#[derive(Debug, Default)]
+struct State {
+    // The expectations, along with the number of calls they got.
+    expectations: Vec<(Expectation, usize)>,
+    properties: HashMap<(OwnedObjectPath, OwnedInterfaceName), Properties>,
+    // The unexpected calls, as reported.
+    unexpected: Vec<String>,
+}
+
+impl State {
+    // The reply to `call`, followed by the signals to emit.
+    fn answer(&mut self, call: &Message) -> Result<Vec<Message>> {
+        let expectation = self.expectations.iter_mut().find(|(expectation, calls)| {
+            expectation.times.map_or(true, |n| *calls < n) && expectation.matches(call)
+        });
+        if let Some((expectation, calls)) = expectation {
+            *calls += 1;
+
+            return expectation.answer(call);
+        }
+
+        let header = call.header();
+        if header
+            .interface()
+            .map_or(false, |i| i == "org.freedesktop.DBus.Properties")
+        {
+            if let Some(msgs) = self.properties_call(call)? {
+                return Ok(msgs);
+            }
+        }
+
+        let report = Call(call).to_string();
+        debug!("Unexpected call: {report}");
+        let reply = Message::method_error(call, "org.freedesktop.DBus.Error.UnknownMethod")?
+            .build(&format!("Unexpected call to {report}"))?;
+        self.unexpected.push(report);
+
+        Ok(vec![reply])
+    }
+
+    // Serve the properties. Returns `None` for calls to unknown properties.
+    fn properties_call(&mut self, call: &Message) -> Result<Option<Vec<Message>>> {
+        let header = call.header();
+        let path = match header.path() {
+            Some(path) => OwnedObjectPath::from(path.to_owned()),
+            None => return Ok(None),
+        };
+        let body = call.body();
+        let msgs = match header.member().map(|m| m.as_str()) {
+            Some("Get") => {
+                let Ok((interface, name)) = body.deserialize::<(InterfaceName<'_>, &str)>() else {
+                    return Ok(None);
+                };
+                let value = self
+                    .properties
+                    .get(&(path, interface.into()))
+                    .and_then(|properties| properties.get(name));
+                match value {
+                    Some(value) => vec![Message::method_reply(call)?.build(value)?],
+                    None => return Ok(None),
+                }
+            }
+            Some("GetAll") => {
+                let Ok(interface) = body.deserialize::<InterfaceName<'_>>() else {
+                    return Ok(None);
+                };
+                let properties = self
+                    .properties
+                    .get(&(path, interface.into()))
+                    .cloned()
+                    .unwrap_or_default();
+
+                vec![Message::method_reply(call)?.build(&properties)?]
+            }
+            Some("Set") => {
+                let Ok((interface, name, value)) =
+                    body.deserialize::<(InterfaceName<'_>, &str, OwnedValue)>()
+                else {
+                    return Ok(None);
+                };
+                let changed = self.set_property(path, interface.into(), name, value)?;
+
+                vec![Message::method_reply(call)?.build(&())?, changed]
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(msgs))
+    }
+
+    // Set a property, returning the `PropertiesChanged` signal to emit.
+    fn set_property(
+        &mut self,
+        path: OwnedObjectPath,
+        interface: OwnedInterfaceName,
+        name: &str,
+        value: OwnedValue,
+    ) -> Result<Message> {
+        let changed = HashMap::from([(name, &value)]);
+        let signal = Message::signal(
+            &path,
+            "org.freedesktop.DBus.Properties",
+            "PropertiesChanged",
+        )?
+        .build(&(&interface, changed, Vec::<&str>::new()))?;
+        self.properties
+            .entry((path, interface))
+            .or_default()
+            .insert(name.to_string(), value);
+
+        Ok(signal)
+    }
+}
+
+async fn serve(service: Connection, mut calls: MessageStream, state: Arc<Mutex<State>>) {
+    while let Some(call) = calls.next().await {
+        let call = match call {
+            Ok(call) => call,
+            Err(e) => {
+                debug!("Error receiving a method call: {e}");
+
+                continue;
+            }
+        };
+        let msgs = state.lock().expect("poisoned lock").answer(&call);
+        let msgs = match msgs {
+            Ok(msgs) => msgs,
+            Err(e) => {
+                debug!("Failed to answer {}: {e}", Call(&call));
+
+                continue;
+            }
+        };
+        for msg in msgs {
+            if let Err(e) = service.send(&msg).await {
+                debug!("Failed to send {:?}: {e}", msg);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use ntest::timeout;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use test_log::test;
+
+    use super::*;
+    use crate::dbus_proxy;
+
+    const PATH: &str = "/org/zbus/Greeter";
+    const INTERFACE: &str = "org.zbus.Greeter";
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize, zvariant::Type)]
+    struct Stats {
+        greetings: u32,
+        names: u32,
+    }
+
+    #[dbus_proxy(
+        interface = "org.zbus.Greeter",
+        default_service = "org.zbus.Greeter",
+        default_path = "/org/zbus/Greeter"
+    )]
+    trait Greeter {
+        fn greet(&self, name: &str) -> Result<String>;
+
+        fn reset(&self) -> Result<()>;
+
+        fn stats(&self) -> Result<Stats>;
+
+        #[dbus_proxy(signal)]
+        fn greeted(&self, name: &str) -> Result<()>;
+
+        #[dbus_proxy(signal)]
+        fn stats_changed(&self, stats: Stats) -> Result<()>;
+
+        #[dbus_proxy(property)]
+        fn greeting(&self) -> Result<String>;
+
+        #[dbus_proxy(property)]
+        fn set_greeting(&self, greeting: &str) -> Result<()>;
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn mock_service() {
+        crate::utils::block_on(test_mock_service()).unwrap();
+    }
+
+    async fn test_mock_service() -> Result<()> {
+        let mock = MockService::new("org.zbus.Greeter").await?;
+        mock.set_property(PATH, INTERFACE, "Greeting", "Hello")
+            .await?;
+        mock.expect(
+            Expectation::call(PATH, INTERFACE, "Greet")?
+                .with_args(&"Maria")?
+                .returns(&"Hello Maria!")?
+                .emits(PATH, INTERFACE, "Greeted", &"Maria")?,
+        );
+        mock.expect(
+            Expectation::call(PATH, INTERFACE, "Greet")?
+                .fails("org.zbus.Error.Busy", "Too many greetings")?
+                .any_times(),
+        );
+        mock.expect(Expectation::call(PATH, INTERFACE, "Reset")?.times(2));
+
+        let greeter = GreeterProxy::new(mock.client()).await?;
+        let mut greeted = greeter.receive_greeted().await?;
+        assert_eq!(greeter.greet("Maria").await?, "Hello Maria!");
+        let signal = greeted.next().await.unwrap();
+        assert_eq!(signal.args()?.name(), &"Maria");
+
+        // The first expectation only matches once.
+        match greeter.greet("Maria").await.unwrap_err() {
+            Error::MethodError(name, description, _) => {
+                assert_eq!(name, "org.zbus.Error.Busy");
+                assert_eq!(description.as_deref(), Some("Too many greetings"));
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+
+        assert_eq!(greeter.greeting().await?, "Hello");
+        greeter.set_greeting("Hi").await?;
+        assert_eq!(
+            mock.property(PATH, INTERFACE, "Greeting")?,
+            Some(Value::from("Hi").into())
+        );
+
+        greeter.reset().await?;
+        match mock.verify().unwrap_err() {
+            Error::Failure(report) => assert_eq!(
+                report,
+                "Unmet expectations:\n\
+                 - org.zbus.Greeter.Reset at /org/zbus/Greeter: expected 2 call(s), got 1\n"
+            ),
+            e => panic!("unexpected error: {e}"),
+        }
+        greeter.reset().await?;
+        mock.verify()?;
+
+        greeter
+            .inner()
+            .call_method("Frobnicate", &(42u32,))
+            .await
+            .unwrap_err();
+        match mock.verify().unwrap_err() {
+            Error::Failure(report) => assert_eq!(
+                report,
+                "Unexpected calls:\n\
+                 - org.zbus.Greeter.Frobnicate at /org/zbus/Greeter with (uint32 42,)\n"
+            ),
+            e => panic!("unexpected error: {e}"),
+        }
+        // File descriptors can't be compared.
+        let fd = zvariant::Fd::from(&std::io::stdin());
+        assert!(matches!(
+            Expectation::call(PATH, INTERFACE, "Greet")?.with_args(&(fd,)),
+            Err(Error::Unsupported)
+        ));
+
+        // Failures are reported on drop too.
+        assert!(catch_unwind(AssertUnwindSafe(|| drop(mock))).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[timeout(15000)]
+    fn struct_args() {
+        crate::utils::block_on(test_struct_args()).unwrap();
+    }
+
+    async fn test_struct_args() -> Result<()> {
+        let mock = MockService::new("org.zbus.Greeter").await?;
+        let stats = Stats {
+            greetings: 1,
+            names: 2,
+        };
+        mock.expect(
+            Expectation::call(PATH, INTERFACE, "Stats")?
+                .returns(&(&stats,))?
+                .emits(PATH, INTERFACE, "StatsChanged", &(&stats,))?
+                .times(2),
+        );
+
+        let greeter = GreeterProxy::new(mock.client()).await?;
+        let mut changed = greeter.receive_stats_changed().await?;
+        assert_eq!(greeter.stats().await?, stats);
+        let signal = changed.next().await.unwrap();
+        assert_eq!(signal.args()?.stats(), &stats);
+        // A single struct argument isn't flattened into its fields.
+        let reply = greeter.inner().call_method("Stats", &()).await?;
+        assert_eq!(reply.body().signature().unwrap(), "(uu)");
+        let signal = Message::from(changed.next().await.unwrap());
+        assert_eq!(signal.body().signature().unwrap(), "(uu)");
+        mock.verify()
+    }
+}
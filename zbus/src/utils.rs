@@ -47,3 +47,21 @@ pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
         });
     TOKIO_RT.block_on(future)
 }
+
+/// A timer that fires once, after the given duration.
+#[cfg(not(feature = "tokio"))]
+pub(crate) type Sleep = async_io::Timer;
+
+/// A timer that fires once, after the given duration.
+#[cfg(feature = "tokio")]
+pub(crate) type Sleep = std::pin::Pin<Box<tokio::time::Sleep>>;
+
+#[cfg(not(feature = "tokio"))]
+pub(crate) fn sleep(duration: std::time::Duration) -> Sleep {
+    async_io::Timer::after(duration)
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) fn sleep(duration: std::time::Duration) -> Sleep {
+    Box::pin(tokio::time::sleep(duration))
+}
@@ -23,7 +23,12 @@ def_attrs! {
     pub MethodAttributes("method") {
         name str,
         signal none,
-        property none,
+        unprivileged none,
+        property {
+            pub PropertyAttributes("property") {
+                emits_changed_signal str
+            }
+        },
         out_args [str]
     };
 }
@@ -38,7 +43,8 @@ mod arg_attrs {
             object_server none,
             connection none,
             header none,
-            signal_context none
+            signal_context none,
+            reply_handle none
         };
     }
 }
@@ -51,6 +57,7 @@ struct Property<'a> {
     write: bool,
     ty: Option<&'a Type>,
     doc_comments: TokenStream,
+    emits_changed_signal: PropertyEmitsChangedSignal,
 }
 
 impl<'a> Property<'a> {
@@ -60,6 +67,7 @@ impl<'a> Property<'a> {
             write: false,
             ty: None,
             doc_comments: quote!(),
+            emits_changed_signal: PropertyEmitsChangedSignal::default(),
         }
     }
 }
@@ -75,6 +83,8 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
     let mut get_all = quote!();
     let mut call_dispatch = quote!();
     let mut call_mut_dispatch = quote!();
+    let mut unprivileged_methods = quote!();
+    let mut methods = quote!();
     let mut introspect = quote!();
     let mut generated_signals = quote!();
 
@@ -104,6 +114,33 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
             }
         };
 
+    // The setter of a property can come before its getter so we need to know how changes of each
+    // property are signaled, before generating any code for it.
+    let mut emits_changed_signals = BTreeMap::new();
+    for method in &input.items {
+        let method = match method {
+            ImplItem::Method(m) => m,
+            _ => continue,
+        };
+        let attrs = MethodAttributes::parse(&method.attrs)?;
+        let emits_changed_signal = match &attrs.property {
+            Some(PropertyAttributes {
+                emits_changed_signal: Some(s),
+            }) => PropertyEmitsChangedSignal::parse(s, method.span())?,
+            _ => continue,
+        };
+        let member_name = member_name(&attrs, &method.sig);
+        match emits_changed_signals.insert(member_name, emits_changed_signal) {
+            Some(other) if other != emits_changed_signal => {
+                return Err(Error::new_spanned(
+                    method,
+                    "conflicting `emits_changed_signal` values for the same property",
+                ));
+            }
+            _ => (),
+        }
+    }
+
     for method in &mut input.items {
         let method = match method {
             ImplItem::Method(m) => m,
@@ -112,6 +149,9 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
 
         let is_async = method.sig.asyncness.is_some();
 
+        let attrs = MethodAttributes::parse(&method.attrs)?;
+        let member_name = member_name(&attrs, &method.sig);
+
         let Signature {
             ident,
             inputs,
@@ -119,7 +159,6 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
             ..
         } = &mut method.sig;
 
-        let attrs = MethodAttributes::parse(&method.attrs)?;
         method
             .attrs
             .retain(|attr| !attr.path.is_ident("dbus_interface"));
@@ -143,8 +182,15 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
             .collect();
 
         let doc_comments = to_xml_docs(docs);
-        let is_property = attrs.property;
+        let is_property = attrs.property.is_some();
         let is_signal = attrs.signal;
+        let is_unprivileged = attrs.unprivileged;
+        if is_unprivileged && (is_property || is_signal) {
+            return Err(Error::new_spanned(
+                &method,
+                "`unprivileged` is only supported on methods",
+            ));
+        }
         let out_args = attrs.out_args.as_deref();
         assert!(!is_property || !is_signal);
 
@@ -188,10 +234,34 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
             None
         };
 
+        let reply_handle_ty = get_reply_handle_type(&typed_inputs)?;
+        if reply_handle_ty.is_some() {
+            if is_signal || is_property {
+                return Err(Error::new_spanned(
+                    &method,
+                    "`reply_handle` arguments are only supported in methods",
+                ));
+            }
+            if !matches!(output, ReturnType::Default) {
+                return Err(Error::new_spanned(
+                    &output,
+                    "Methods with a `reply_handle` argument must not return a value",
+                ));
+            }
+        }
+
         let mut intro_args = quote!();
         intro_args.extend(introspect_input_args(&typed_inputs, is_signal, &cfg_attrs));
-        let is_result_output =
-            introspect_add_output_args(&mut intro_args, output, out_args, &cfg_attrs)?;
+        let is_result_output = match &reply_handle_ty {
+            // The reply is sent through the handle so its type describes the out arguments.
+            Some(ty) => {
+                let output = parse_quote!(-> #ty);
+                introspect_add_output_args(&mut intro_args, &output, out_args, &cfg_attrs)?;
+
+                false
+            }
+            None => introspect_add_output_args(&mut intro_args, output, out_args, &cfg_attrs)?,
+        };
 
         let (args_from_msg, args_names) = get_args_from_inputs(&typed_inputs, &zbus)?;
 
@@ -210,15 +280,18 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
         } else {
             quote!(c.reply(m, &reply).await)
         };
-
-        let member_name = attrs.name.clone().unwrap_or_else(|| {
-            let mut name = ident.to_string();
-            if is_property && has_inputs {
-                assert!(name.starts_with("set_"));
-                name = name[4..].to_string();
+        let call_and_reply = if reply_handle_ty.is_some() {
+            // The method replies through the handle.
+            quote! {
+                self.#ident(#args_names)#method_await;
+                ::std::result::Result::Ok(())
             }
-            pascal_case(&name)
-        });
+        } else {
+            quote! {
+                let reply = self.#ident(#args_names)#method_await;
+                #reply
+            }
+        };
 
         if is_signal {
             introspect.extend(doc_comments);
@@ -242,8 +315,13 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
             let prop_changed_method_name = format_ident!("{sk_member_name}_changed");
             let prop_invalidate_method_name = format_ident!("{sk_member_name}_invalidate");
 
+            let emits_changed_signal = emits_changed_signals
+                .get(&member_name)
+                .copied()
+                .unwrap_or_default();
             let p = p.or_insert_with(Property::new);
             p.doc_comments.extend(doc_comments);
+            p.emits_changed_signal = emits_changed_signal;
             if has_inputs {
                 p.write = true;
 
@@ -294,18 +372,26 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
                         .unwrap_or_else(|| value_to_owned.clone()),
                     _ => value_to_owned,
                 };
+                let signal_change = match emits_changed_signal {
+                    PropertyEmitsChangedSignal::True | PropertyEmitsChangedSignal::Invalidates => {
+                        quote!(
+                            self
+                                .#prop_changed_method_name(&signal_context)
+                                .await
+                                .map(|_| set_result)
+                                .map_err(Into::into)
+                        )
+                    }
+                    PropertyEmitsChangedSignal::Const | PropertyEmitsChangedSignal::False => {
+                        quote!(::std::result::Result::Ok(set_result))
+                    }
+                };
                 let do_set = quote!({
                     let value = #value_arg;
                     match ::std::convert::TryInto::try_into(value) {
                         ::std::result::Result::Ok(val) => {
                             match #set_call {
-                                ::std::result::Result::Ok(set_result) => {
-                                    self
-                                        .#prop_changed_method_name(&signal_context)
-                                        .await
-                                        .map(|_| set_result)
-                                        .map_err(Into::into)
-                                }
+                                ::std::result::Result::Ok(set_result) => #signal_change,
                                 e => e,
                             }
                         }
@@ -397,22 +483,37 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
                     quote!(self.#ident()#method_await)
                 };
 
-                let prop_changed_method = quote!(
-                    pub async fn #prop_changed_method_name(
-                        &self,
-                        signal_context: &#zbus::object_server::SignalContext<'_>,
-                    ) -> #zbus::Result<()> {
-                        let mut changed = ::std::collections::HashMap::new();
-                        let value = <#zbus::zvariant::Value as ::std::convert::From<_>>::from(#prop_value_handled);
-                        changed.insert(#member_name, &value);
-                        #zbus::fdo::Properties::properties_changed(
-                            signal_context,
-                            #zbus::names::InterfaceName::from_static_str_unchecked(#iface_name),
-                            &changed,
-                            &[],
-                        ).await
+                let prop_changed_method = match emits_changed_signal {
+                    PropertyEmitsChangedSignal::True | PropertyEmitsChangedSignal::False => {
+                        quote!(
+                            pub async fn #prop_changed_method_name(
+                                &self,
+                                signal_context: &#zbus::object_server::SignalContext<'_>,
+                            ) -> #zbus::Result<()> {
+                                let mut changed = ::std::collections::HashMap::new();
+                                let value = <#zbus::zvariant::Value as ::std::convert::From<_>>::from(#prop_value_handled);
+                                changed.insert(#member_name, &value);
+                                #zbus::fdo::Properties::properties_changed(
+                                    signal_context,
+                                    #zbus::names::InterfaceName::from_static_str_unchecked(#iface_name),
+                                    &changed,
+                                    &[],
+                                ).await
+                            }
+                        )
                     }
-                );
+                    // The new value must not be included in the signal.
+                    PropertyEmitsChangedSignal::Invalidates => quote!(
+                        pub async fn #prop_changed_method_name(
+                            &self,
+                            signal_context: &#zbus::object_server::SignalContext<'_>,
+                        ) -> #zbus::Result<()> {
+                            self.#prop_invalidate_method_name(signal_context).await
+                        }
+                    ),
+                    // The property never changes so there is nothing to signal.
+                    PropertyEmitsChangedSignal::Const => continue,
+                };
                 generated_signals.extend(prop_changed_method);
 
                 let prop_invalidate_method = quote!(
@@ -439,8 +540,7 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
                 #member_name => {
                     let future = async move {
                         #args_from_msg
-                        let reply = self.#ident(#args_names)#method_await;
-                        #reply
+                        #call_and_reply
                     };
                     #zbus::object_server::DispatchResult::Async(::std::boxed::Box::pin(async move {
                         future.await
@@ -457,6 +557,17 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
             } else {
                 call_dispatch.extend(m);
             }
+
+            methods.extend(quote! {
+                #(#cfg_attrs)*
+                #member_name => true,
+            });
+            if is_unprivileged {
+                unprivileged_methods.extend(quote! {
+                    #(#cfg_attrs)*
+                    #member_name => false,
+                });
+            }
         }
     }
 
@@ -530,6 +641,20 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
                 }
             }
 
+            fn has_method(&self, name: &#zbus::names::MemberName<'_>) -> bool {
+                match name.as_str() {
+                    #methods
+                    _ => false,
+                }
+            }
+
+            fn requires_authorization(&self, name: &#zbus::names::MemberName<'_>) -> bool {
+                match name.as_str() {
+                    #unprivileged_methods
+                    _ => true,
+                }
+            }
+
             fn call<'call>(
                 &'call self,
                 s: &'call #zbus::ObjectServer,
@@ -576,6 +701,18 @@ pub fn expand(args: AttributeArgs, mut input: ItemImpl) -> syn::Result<TokenStre
     })
 }
 
+// The D-Bus name of the method, signal or property.
+fn member_name(attrs: &MethodAttributes, sig: &Signature) -> String {
+    attrs.name.clone().unwrap_or_else(|| {
+        let mut name = sig.ident.to_string();
+        if attrs.property.is_some() && sig.inputs.len() > 1 {
+            assert!(name.starts_with("set_"));
+            name = name[4..].to_string();
+        }
+        pascal_case(&name)
+    })
+}
+
 fn get_args_from_inputs(
     inputs: &[PatType],
     zbus: &TokenStream,
@@ -587,6 +724,7 @@ fn get_args_from_inputs(
         let mut conn_arg_decl = None;
         let mut header_arg_decl = None;
         let mut signal_context_arg_decl = None;
+        let mut reply_handle_arg_decl = None;
         let mut args_names = Vec::new();
         let mut tys = Vec::new();
 
@@ -647,6 +785,19 @@ fn get_args_from_inputs(
                         }
                     };
                 });
+            } else if attrs.reply_handle {
+                if reply_handle_arg_decl.is_some() {
+                    return Err(Error::new_spanned(
+                        input,
+                        "There can only be one `reply_handle` argument",
+                    ));
+                }
+
+                let reply_handle_arg = &input.pat;
+
+                reply_handle_arg_decl = Some(quote! {
+                    let #reply_handle_arg = #zbus::object_server::MethodReply::new(c, m);
+                });
             } else {
                 args_names.push(pat_ident(input).unwrap());
                 tys.push(&input.ty);
@@ -673,6 +824,9 @@ fn get_args_from_inputs(
                         return c.reply_dbus_error(&hdr, err).await;
                     }
                 };
+
+            // Created last, so that the handle doesn't send an error reply on the early returns.
+            #reply_handle_arg_decl
         };
 
         let all_args_names = inputs.iter().filter_map(pat_ident);
@@ -682,6 +836,33 @@ fn get_args_from_inputs(
     }
 }
 
+fn get_reply_handle_type(inputs: &[PatType]) -> syn::Result<Option<Type>> {
+    for input in inputs {
+        if !ArgAttributes::parse(&input.attrs)?.reply_handle {
+            continue;
+        }
+
+        if let Type::Path(p) = &*input.ty {
+            if let Some(PathArguments::AngleBracketed(AngleBracketedGenericArguments {
+                args,
+                ..
+            })) = p.path.segments.last().map(|s| &s.arguments)
+            {
+                if let Some(GenericArgument::Type(ty)) = args.first() {
+                    return Ok(Some(ty.clone()));
+                }
+            }
+        }
+
+        return Err(Error::new_spanned(
+            &input.ty,
+            "Expected a `zbus::object_server::MethodReply<T>` argument",
+        ));
+    }
+
+    Ok(None)
+}
+
 fn clean_input_args(inputs: &mut Punctuated<FnArg, Token![,]>) {
     for input in inputs {
         if let FnArg::Typed(t) = input {
@@ -739,7 +920,7 @@ fn introspect_input_args<'i>(
                     matches!(
                         nested_meta,
                         NestedMeta::Meta(Meta::Path(path))
-                        if path.is_ident("object_server") || path.is_ident("connection") || path.is_ident("header") || path.is_ident("signal_context")
+                        if path.is_ident("object_server") || path.is_ident("connection") || path.is_ident("header") || path.is_ident("signal_context") || path.is_ident("reply_handle")
                     )
                 });
 
@@ -881,14 +1062,32 @@ fn introspect_properties(
         })?;
 
         let doc_comments = prop.doc_comments;
-        introspection.extend(quote!(
-            #doc_comments
-            ::std::writeln!(
-                writer,
-                "{:indent$}<property name=\"{}\" type=\"{}\" access=\"{}\"/>",
-                "", #name, <#ty>::signature(), #access, indent = level,
-            ).unwrap();
-        ));
+        if prop.emits_changed_signal == PropertyEmitsChangedSignal::True {
+            introspection.extend(quote!(
+                #doc_comments
+                ::std::writeln!(
+                    writer,
+                    "{:indent$}<property name=\"{}\" type=\"{}\" access=\"{}\"/>",
+                    "", #name, <#ty>::signature(), #access, indent = level,
+                ).unwrap();
+            ));
+        } else {
+            let emits_changed_signal = prop.emits_changed_signal.as_str();
+            introspection.extend(quote!(
+                #doc_comments
+                ::std::writeln!(
+                    writer,
+                    "{:indent$}<property name=\"{}\" type=\"{}\" access=\"{}\">",
+                    "", #name, <#ty>::signature(), #access, indent = level,
+                ).unwrap();
+                ::std::writeln!(
+                    writer,
+                    "{:indent$}<annotation name=\"org.freedesktop.DBus.Property.EmitsChangedSignal\" value=\"{}\"/>",
+                    "", #emits_changed_signal, indent = level + 2,
+                ).unwrap();
+                ::std::writeln!(writer, "{:indent$}</property>", "", indent = level).unwrap();
+            ));
+        }
     }
 
     Ok(())
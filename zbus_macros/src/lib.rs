@@ -211,7 +211,22 @@ pub fn dbus_proxy(attr: TokenStream, item: TokenStream) -> TokenStream {
 ///
 /// * `property` - expose the method as a property. If the method takes an argument, it must be a
 ///   setter, with a `set_` prefix. Otherwise, it's a getter. If it may fail, a property method must
-///   return `zbus::fdo::Result`.
+///   return `zbus::fdo::Result`. Additional sub-attributes exists to control specific property
+///   behaviors:
+///   * `emits_changed_signal` - specifies how property changes are signaled. It only needs to be
+///     specified on either the getter or the setter. Valid values are those documented in [DBus
+///     specifications][dbus_emits_changed_signal]:
+///     * `"true"` - (default) the setter emits the "PropertiesChanged" signal with the new value.
+///     * `"invalidates"` - the setter emits the "PropertiesChanged" signal without the new value.
+///       The generated `<property_name_in_snake_case>_changed` method does the same.
+///     * `"const"` - property never changes, thus no signal is ever emitted for it. The
+///       `<property_name_in_snake_case>_changed` and `<property_name_in_snake_case>_invalidate`
+///       methods are not generated.
+///     * `"false"` - the setter doesn't emit any signal. You can still do so yourself, through the
+///       generated methods.
+///
+///     Unless it's `"true"`, the value is advertised through the
+///     `org.freedesktop.DBus.Property.EmitsChangedSignal` annotation in the introspection data.
 ///
 /// * `signal` - the method is a "signal". It must be a method declaration (without body). Its code
 ///   block will be expanded to emit the signal from the object path associated with the interface
@@ -220,6 +235,9 @@ pub fn dbus_proxy(attr: TokenStream, item: TokenStream) -> TokenStream {
 ///   You can call a signal method from a an interface method, or from an [`ObjectServer::with`]
 ///   function.
 ///
+/// * `unprivileged` - calls to the method are dispatched without being checked by the
+///   [`Authorizer`] of the object server.
+///
 /// * `out_args` - When returning multiple values from a method, naming the out arguments become
 ///   important. You can use `out_args` to specify their names.
 ///
@@ -251,6 +269,9 @@ pub fn dbus_proxy(attr: TokenStream, item: TokenStream) -> TokenStream {
 ///   D-Bus method call being handled.
 /// * `signal_context` - This marks the method argument to receive a [`SignalContext`] instance,
 ///   which is needed for emitting signals the easy way.
+/// * `reply_handle` - This marks the method argument to receive a [`MethodReply<T>`] handle, for
+///   replying to the method call later (e.g from another task or another method call). `T` is the
+///   type of the reply. The method itself must not return a value.
 ///
 /// # Example
 ///
@@ -316,10 +337,13 @@ pub fn dbus_proxy(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// See also [`ObjectServer`] documentation to learn how to export an interface over a `Connection`.
 ///
 /// [`ObjectServer`]: https://docs.rs/zbus/latest/zbus/object_server/struct.ObjectServer.html
+/// [dbus_emits_changed_signal]: https://dbus.freedesktop.org/doc/dbus-specification.html#introspection-format
 /// [`ObjectServer::with`]: https://docs.rs/zbus/latest/zbus/object_server/struct.ObjectServer.html#method.with
 /// [`Connection`]: https://docs.rs/zbus/latest/zbus/connection/struct.Connection.html
 /// [`Connection::emit_signal()`]: https://docs.rs/zbus/latest/zbus/connection/struct.Connection.html#method.emit_signal
 /// [`SignalContext`]: https://docs.rs/zbus/latest/zbus/object_server/struct.SignalContext.html
+/// [`MethodReply<T>`]: https://docs.rs/zbus/latest/zbus/object_server/struct.MethodReply.html
+/// [`Authorizer`]: https://docs.rs/zbus/latest/zbus/object_server/trait.Authorizer.html
 /// [`Interface`]: https://docs.rs/zbus/latest/zbus/object_server/trait.Interface.html
 #[proc_macro_attribute]
 pub fn dbus_interface(attr: TokenStream, item: TokenStream) -> TokenStream {